@@ -1,6 +1,6 @@
 extern crate goblin;
 
-use goblin::mach::{self, Mach};
+use goblin::mach::{self, Mach, writer};
 use std::env;
 use std::process;
 use std::path::Path;
@@ -10,11 +10,51 @@ use std::io::{Read, Write};
 fn usage() -> ! {
     println!("usage: lipo <options> <mach-o fat file>");
     println!("    -m64              Extracts and writes the 64-bit binary in this fat container, if any");
+    println!("       lipo -create <mach-o file>... -output <mach-o fat file>");
+    println!("       lipo -replace <mach-o file> -output <mach-o fat file> <mach-o fat file>");
     process::exit(1);
 }
 
+fn read(path: &str) -> Vec<u8> {
+    let mut v = Vec::new();
+    let mut f = File::open(path).unwrap();
+    f.read_to_end(&mut v).unwrap();
+    v
+}
+
+/// Writes the fat binary `lipo -create` or `lipo -replace` asks for, if `args` is either
+fn create_or_replace(args: &[String]) -> Option<Vec<u8>> {
+    let output = match args.iter().position(|arg| arg == "-output") {
+        Some(i) if i + 1 < args.len() => i,
+        _ => return None,
+    };
+    match args[0].as_str() {
+        "-create" => {
+            let binaries = args[1..output].iter().map(|path| read(path)).collect::<Vec<_>>();
+            let binaries = binaries.iter().map(|binary| binary.as_slice()).collect::<Vec<_>>();
+            Some(writer::fat(&binaries).unwrap())
+        },
+        "-replace" if output == 2 && args.len() == 5 => {
+            Some(writer::replace_fat(&read(&args[4]), &read(&args[1])).unwrap())
+        },
+        _ => None,
+    }
+}
+
 fn main () {
     let len = env::args().len();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if len > 1 && (args[0] == "-create" || args[0] == "-replace") {
+        let output = args.iter().position(|arg| arg == "-output").map(|i| i + 1);
+        match (create_or_replace(&args), output) {
+            (Some(fat), Some(i)) => {
+                let mut file = File::create(&args[i]).unwrap();
+                file.write_all(&fat).unwrap();
+                process::exit(0);
+            },
+            _ => usage(),
+        }
+    }
 
     if len <= 1 {
         usage();
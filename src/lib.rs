@@ -45,6 +45,9 @@
 //!                 Object::PE(pe) => {
 //!                     println!("pe: {:#?}", &pe);
 //!                 },
+//!                 Object::COFF(coff) => {
+//!                     println!("coff: {:#?}", &coff);
+//!                 },
 //!                 Object::Mach(mach) => {
 //!                     println!("mach: {:#?}", &mach);
 //!                 },
@@ -208,6 +211,7 @@ if_std! {
         Mach(HintData),
         MachFat(usize),
         PE,
+        COFF,
         Archive,
         Unknown(u64),
     }
@@ -246,7 +250,13 @@ if_std! {
                     Ok(Hint::Mach(HintData { is_lsb: is_lsb, is_64: Some(is_64) }))
                 },
                 // its something else
-                _ => Ok(Hint::Unknown(bytes.pread::<u64>(0)?))
+                _ => {
+                    if pe::coff::peek(bytes) {
+                        Ok(Hint::COFF)
+                    } else {
+                        Ok(Hint::Unknown(bytes.pread::<u64>(0)?))
+                    }
+                }
             }
         }
     }
@@ -271,6 +281,8 @@ pub enum Object<'a> {
     Elf(elf::Elf<'a>),
     /// A PE32/PE32+!
     PE(pe::PE<'a>),
+    /// A COFF object file, e.g., an MSVC `.obj`
+    COFF(pe::coff::Coff<'a>),
     /// A 32/64-bit Mach-o binary _OR_ it is a multi-architecture binary container!
     Mach(mach::Mach<'a>),
     /// A Unix archive
@@ -284,11 +296,17 @@ impl<'a> Object<'a> {
     /// Tries to parse an `Object` from `bytes`
     pub fn parse(bytes: &[u8]) -> error::Result<Object> {
         use std::io::Cursor;
+        use scroll::Pread;
         match peek(&mut Cursor::new(&bytes))? {
             Hint::Elf(_) => Ok(Object::Elf(elf::Elf::parse(bytes)?)),
             Hint::Mach(_) | Hint::MachFat(_) => Ok(Object::Mach(mach::Mach::parse(bytes)?)),
             Hint::Archive => Ok(Object::Archive(archive::Archive::parse(bytes)?)),
             Hint::PE => Ok(Object::PE(pe::PE::parse(bytes)?)),
+            // the COFF header checks are a heuristic, so something which only looks like an object file is unknown
+            Hint::COFF => match pe::coff::Coff::parse(bytes) {
+                Ok(coff) => Ok(Object::COFF(coff)),
+                Err(_) => Ok(Object::Unknown(bytes.pread::<u64>(0)?)),
+            },
             Hint::Unknown(magic) => Ok(Object::Unknown(magic))
         }
     }
@@ -341,6 +359,9 @@ pub mod elf64 {
     }
 }
 
+#[cfg(feature = "std")]
+mod digest;
+
 #[cfg(all(feature = "mach32", feature = "mach64", feature = "endian_fd"))]
 pub mod mach;
 
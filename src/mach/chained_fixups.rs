@@ -0,0 +1,555 @@
+//! Chained fixups replace the rebase and bind opcodes of `LC_DYLD_INFO` in binaries targeting macOS 12 / iOS 15 and later
+//!
+//! The `LC_DYLD_CHAINED_FIXUPS` payload holds a table of imported symbols and, for every page of every segment with
+//! pointers to fix up, the offset of the first pointer in that page. Each pointer in turn encodes, besides its rebase
+//! target or the index of the import it binds to, the distance to the next pointer in the page, forming a chain.
+
+use core::ops::Range;
+use core::fmt::{self, Debug};
+use scroll::{self, Pread};
+
+use error;
+use mach::load_command;
+use mach::segment;
+use mach::imports::{Import, ImportKind};
+use mach::rebase_opcodes;
+use mach::rebases::Rebase;
+
+/// The only `fixups_version` there is
+pub const DYLD_CHAINED_FIXUPS_VERSION: u32 = 0;
+
+// values for dyld_chained_fixups_header.imports_format
+pub const DYLD_CHAINED_IMPORT: u32 = 1;
+pub const DYLD_CHAINED_IMPORT_ADDEND: u32 = 2;
+pub const DYLD_CHAINED_IMPORT_ADDEND64: u32 = 3;
+
+// values for dyld_chained_fixups_header.symbols_format
+pub const DYLD_CHAINED_SYMBOL_UNCOMPRESSED: u32 = 0;
+pub const DYLD_CHAINED_SYMBOL_ZLIB: u32 = 1;
+
+// values for dyld_chained_starts_in_segment.pointer_format
+/// stride 8, unauth target is vmaddr
+pub const DYLD_CHAINED_PTR_ARM64E: u16 = 1;
+/// target is vmaddr
+pub const DYLD_CHAINED_PTR_64: u16 = 2;
+pub const DYLD_CHAINED_PTR_32: u16 = 3;
+pub const DYLD_CHAINED_PTR_32_CACHE: u16 = 4;
+pub const DYLD_CHAINED_PTR_32_FIRMWARE: u16 = 5;
+/// target is vm offset
+pub const DYLD_CHAINED_PTR_64_OFFSET: u16 = 6;
+/// stride 4, unauth target is vm offset
+pub const DYLD_CHAINED_PTR_ARM64E_KERNEL: u16 = 7;
+pub const DYLD_CHAINED_PTR_64_KERNEL_CACHE: u16 = 8;
+/// stride 8, unauth target is vm offset
+pub const DYLD_CHAINED_PTR_ARM64E_USERLAND: u16 = 9;
+/// stride 4, unauth target is vmaddr
+pub const DYLD_CHAINED_PTR_ARM64E_FIRMWARE: u16 = 10;
+/// stride 1, x86_64 kernel caches
+pub const DYLD_CHAINED_PTR_X86_64_KERNEL_CACHE: u16 = 11;
+/// stride 8, unauth target is vm offset, 24-bit bind ordinals
+pub const DYLD_CHAINED_PTR_ARM64E_USERLAND24: u16 = 12;
+
+// values for dyld_chained_starts_in_segment.page_start
+/// no fixups in the page
+pub const DYLD_CHAINED_PTR_START_NONE: u16 = 0xffff;
+/// the page has multiple chains, whose starts are listed after the page starts; 32-bit formats only
+pub const DYLD_CHAINED_PTR_START_MULTI: u16 = 0x8000;
+/// the last chain start of a page with multiple chains
+pub const DYLD_CHAINED_PTR_START_LAST: u16 = 0x8000;
+
+/// The special library ordinal binding to the main executable
+pub const BIND_SPECIAL_DYLIB_MAIN_EXECUTABLE: i64 = -1;
+/// The special library ordinal binding to the first image exporting the symbol
+pub const BIND_SPECIAL_DYLIB_FLAT_LOOKUP: i64 = -2;
+/// The special library ordinal binding to the weak definition of the symbol, if any
+pub const BIND_SPECIAL_DYLIB_WEAK_LOOKUP: i64 = -3;
+
+/// Returns the name of the chained pointer `format`
+pub fn pointer_format_to_str(format: u16) -> &'static str {
+    match format {
+        DYLD_CHAINED_PTR_ARM64E => "DYLD_CHAINED_PTR_ARM64E",
+        DYLD_CHAINED_PTR_64 => "DYLD_CHAINED_PTR_64",
+        DYLD_CHAINED_PTR_32 => "DYLD_CHAINED_PTR_32",
+        DYLD_CHAINED_PTR_32_CACHE => "DYLD_CHAINED_PTR_32_CACHE",
+        DYLD_CHAINED_PTR_32_FIRMWARE => "DYLD_CHAINED_PTR_32_FIRMWARE",
+        DYLD_CHAINED_PTR_64_OFFSET => "DYLD_CHAINED_PTR_64_OFFSET",
+        DYLD_CHAINED_PTR_ARM64E_KERNEL => "DYLD_CHAINED_PTR_ARM64E_KERNEL",
+        DYLD_CHAINED_PTR_64_KERNEL_CACHE => "DYLD_CHAINED_PTR_64_KERNEL_CACHE",
+        DYLD_CHAINED_PTR_ARM64E_USERLAND => "DYLD_CHAINED_PTR_ARM64E_USERLAND",
+        DYLD_CHAINED_PTR_ARM64E_FIRMWARE => "DYLD_CHAINED_PTR_ARM64E_FIRMWARE",
+        DYLD_CHAINED_PTR_X86_64_KERNEL_CACHE => "DYLD_CHAINED_PTR_X86_64_KERNEL_CACHE",
+        DYLD_CHAINED_PTR_ARM64E_USERLAND24 => "DYLD_CHAINED_PTR_ARM64E_USERLAND24",
+        _ => "DYLD_CHAINED_PTR_UNKNOWN",
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pread, Pwrite, SizeWith)]
+/// The header of the `LC_DYLD_CHAINED_FIXUPS` payload
+pub struct DyldChainedFixupsHeader {
+    /// 0
+    pub fixups_version: u32,
+    /// offset of `dyld_chained_starts_in_image` in the payload
+    pub starts_offset: u32,
+    /// offset of the imports table in the payload
+    pub imports_offset: u32,
+    /// offset of the symbol strings in the payload
+    pub symbols_offset: u32,
+    /// number of imported symbol names
+    pub imports_count: u32,
+    /// `DYLD_CHAINED_IMPORT*`
+    pub imports_format: u32,
+    /// 0 => uncompressed, 1 => zlib compressed
+    pub symbols_format: u32,
+}
+
+pub const SIZEOF_DYLD_CHAINED_FIXUPS_HEADER: usize = 28;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pread, Pwrite, SizeWith)]
+/// The fixed size part of `dyld_chained_starts_in_segment`, which is followed by `page_count` page starts
+pub struct DyldChainedStartsInSegment {
+    /// size of this, including the page starts
+    pub size: u32,
+    /// 0x1000 or 0x4000
+    pub page_size: u16,
+    /// `DYLD_CHAINED_PTR_*`
+    pub pointer_format: u16,
+    /// offset in memory to the start of the segment
+    pub segment_offset: u64,
+    /// for 32-bit formats, any value above this is not a pointer
+    pub max_valid_pointer: u32,
+    /// how many pages are in the segment
+    pub page_count: u16,
+}
+
+pub const SIZEOF_DYLD_CHAINED_STARTS_IN_SEGMENT: usize = 22;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// An entry of the imports table, in any of the `DYLD_CHAINED_IMPORT*` formats
+pub struct ChainedImport {
+    /// The library ordinal; the special ordinals in the top 15 values of the field (e.g., 0xfe in the 8-bit formats) are
+    /// sign extended to be negative
+    pub lib_ordinal: i64,
+    /// Whether the import may be missing at runtime
+    pub weak_import: bool,
+    /// The offset of the symbol name in the symbol strings
+    pub name_offset: u32,
+    /// The addend, 0 for `DYLD_CHAINED_IMPORT`
+    pub addend: i64,
+}
+
+/// Sign extends the `bits` wide `value`
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// Decodes the `bits` wide library ordinal `value`: like dyld, only the top 15 values are special negative ordinals
+fn lib_ordinal(value: u64, bits: u32) -> i64 {
+    if value > (1u64 << bits) - 0x10 { sign_extend(value, bits) } else { value as i64 }
+}
+
+/// Extracts the `bits` wide field at bit `start` of `value`
+fn bits(value: u64, start: u32, bits: u32) -> u64 {
+    (value >> start) & ((1u64 << bits) - 1)
+}
+
+impl ChainedImport {
+    /// Parses the import at `offset` in `bytes` in the `DYLD_CHAINED_IMPORT*` `format`
+    pub fn parse(bytes: &[u8], offset: &mut usize, format: u32, le: scroll::Endian) -> error::Result<Self> {
+        match format {
+            DYLD_CHAINED_IMPORT | DYLD_CHAINED_IMPORT_ADDEND => {
+                let raw = bytes.gread_with::<u32>(offset, le)? as u64;
+                let addend = if format == DYLD_CHAINED_IMPORT_ADDEND { bytes.gread_with::<i32>(offset, le)? as i64 } else { 0 };
+                Ok(ChainedImport {
+                    lib_ordinal: lib_ordinal(bits(raw, 0, 8), 8),
+                    weak_import: bits(raw, 8, 1) != 0,
+                    name_offset: bits(raw, 9, 23) as u32,
+                    addend: addend,
+                })
+            },
+            DYLD_CHAINED_IMPORT_ADDEND64 => {
+                let raw = bytes.gread_with::<u64>(offset, le)?;
+                let addend = bytes.gread_with::<u64>(offset, le)? as i64;
+                Ok(ChainedImport {
+                    lib_ordinal: lib_ordinal(bits(raw, 0, 16), 16),
+                    weak_import: bits(raw, 16, 1) != 0,
+                    name_offset: bits(raw, 32, 32) as u32,
+                    addend: addend,
+                })
+            },
+            _ => Err(error::Error::Malformed(format!("Unknown chained imports format {}", format))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// The pointer authentication an arm64e pointer is signed with
+pub struct PointerAuth {
+    /// The ptrauth key: 0 => IA, 1 => IB, 2 => DA, 3 => DB
+    pub key: u8,
+    /// The extra discriminator
+    pub diversity: u16,
+    /// Whether the pointer's address is blended into the discriminator
+    pub addr_div: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A pointer decoded from a fixup chain
+pub enum ChainedPointer {
+    /// A pointer to `target`, a virtual memory address in this image
+    Rebase {
+        target: u64,
+        auth: Option<PointerAuth>,
+    },
+    /// A pointer to import `ordinal` plus `addend`
+    Bind {
+        ordinal: u32,
+        addend: i64,
+        auth: Option<PointerAuth>,
+    },
+    /// Not a pointer, e.g. a small integer in a 32-bit chain
+    None,
+}
+
+/// Decodes `raw`, a pointer in the `DYLD_CHAINED_PTR_*` `format`, into the pointer and the stride-scaled offset of the next
+/// pointer in its chain (0 if it is the last). Rebase targets which are offsets are made absolute with `image_base`.
+pub fn decode_pointer(raw: u64, format: u16, image_base: u64, max_valid_pointer: u32) -> error::Result<(ChainedPointer, u64)> {
+    let auth = |raw: u64| PointerAuth { key: bits(raw, 49, 2) as u8, diversity: bits(raw, 32, 16) as u16, addr_div: bits(raw, 48, 1) != 0 };
+    match format {
+        DYLD_CHAINED_PTR_ARM64E | DYLD_CHAINED_PTR_ARM64E_KERNEL | DYLD_CHAINED_PTR_ARM64E_USERLAND
+      | DYLD_CHAINED_PTR_ARM64E_FIRMWARE | DYLD_CHAINED_PTR_ARM64E_USERLAND24 => {
+            let stride = if format == DYLD_CHAINED_PTR_ARM64E || format == DYLD_CHAINED_PTR_ARM64E_USERLAND || format == DYLD_CHAINED_PTR_ARM64E_USERLAND24 { 8 } else { 4 };
+            let next = bits(raw, 51, 11) * stride;
+            let is_auth = bits(raw, 63, 1) != 0;
+            let is_bind = bits(raw, 62, 1) != 0;
+            let ordinal_bits = if format == DYLD_CHAINED_PTR_ARM64E_USERLAND24 { 24 } else { 16 };
+            let pointer = match (is_auth, is_bind) {
+                (false, false) => {
+                    let target = bits(raw, 0, 43) | bits(raw, 43, 8) << 56;
+                    let target = if format == DYLD_CHAINED_PTR_ARM64E || format == DYLD_CHAINED_PTR_ARM64E_FIRMWARE { target } else { image_base.wrapping_add(target) };
+                    ChainedPointer::Rebase { target: target, auth: None }
+                },
+                (false, true) => ChainedPointer::Bind { ordinal: bits(raw, 0, ordinal_bits) as u32, addend: sign_extend(bits(raw, 32, 19), 19), auth: None },
+                (true, false) => ChainedPointer::Rebase { target: image_base.wrapping_add(bits(raw, 0, 32)), auth: Some(auth(raw)) },
+                (true, true) => ChainedPointer::Bind { ordinal: bits(raw, 0, ordinal_bits) as u32, addend: 0, auth: Some(auth(raw)) },
+            };
+            Ok((pointer, next))
+        },
+        DYLD_CHAINED_PTR_64 | DYLD_CHAINED_PTR_64_OFFSET => {
+            let next = bits(raw, 51, 12) * 4;
+            let pointer = if bits(raw, 63, 1) != 0 {
+                ChainedPointer::Bind { ordinal: bits(raw, 0, 24) as u32, addend: bits(raw, 24, 8) as i64, auth: None }
+            } else {
+                let target = bits(raw, 0, 36);
+                let target = if format == DYLD_CHAINED_PTR_64_OFFSET { image_base.wrapping_add(target) } else { target };
+                ChainedPointer::Rebase { target: target | bits(raw, 36, 8) << 56, auth: None }
+            };
+            Ok((pointer, next))
+        },
+        DYLD_CHAINED_PTR_64_KERNEL_CACHE | DYLD_CHAINED_PTR_X86_64_KERNEL_CACHE => {
+            let stride = if format == DYLD_CHAINED_PTR_X86_64_KERNEL_CACHE { 1 } else { 4 };
+            let next = bits(raw, 51, 12) * stride;
+            let auth = if bits(raw, 63, 1) != 0 { Some(auth(raw)) } else { None };
+            Ok((ChainedPointer::Rebase { target: image_base.wrapping_add(bits(raw, 0, 30)), auth: auth }, next))
+        },
+        DYLD_CHAINED_PTR_32 => {
+            let next = bits(raw, 26, 5) * 4;
+            let pointer = if bits(raw, 31, 1) != 0 {
+                ChainedPointer::Bind { ordinal: bits(raw, 0, 20) as u32, addend: bits(raw, 20, 6) as i64, auth: None }
+            } else if bits(raw, 0, 26) > max_valid_pointer as u64 {
+                ChainedPointer::None
+            } else {
+                ChainedPointer::Rebase { target: bits(raw, 0, 26), auth: None }
+            };
+            Ok((pointer, next))
+        },
+        DYLD_CHAINED_PTR_32_CACHE => Ok((ChainedPointer::Rebase { target: image_base.wrapping_add(bits(raw, 0, 30)), auth: None }, bits(raw, 30, 2) * 4)),
+        DYLD_CHAINED_PTR_32_FIRMWARE => Ok((ChainedPointer::Rebase { target: bits(raw, 0, 26), auth: None }, bits(raw, 26, 6) * 4)),
+        _ => Err(error::Error::Malformed(format!("Unknown chained pointer format {}", format))),
+    }
+}
+
+/// The size of a pointer in the `DYLD_CHAINED_PTR_*` `format`
+fn pointer_size(format: u16) -> usize {
+    match format {
+        DYLD_CHAINED_PTR_32 | DYLD_CHAINED_PTR_32_CACHE | DYLD_CHAINED_PTR_32_FIRMWARE => 4,
+        _ => 8,
+    }
+}
+
+/// Returns the name of the library `lib_ordinal` refers to, using `libs` for positive ordinals
+fn dylib<'a>(libs: &[&'a str], lib_ordinal: i64) -> error::Result<&'a str> {
+    match lib_ordinal {
+        BIND_SPECIAL_DYLIB_MAIN_EXECUTABLE => Ok("main executable"),
+        BIND_SPECIAL_DYLIB_FLAT_LOOKUP => Ok("flat lookup"),
+        BIND_SPECIAL_DYLIB_WEAK_LOOKUP => Ok("weak lookup"),
+        ordinal if ordinal >= 0 && (ordinal as usize) < libs.len() => Ok(libs[ordinal as usize]),
+        ordinal => Err(error::Error::Malformed(format!("Chained import library ordinal {} is out of range for {} libraries", ordinal, libs.len()))),
+    }
+}
+
+/// The fixup chains of `LC_DYLD_CHAINED_FIXUPS`, which are walked lazily to compute the imports and rebases
+pub struct ChainedFixups<'a> {
+    data: &'a [u8],
+    location: Range<usize>,
+    le: scroll::Endian,
+}
+
+impl<'a> Debug for ChainedFixups<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "ChainedFixups {{")?;
+        writeln!(fmt, "  Location: {:#x}..{:#x}", self.location.start, self.location.end)?;
+        writeln!(fmt, "}}")
+    }
+}
+
+impl<'a> ChainedFixups<'a> {
+    /// Construct the chained fixups from `bytes` and the `LC_DYLD_CHAINED_FIXUPS` `command`
+    pub fn new(bytes: &'a [u8], command: &load_command::LinkeditDataCommand, le: scroll::Endian) -> Self {
+        let start = command.dataoff as usize;
+        ChainedFixups {
+            data: bytes,
+            location: start..start + command.datasize as usize,
+            le: le,
+        }
+    }
+    fn payload(&self) -> error::Result<&'a [u8]> {
+        Ok(self.data.pread_with::<&[u8]>(self.location.start, self.location.end - self.location.start)?)
+    }
+    /// The header of the payload
+    pub fn header(&self) -> error::Result<DyldChainedFixupsHeader> {
+        let header = self.payload()?.pread_with::<DyldChainedFixupsHeader>(0, self.le)?;
+        if header.fixups_version != DYLD_CHAINED_FIXUPS_VERSION {
+            return Err(error::Error::Malformed(format!("Unknown chained fixups version {}", header.fixups_version)));
+        }
+        Ok(header)
+    }
+    /// The imports table, in the order bind pointers refer to it
+    pub fn chained_imports(&self) -> error::Result<Vec<ChainedImport>> {
+        let header = self.header()?;
+        let payload = self.payload()?;
+        let offset = &mut (header.imports_offset as usize);
+        let mut imports = Vec::new();
+        for _ in 0..header.imports_count {
+            imports.push(ChainedImport::parse(payload, offset, header.imports_format, self.le)?);
+        }
+        Ok(imports)
+    }
+    /// Returns the name of `import`
+    pub fn symbol_name(&self, import: &ChainedImport) -> error::Result<&'a str> {
+        let header = self.header()?;
+        if header.symbols_format != DYLD_CHAINED_SYMBOL_UNCOMPRESSED {
+            return Err(error::Error::Malformed(format!("Unsupported chained fixups symbols format {}", header.symbols_format)));
+        }
+        Ok(self.payload()?.pread::<&str>(header.symbols_offset as usize + import.name_offset as usize)?)
+    }
+    /// Returns the starts of every segment with fixups: the segment index, its `dyld_chained_starts_in_segment`, and the
+    /// offsets of each page's chains
+    pub fn starts(&self) -> error::Result<Vec<(usize, DyldChainedStartsInSegment, Vec<Vec<u16>>)>> {
+        let header = self.header()?;
+        let payload = self.payload()?;
+        let offset = &mut (header.starts_offset as usize);
+        let seg_count = payload.gread_with::<u32>(offset, self.le)?;
+        let mut starts = Vec::new();
+        for i in 0..seg_count as usize {
+            let seg_info_offset = payload.gread_with::<u32>(offset, self.le)?;
+            if seg_info_offset == 0 { continue }
+            let start = header.starts_offset as usize + seg_info_offset as usize;
+            let segment = payload.pread_with::<DyldChainedStartsInSegment>(start, self.le)?;
+            let page_starts = start + SIZEOF_DYLD_CHAINED_STARTS_IN_SEGMENT;
+            let mut pages = Vec::new();
+            for page in 0..segment.page_count as usize {
+                let page_start = payload.pread_with::<u16>(page_starts + page * 2, self.le)?;
+                let mut chains = Vec::new();
+                if page_start == DYLD_CHAINED_PTR_START_NONE {
+                } else if page_start & DYLD_CHAINED_PTR_START_MULTI != 0 {
+                    let mut index = (page_start & !DYLD_CHAINED_PTR_START_MULTI) as usize;
+                    loop {
+                        let chain_start = payload.pread_with::<u16>(page_starts + index * 2, self.le)?;
+                        chains.push(chain_start & !DYLD_CHAINED_PTR_START_LAST);
+                        if chain_start & DYLD_CHAINED_PTR_START_LAST != 0 { break }
+                        index += 1;
+                    }
+                } else {
+                    chains.push(page_start);
+                }
+                pages.push(chains);
+            }
+            starts.push((i, segment, pages));
+        }
+        Ok(starts)
+    }
+    /// Walks every fixup chain, calling `f` with the segment index, offset in the segment, file offset, virtual memory
+    /// address, size and decoded value of every pointer
+    fn walk<F: FnMut(usize, u64, u64, u64, usize, ChainedPointer) -> error::Result<()>>(&self, segments: &[segment::Segment], mut f: F) -> error::Result<()> {
+        let image_base = segments.iter().find(|segment| segment.fileoff == 0 && segment.filesize != 0).map_or(0, |segment| segment.vmaddr);
+        for (index, starts, pages) in self.starts()? {
+            let segment = match segments.get(index) {
+                Some(segment) => segment,
+                None => return Err(error::Error::Malformed(format!("Chained fixups for segment {} but there are only {} segments", index, segments.len()))),
+            };
+            let size = pointer_size(starts.pointer_format);
+            for (page, chains) in pages.iter().enumerate() {
+                for chain in chains {
+                    let mut offset = page as u64 * starts.page_size as u64 + *chain as u64;
+                    loop {
+                        let (file_offset, address) = match (segment.fileoff.checked_add(offset), segment.vmaddr.checked_add(offset)) {
+                            (Some(file_offset), Some(address)) => (file_offset, address),
+                            _ => return Err(error::Error::Malformed(format!("Fixup chain at offset {:#x} of segment {} overflows", offset, index))),
+                        };
+                        let raw = if size == 8 {
+                            self.data.pread_with::<u64>(file_offset as usize, self.le)?
+                        } else {
+                            self.data.pread_with::<u32>(file_offset as usize, self.le)? as u64
+                        };
+                        let (pointer, next) = decode_pointer(raw, starts.pointer_format, image_base, starts.max_valid_pointer)?;
+                        f(index, offset, file_offset, address, size, pointer)?;
+                        if next == 0 { break }
+                        offset += next;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Return the imports bound by the fixup chains, resolving library ordinals with `libs`
+    pub fn imports(&self, libs: &[&'a str], segments: &[segment::Segment]) -> error::Result<Vec<Import<'a>>> {
+        let header = self.header()?;
+        let chained_imports = self.chained_imports()?;
+        let import_size = match header.imports_format { DYLD_CHAINED_IMPORT => 4, DYLD_CHAINED_IMPORT_ADDEND => 8, _ => 16 };
+        let imports_start = self.location.start as u64 + header.imports_offset as u64;
+        let mut imports = Vec::new();
+        self.walk(segments, |_, _, offset, address, size, pointer| {
+            if let ChainedPointer::Bind { ordinal, addend, .. } = pointer {
+                let import = match chained_imports.get(ordinal as usize) {
+                    Some(import) => import,
+                    None => return Err(error::Error::Malformed(format!("Chained bind at {:#x} refers to import {} of {}", offset, ordinal, chained_imports.len()))),
+                };
+                imports.push(Import {
+                    name: self.symbol_name(import)?,
+                    dylib: dylib(libs, import.lib_ordinal)?,
+                    is_lazy: false,
+                    kind: ImportKind::NonLazy,
+                    offset: offset,
+                    size: size,
+                    address: address,
+                    addend: import.addend.wrapping_add(addend),
+                    is_weak: import.weak_import,
+                    start_of_sequence_offset: imports_start + ordinal as u64 * import_size,
+                });
+            }
+            Ok(())
+        })?;
+        Ok(imports)
+    }
+    /// Return the pointers the fixup chains rebase
+    pub fn rebases(&self, segments: &[segment::Segment]) -> error::Result<Vec<Rebase>> {
+        let mut rebases = Vec::new();
+        self.walk(segments, |seg_index, seg_offset, offset, address, size, pointer| {
+            if let ChainedPointer::Rebase { target, auth } = pointer {
+                rebases.push(Rebase {
+                    seg_index: seg_index,
+                    seg_offset: seg_offset,
+                    rebase_type: rebase_opcodes::REBASE_TYPE_POINTER,
+                    offset: offset,
+                    address: address,
+                    target: target,
+                    size: size,
+                    auth: auth,
+                });
+            }
+            Ok(())
+        })?;
+        Ok(rebases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+    use container;
+
+    #[test]
+    fn import_lib_ordinals() {
+        let le = scroll::LE;
+        let mut bytes = [0u8; 16];
+        for &(raw, ordinal) in &[(0x01u32, 1i64), (0x80, 0x80), (0xf0, 0xf0), (0xfe, -2), (0xff, -1)] {
+            bytes.pwrite_with(raw | 0x1000, 0, le).unwrap();
+            let import = ChainedImport::parse(&bytes, &mut 0, DYLD_CHAINED_IMPORT, le).unwrap();
+            assert_eq!(import.lib_ordinal, ordinal);
+            assert_eq!(import.name_offset, 8);
+        }
+        for &(raw, ordinal) in &[(0x80u64, 0x80i64), (0xfff0, 0xfff0), (0xfffd, -3)] {
+            bytes.pwrite_with(raw, 0, le).unwrap();
+            let import = ChainedImport::parse(&bytes, &mut 0, DYLD_CHAINED_IMPORT_ADDEND64, le).unwrap();
+            assert_eq!(import.lib_ordinal, ordinal);
+        }
+    }
+
+    #[test]
+    fn decode_arm64e() {
+        // auth rebase to offset 0x4000, key DA, diversity 0x1234, next 2
+        let raw = 1 << 63 | 2 << 51 | 2 << 49 | 0x1234 << 32 | 0x4000;
+        let (pointer, next) = decode_pointer(raw, DYLD_CHAINED_PTR_ARM64E, 0x1_0000_0000, 0).unwrap();
+        assert_eq!(next, 16);
+        assert_eq!(pointer, ChainedPointer::Rebase { target: 0x1_0000_4000, auth: Some(PointerAuth { key: 2, diversity: 0x1234, addr_div: false }) });
+        // bind to import 3 with addend -8
+        let raw = 1 << 62 | (0x7fff8 << 32) | 3;
+        let (pointer, next) = decode_pointer(raw, DYLD_CHAINED_PTR_ARM64E_USERLAND24, 0, 0).unwrap();
+        assert_eq!(next, 0);
+        assert_eq!(pointer, ChainedPointer::Bind { ordinal: 3, addend: -8, auth: None });
+    }
+
+    #[test]
+    fn walk_chains() {
+        let le = scroll::LE;
+        let mut bytes = vec![0u8; 0x200];
+        // __DATA at 0x100: a rebase to 0x1_0000_0010 then, 8 bytes later, a bind to import 0
+        bytes.pwrite_with::<u64>(2 << 51 | 0x10, 0x100, le).unwrap();
+        bytes.pwrite_with::<u64>(1 << 63, 0x108, le).unwrap();
+        let payload = 0x180;
+        let header = DyldChainedFixupsHeader { fixups_version: 0, starts_offset: 28, imports_offset: 64, symbols_offset: 68, imports_count: 1, imports_format: DYLD_CHAINED_IMPORT, symbols_format: 0 };
+        bytes.pwrite_with(header, payload, le).unwrap();
+        // two segments, only the second has fixups
+        bytes.pwrite_with::<u32>(2, payload + 28, le).unwrap();
+        bytes.pwrite_with::<u32>(0, payload + 32, le).unwrap();
+        bytes.pwrite_with::<u32>(12, payload + 36, le).unwrap();
+        let starts = DyldChainedStartsInSegment { size: 24, page_size: 0x1000, pointer_format: DYLD_CHAINED_PTR_64_OFFSET, segment_offset: 0x100, max_valid_pointer: 0, page_count: 1 };
+        bytes.pwrite_with(starts, payload + 40, le).unwrap();
+        bytes.pwrite_with::<u16>(0, payload + 62, le).unwrap();
+        // weak import of "_foo" from library 1
+        bytes.pwrite_with::<u32>(1 | 1 << 8 | 1 << 9, payload + 64, le).unwrap();
+        bytes[payload + 68..payload + 74].copy_from_slice(b"\0_foo\0");
+        let command = load_command::LinkeditDataCommand { cmd: load_command::LC_DYLD_CHAINED_FIXUPS, cmdsize: 16, dataoff: payload as u32, datasize: 0x80 };
+        let fixups = ChainedFixups::new(&bytes, &command, le);
+
+        let ctx = container::Ctx { container: container::Container::Big, le: le };
+        let mut text = segment::Segment::new(ctx, &[]);
+        text.vmaddr = 0x1_0000_0000;
+        text.filesize = 0x100;
+        let mut data = segment::Segment::new(ctx, &[]);
+        data.vmaddr = 0x1_0000_0100;
+        data.fileoff = 0x100;
+        data.filesize = 0x100;
+        let mut segments = [text, data];
+
+        let rebases = fixups.rebases(&segments).unwrap();
+        assert_eq!(rebases, vec![Rebase { seg_index: 1, seg_offset: 0, rebase_type: rebase_opcodes::REBASE_TYPE_POINTER, offset: 0x100, address: 0x1_0000_0100, target: 0x1_0000_0010, size: 8, auth: None }]);
+        let imports = fixups.imports(&["self", "/usr/lib/libSystem.B.dylib"], &segments).unwrap();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "_foo");
+        assert_eq!(imports[0].dylib, "/usr/lib/libSystem.B.dylib");
+        assert_eq!(imports[0].address, 0x1_0000_0108);
+        assert!(imports[0].is_weak);
+
+        // a segment whose file offset or address the chain offset overflows
+        segments[1].fileoff = u64::max_value();
+        assert!(fixups.rebases(&segments).is_err());
+        segments[1].fileoff = 0x100;
+        segments[1].vmaddr = u64::max_value();
+        assert!(fixups.rebases(&segments).is_err());
+    }
+}
@@ -0,0 +1,504 @@
+//! The embedded code signature `LC_CODE_SIGNATURE` points to
+//!
+//! The signature is a "SuperBlob": an index of typed blobs, each starting with a magic and its length. The most
+//! important is the CodeDirectory, which holds a hash of every page of the binary up to its code limit, plus hashes of
+//! the other blobs (the "special slots"), e.g., the requirements and entitlements. The hash of the CodeDirectory
+//! itself, the cdhash, identifies the signed binary; the CMS signature blob in turn signs the CodeDirectory.
+//!
+//! Unlike the rest of the binary, every field of the signature is big endian.
+
+use core::cmp;
+use core::ops::Range;
+use core::fmt::{self, Debug};
+use scroll::{self, Pread};
+
+use digest;
+use error;
+use mach::load_command;
+
+/// A single requirement, in the requirement language bytecode
+pub const CSMAGIC_REQUIREMENT: u32 = 0xfade0c00;
+/// A vector of typed requirements
+pub const CSMAGIC_REQUIREMENTS: u32 = 0xfade0c01;
+/// A CodeDirectory
+pub const CSMAGIC_CODEDIRECTORY: u32 = 0xfade0c02;
+/// The SuperBlob of an embedded signature
+pub const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xfade0cc0;
+/// The SuperBlob of a detached signature, for multiple architectures
+pub const CSMAGIC_DETACHED_SIGNATURE: u32 = 0xfade0cc1;
+/// The entitlements, as an XML property list
+pub const CSMAGIC_EMBEDDED_ENTITLEMENTS: u32 = 0xfade7171;
+/// The entitlements, as DER
+pub const CSMAGIC_EMBEDDED_DER_ENTITLEMENTS: u32 = 0xfade7172;
+/// A wrapper around opaque data, i.e., the CMS signature
+pub const CSMAGIC_BLOBWRAPPER: u32 = 0xfade0b01;
+
+/// The slot of the CodeDirectory
+pub const CSSLOT_CODEDIRECTORY: u32 = 0;
+/// The special slot of the hash of the `Info.plist`
+pub const CSSLOT_INFOSLOT: u32 = 1;
+/// The slot of the requirements
+pub const CSSLOT_REQUIREMENTS: u32 = 2;
+/// The special slot of the hash of the resource directory
+pub const CSSLOT_RESOURCEDIR: u32 = 3;
+/// The special slot of the hash of application specific data
+pub const CSSLOT_APPLICATION: u32 = 4;
+/// The slot of the XML entitlements
+pub const CSSLOT_ENTITLEMENTS: u32 = 5;
+/// The slot of the DER entitlements
+pub const CSSLOT_DER_ENTITLEMENTS: u32 = 7;
+/// The slot of the first CodeDirectory using another hash type
+pub const CSSLOT_ALTERNATE_CODEDIRECTORIES: u32 = 0x1000;
+/// How many alternate CodeDirectories there may be
+pub const CSSLOT_ALTERNATE_CODEDIRECTORY_MAX: u32 = 5;
+/// The slot of the CMS signature
+pub const CSSLOT_SIGNATURESLOT: u32 = 0x10000;
+
+pub const CS_HASHTYPE_SHA1: u8 = 1;
+pub const CS_HASHTYPE_SHA256: u8 = 2;
+pub const CS_HASHTYPE_SHA256_TRUNCATED: u8 = 3;
+pub const CS_HASHTYPE_SHA384: u8 = 4;
+
+/// The length a cdhash is truncated to
+pub const CS_CDHASH_LEN: usize = 20;
+
+/// The CodeDirectory `version` introducing `scatter_offset`
+pub const CS_SUPPORTSSCATTER: u32 = 0x20100;
+/// The CodeDirectory `version` introducing `team_offset`
+pub const CS_SUPPORTSTEAMID: u32 = 0x20200;
+/// The CodeDirectory `version` introducing `code_limit64`
+pub const CS_SUPPORTSCODELIMIT64: u32 = 0x20300;
+/// The CodeDirectory `version` introducing the executable segment fields
+pub const CS_SUPPORTSEXECSEG: u32 = 0x20400;
+/// The CodeDirectory `version` introducing `runtime` and `pre_encrypt_offset`
+pub const CS_SUPPORTSRUNTIME: u32 = 0x20500;
+
+/// The binary is signed without an identity, only its cdhash identifies it
+pub const CS_ADHOC: u32 = 0x2;
+/// Do not load invalid pages
+pub const CS_HARD: u32 = 0x100;
+/// Kill the process if it becomes invalid
+pub const CS_KILL: u32 = 0x200;
+/// Check the expiration of the signing certificates
+pub const CS_CHECK_EXPIRATION: u32 = 0x400;
+/// Restrict dyld loading
+pub const CS_RESTRICT: u32 = 0x800;
+/// Enforce code signing
+pub const CS_ENFORCEMENT: u32 = 0x1000;
+/// Require library validation
+pub const CS_REQUIRE_LV: u32 = 0x2000;
+/// Apply the hardened runtime
+pub const CS_RUNTIME: u32 = 0x10000;
+/// The signature was automatically applied by the linker
+pub const CS_LINKER_SIGNED: u32 = 0x20000;
+
+/// The host requirement
+pub const K_SEC_HOST_REQUIREMENT_TYPE: u32 = 1;
+/// The guest requirement
+pub const K_SEC_GUEST_REQUIREMENT_TYPE: u32 = 2;
+/// The designated requirement
+pub const K_SEC_DESIGNATED_REQUIREMENT_TYPE: u32 = 3;
+/// The library requirement
+pub const K_SEC_LIBRARY_REQUIREMENT_TYPE: u32 = 4;
+/// The plugin requirement
+pub const K_SEC_PLUGIN_REQUIREMENT_TYPE: u32 = 5;
+
+/// Returns the name of the `CSMAGIC_*` `magic`
+pub fn magic_to_str(magic: u32) -> &'static str {
+    match magic {
+        CSMAGIC_REQUIREMENT => "CSMAGIC_REQUIREMENT",
+        CSMAGIC_REQUIREMENTS => "CSMAGIC_REQUIREMENTS",
+        CSMAGIC_CODEDIRECTORY => "CSMAGIC_CODEDIRECTORY",
+        CSMAGIC_EMBEDDED_SIGNATURE => "CSMAGIC_EMBEDDED_SIGNATURE",
+        CSMAGIC_DETACHED_SIGNATURE => "CSMAGIC_DETACHED_SIGNATURE",
+        CSMAGIC_EMBEDDED_ENTITLEMENTS => "CSMAGIC_EMBEDDED_ENTITLEMENTS",
+        CSMAGIC_EMBEDDED_DER_ENTITLEMENTS => "CSMAGIC_EMBEDDED_DER_ENTITLEMENTS",
+        CSMAGIC_BLOBWRAPPER => "CSMAGIC_BLOBWRAPPER",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Returns the name of the `CS_HASHTYPE_*` `hash_type`
+pub fn hash_type_to_str(hash_type: u8) -> &'static str {
+    match hash_type {
+        CS_HASHTYPE_SHA1 => "CS_HASHTYPE_SHA1",
+        CS_HASHTYPE_SHA256 => "CS_HASHTYPE_SHA256",
+        CS_HASHTYPE_SHA256_TRUNCATED => "CS_HASHTYPE_SHA256_TRUNCATED",
+        CS_HASHTYPE_SHA384 => "CS_HASHTYPE_SHA384",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Hashes `data` with the `CS_HASHTYPE_*` `hash_type`, as the page and special slot hashes are
+pub fn hash(hash_type: u8, data: &[u8]) -> error::Result<Vec<u8>> {
+    match hash_type {
+        CS_HASHTYPE_SHA1 => Ok(digest::sha1(data).to_vec()),
+        CS_HASHTYPE_SHA256 => Ok(digest::sha256(data).to_vec()),
+        CS_HASHTYPE_SHA256_TRUNCATED => Ok(digest::sha256(data)[..CS_CDHASH_LEN].to_vec()),
+        CS_HASHTYPE_SHA384 => Ok(digest::sha384(data).to_vec()),
+        _ => Err(error::Error::Malformed(format!("Unknown code signature hash type {}", hash_type))),
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pread, Pwrite, SizeWith)]
+/// The header every blob starts with
+pub struct BlobHeader {
+    /// `CSMAGIC_*`
+    pub magic: u32,
+    /// the length of the blob, including this header
+    pub length: u32,
+}
+
+pub const SIZEOF_BLOB_HEADER: usize = 8;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, Pread, Pwrite, SizeWith)]
+/// An entry of the index of a SuperBlob
+pub struct BlobIndex {
+    /// `CSSLOT_*`
+    pub typ: u32,
+    /// offset of the blob in the SuperBlob
+    pub offset: u32,
+}
+
+pub const SIZEOF_BLOB_INDEX: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A blob of a SuperBlob
+pub struct Blob<'a> {
+    /// The slot of this blob in the SuperBlob, `CSSLOT_*` or a `K_SEC_*_REQUIREMENT_TYPE`
+    pub slot: u32,
+    /// `CSMAGIC_*`
+    pub magic: u32,
+    /// The whole blob, including its header, which is what special slots hash
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Blob<'a> {
+    /// The blob following its header
+    pub fn payload(&self) -> &'a [u8] {
+        &self.bytes[SIZEOF_BLOB_HEADER..]
+    }
+}
+
+/// Parses the blob at `offset` of `bytes`
+fn blob<'a>(bytes: &'a [u8], offset: usize, slot: u32) -> error::Result<Blob<'a>> {
+    let header = bytes.pread_with::<BlobHeader>(offset, scroll::BE)?;
+    if (header.length as usize) < SIZEOF_BLOB_HEADER {
+        return Err(error::Error::Malformed(format!("Code signature blob at {:#x} has length {}, less than its header", offset, header.length)));
+    }
+    Ok(Blob {
+        slot: slot,
+        magic: header.magic,
+        bytes: bytes.pread_with::<&[u8]>(offset, header.length as usize)?,
+    })
+}
+
+/// Parses the index of the SuperBlob `bytes`, whose magic must be `magic`
+fn super_blob<'a>(bytes: &'a [u8], magic: u32) -> error::Result<Vec<Blob<'a>>> {
+    let offset = &mut 0;
+    let header = bytes.gread_with::<BlobHeader>(offset, scroll::BE)?;
+    if header.magic != magic {
+        return Err(error::Error::Malformed(format!("Expected {} but found code signature magic {:#x}", magic_to_str(magic), header.magic)));
+    }
+    let bytes = bytes.pread_with::<&[u8]>(0, header.length as usize)?;
+    let count = bytes.gread_with::<u32>(offset, scroll::BE)? as usize;
+    let mut blobs = Vec::with_capacity(cmp::min(count, bytes.len() / SIZEOF_BLOB_INDEX));
+    for _ in 0..count {
+        let index = bytes.gread_with::<BlobIndex>(offset, scroll::BE)?;
+        blobs.push(blob(bytes, index.offset as usize, index.typ)?);
+    }
+    Ok(blobs)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A CodeDirectory, where the fields its `version` does not support are 0
+pub struct CodeDirectory<'a> {
+    /// `CS_SUPPORTS*`, the version of the layout of this CodeDirectory
+    pub version: u32,
+    /// `CS_*` flags, e.g., `CS_ADHOC`
+    pub flags: u32,
+    /// offset of the hash of the first page
+    pub hash_offset: u32,
+    /// offset of the identifier
+    pub ident_offset: u32,
+    /// how many special slots precede the page hashes
+    pub n_special_slots: u32,
+    /// how many pages are hashed
+    pub n_code_slots: u32,
+    /// how many bytes of the binary are hashed, if it is less than 4GiB
+    pub code_limit: u32,
+    /// the size of each hash
+    pub hash_size: u8,
+    /// `CS_HASHTYPE_*`
+    pub hash_type: u8,
+    /// the platform identifier, if this is a platform binary
+    pub platform: u8,
+    /// the log2 of the page size, or 0 if the binary is hashed as a single page
+    pub page_size: u8,
+    /// offset of the scatter vector
+    pub scatter_offset: u32,
+    /// offset of the team identifier
+    pub team_offset: u32,
+    /// how many bytes of the binary are hashed, if it is 4GiB or more
+    pub code_limit64: u64,
+    /// file offset of the executable segment
+    pub exec_seg_base: u64,
+    /// size of the executable segment
+    pub exec_seg_limit: u64,
+    /// `CS_EXECSEG_*` flags of the executable segment
+    pub exec_seg_flags: u64,
+    /// the SDK version the hardened runtime behaves as
+    pub runtime: u32,
+    /// offset of the hashes of the binary before it is encrypted
+    pub pre_encrypt_offset: u32,
+    bytes: &'a [u8],
+}
+
+impl<'a> CodeDirectory<'a> {
+    /// Parses the CodeDirectory `blob`
+    pub fn parse(blob: &Blob<'a>) -> error::Result<Self> {
+        if blob.magic != CSMAGIC_CODEDIRECTORY {
+            return Err(error::Error::Malformed(format!("Expected a CodeDirectory but found {} in slot {:#x}", magic_to_str(blob.magic), blob.slot)));
+        }
+        let bytes = blob.bytes;
+        let be = scroll::BE;
+        let mut offset = SIZEOF_BLOB_HEADER;
+        let offset = &mut offset;
+        let mut directory = CodeDirectory {
+            version: bytes.gread_with(offset, be)?,
+            flags: bytes.gread_with(offset, be)?,
+            hash_offset: bytes.gread_with(offset, be)?,
+            ident_offset: bytes.gread_with(offset, be)?,
+            n_special_slots: bytes.gread_with(offset, be)?,
+            n_code_slots: bytes.gread_with(offset, be)?,
+            code_limit: bytes.gread_with(offset, be)?,
+            hash_size: bytes.gread_with(offset, be)?,
+            hash_type: bytes.gread_with(offset, be)?,
+            platform: bytes.gread_with(offset, be)?,
+            page_size: bytes.gread_with(offset, be)?,
+            scatter_offset: 0,
+            team_offset: 0,
+            code_limit64: 0,
+            exec_seg_base: 0,
+            exec_seg_limit: 0,
+            exec_seg_flags: 0,
+            runtime: 0,
+            pre_encrypt_offset: 0,
+            bytes: bytes,
+        };
+        // spare2
+        *offset += 4;
+        if directory.version >= CS_SUPPORTSSCATTER {
+            directory.scatter_offset = bytes.gread_with(offset, be)?;
+        }
+        if directory.version >= CS_SUPPORTSTEAMID {
+            directory.team_offset = bytes.gread_with(offset, be)?;
+        }
+        if directory.version >= CS_SUPPORTSCODELIMIT64 {
+            // spare3
+            *offset += 4;
+            directory.code_limit64 = bytes.gread_with(offset, be)?;
+        }
+        if directory.version >= CS_SUPPORTSEXECSEG {
+            directory.exec_seg_base = bytes.gread_with(offset, be)?;
+            directory.exec_seg_limit = bytes.gread_with(offset, be)?;
+            directory.exec_seg_flags = bytes.gread_with(offset, be)?;
+        }
+        if directory.version >= CS_SUPPORTSRUNTIME {
+            directory.runtime = bytes.gread_with(offset, be)?;
+            directory.pre_encrypt_offset = bytes.gread_with(offset, be)?;
+        }
+        Ok(directory)
+    }
+    /// The whole CodeDirectory blob
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// The identifier of the signed binary, e.g., its bundle identifier
+    pub fn identifier(&self) -> error::Result<&'a str> {
+        Ok(self.bytes.pread::<&str>(self.ident_offset as usize)?)
+    }
+    /// The team identifier of the signing certificate, if any
+    pub fn team_id(&self) -> error::Result<Option<&'a str>> {
+        if self.version < CS_SUPPORTSTEAMID || self.team_offset == 0 {
+            return Ok(None)
+        }
+        Ok(Some(self.bytes.pread::<&str>(self.team_offset as usize)?))
+    }
+    /// How many bytes of the binary are hashed
+    pub fn code_limit(&self) -> u64 {
+        if self.code_limit64 != 0 { self.code_limit64 } else { self.code_limit as u64 }
+    }
+    /// The size of the hashed pages, or 0 if the binary is hashed as a single page
+    pub fn page_size(&self) -> usize {
+        if self.page_size == 0 { 0 } else { 1 << self.page_size }
+    }
+    /// The hash of every page of the binary, in order
+    pub fn page_hashes(&self) -> error::Result<Vec<&'a [u8]>> {
+        let size = self.hash_size as usize;
+        let mut hashes = Vec::with_capacity(cmp::min(self.n_code_slots as usize, self.bytes.len() / cmp::max(size, 1)));
+        for i in 0..self.n_code_slots as usize {
+            hashes.push(self.bytes.pread_with::<&[u8]>(self.hash_offset as usize + i * size, size)?);
+        }
+        Ok(hashes)
+    }
+    /// The hash in the special `slot`, e.g., `CSSLOT_ENTITLEMENTS`, which precede the page hashes in reverse order
+    pub fn special_slot_hash(&self, slot: u32) -> error::Result<Option<&'a [u8]>> {
+        if slot == 0 || slot > self.n_special_slots {
+            return Ok(None)
+        }
+        let size = self.hash_size as usize;
+        let offset = (self.hash_offset as usize).checked_sub(slot as usize * size)
+            .ok_or_else(|| error::Error::Malformed(format!("Special slot {} is before the start of the CodeDirectory", slot)))?;
+        Ok(Some(self.bytes.pread_with::<&[u8]>(offset, size)?))
+    }
+    /// Hashes `data` with the hash type of this CodeDirectory
+    pub fn hash(&self, data: &[u8]) -> error::Result<Vec<u8>> {
+        hash(self.hash_type, data)
+    }
+    /// The cdhash, i.e., the hash of this CodeDirectory truncated to 20 bytes, which identifies the signed binary
+    pub fn cdhash(&self) -> error::Result<[u8; CS_CDHASH_LEN]> {
+        let hash = self.hash(self.bytes)?;
+        let mut cdhash = [0; CS_CDHASH_LEN];
+        cdhash.copy_from_slice(&hash[..CS_CDHASH_LEN]);
+        Ok(cdhash)
+    }
+}
+
+/// The embedded code signature `LC_CODE_SIGNATURE` points to, whose blobs are parsed lazily
+pub struct CodeSignature<'a> {
+    data: &'a [u8],
+    location: Range<usize>,
+}
+
+impl<'a> Debug for CodeSignature<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "CodeSignature {{")?;
+        writeln!(fmt, "  Location: {:#x}..{:#x}", self.location.start, self.location.end)?;
+        writeln!(fmt, "}}")
+    }
+}
+
+impl<'a> CodeSignature<'a> {
+    /// Construct a new code signature from `bytes` and the `LC_CODE_SIGNATURE` load `command`
+    pub fn new(bytes: &'a [u8], command: &load_command::LinkeditDataCommand) -> Self {
+        let start = command.dataoff as usize;
+        CodeSignature {
+            data: bytes,
+            location: start..start + command.datasize as usize,
+        }
+    }
+    /// The bytes of the signature, which may be padded after the SuperBlob
+    pub fn bytes(&self) -> error::Result<&'a [u8]> {
+        Ok(self.data.pread_with::<&[u8]>(self.location.start, self.location.end - self.location.start)?)
+    }
+    /// Every blob of the SuperBlob, in index order
+    pub fn blobs(&self) -> error::Result<Vec<Blob<'a>>> {
+        super_blob(self.bytes()?, CSMAGIC_EMBEDDED_SIGNATURE)
+    }
+    /// The blob in `slot`, if any
+    pub fn blob(&self, slot: u32) -> error::Result<Option<Blob<'a>>> {
+        Ok(self.blobs()?.into_iter().find(|blob| blob.slot == slot))
+    }
+    /// The CodeDirectory, if any
+    pub fn code_directory(&self) -> error::Result<Option<CodeDirectory<'a>>> {
+        match self.blob(CSSLOT_CODEDIRECTORY)? {
+            Some(blob) => Ok(Some(CodeDirectory::parse(&blob)?)),
+            None => Ok(None),
+        }
+    }
+    /// Every CodeDirectory, i.e., the primary one followed by the alternates using other hash types
+    pub fn code_directories(&self) -> error::Result<Vec<CodeDirectory<'a>>> {
+        let alternates = CSSLOT_ALTERNATE_CODEDIRECTORIES..CSSLOT_ALTERNATE_CODEDIRECTORIES + CSSLOT_ALTERNATE_CODEDIRECTORY_MAX;
+        let mut directories = Vec::new();
+        for blob in self.blobs()? {
+            if blob.slot == CSSLOT_CODEDIRECTORY || (blob.slot >= alternates.start && blob.slot < alternates.end) {
+                directories.push(CodeDirectory::parse(&blob)?);
+            }
+        }
+        Ok(directories)
+    }
+    /// The requirements, as the `K_SEC_*_REQUIREMENT_TYPE` slot and the requirement bytecode blob
+    pub fn requirements(&self) -> error::Result<Vec<Blob<'a>>> {
+        match self.blob(CSSLOT_REQUIREMENTS)? {
+            Some(blob) => super_blob(blob.bytes, CSMAGIC_REQUIREMENTS),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// The entitlements, as an XML property list, if any
+    pub fn entitlements(&self) -> error::Result<Option<&'a str>> {
+        match self.blob(CSSLOT_ENTITLEMENTS)? {
+            Some(blob) => Ok(Some(blob.payload().pread_with::<&str>(0, scroll::ctx::StrCtx::Length(blob.payload().len()))?)),
+            None => Ok(None),
+        }
+    }
+    /// The DER encoded entitlements, if any
+    pub fn der_entitlements(&self) -> error::Result<Option<&'a [u8]>> {
+        Ok(self.blob(CSSLOT_DER_ENTITLEMENTS)?.map(|blob| blob.payload()))
+    }
+    /// The CMS signature of the CodeDirectory, if this is not an ad hoc signature
+    pub fn cms_signature(&self) -> error::Result<Option<&'a [u8]>> {
+        Ok(self.blob(CSSLOT_SIGNATURESLOT)?.map(|blob| blob.payload()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+
+    #[test]
+    fn parse_signature() {
+        let be = scroll::BE;
+        let entitlements = b"<plist/>";
+        let mut bytes = vec![0u8; 0x400];
+        let start = 0x100;
+        // the SuperBlob: a CodeDirectory, the entitlements and an empty CMS signature
+        bytes.pwrite_with(BlobHeader { magic: CSMAGIC_EMBEDDED_SIGNATURE, length: 0x200 }, start, be).unwrap();
+        bytes.pwrite_with::<u32>(3, start + 8, be).unwrap();
+        bytes.pwrite_with(BlobIndex { typ: CSSLOT_CODEDIRECTORY, offset: 0x40 }, start + 12, be).unwrap();
+        bytes.pwrite_with(BlobIndex { typ: CSSLOT_ENTITLEMENTS, offset: 0x180 }, start + 20, be).unwrap();
+        bytes.pwrite_with(BlobIndex { typ: CSSLOT_SIGNATURESLOT, offset: 0x1c0 }, start + 28, be).unwrap();
+        let entitlements_blob = start + 0x180;
+        bytes.pwrite_with(BlobHeader { magic: CSMAGIC_EMBEDDED_ENTITLEMENTS, length: 8 + entitlements.len() as u32 }, entitlements_blob, be).unwrap();
+        bytes[entitlements_blob + 8..entitlements_blob + 8 + entitlements.len()].copy_from_slice(entitlements);
+        bytes.pwrite_with(BlobHeader { magic: CSMAGIC_BLOBWRAPPER, length: 8 }, start + 0x1c0, be).unwrap();
+        // a version 0x20200 CodeDirectory with 5 special slots and 2 SHA-256 page hashes
+        let cd = start + 0x40;
+        let hash_offset = 0x60 + 5 * 32;
+        let length = hash_offset + 2 * 32;
+        let fields = [CSMAGIC_CODEDIRECTORY, length as u32, CS_SUPPORTSTEAMID, CS_ADHOC, hash_offset as u32, 0x34, 5, 2, 0x1800];
+        for (i, field) in fields.iter().enumerate() {
+            bytes.pwrite_with(*field, cd + i * 4, be).unwrap();
+        }
+        bytes[cd + 36..cd + 40].copy_from_slice(&[32, CS_HASHTYPE_SHA256, 0, 12]);
+        bytes.pwrite_with::<u32>(0x3e, cd + 48, be).unwrap();
+        bytes[cd + 0x34..cd + 0x3e].copy_from_slice(b"com.dummy\0");
+        bytes[cd + 0x3e..cd + 0x45].copy_from_slice(b"ABCDEF\0");
+        let entitlements_hash = digest::sha256(&bytes[entitlements_blob..entitlements_blob + 8 + entitlements.len()]);
+        bytes[cd + hash_offset - 5 * 32..cd + hash_offset - 4 * 32].copy_from_slice(&entitlements_hash);
+        bytes[cd + hash_offset + 32..cd + length].copy_from_slice(&[0xaa; 32]);
+
+        let command = load_command::LinkeditDataCommand { cmd: load_command::LC_CODE_SIGNATURE, cmdsize: 16, dataoff: start as u32, datasize: 0x210 };
+        let signature = CodeSignature::new(&bytes, &command);
+        assert_eq!(signature.blobs().unwrap().len(), 3);
+        let directory = signature.code_directory().unwrap().unwrap();
+        assert_eq!(directory.identifier().unwrap(), "com.dummy");
+        assert_eq!(directory.team_id().unwrap(), Some("ABCDEF"));
+        assert_eq!(directory.flags, CS_ADHOC);
+        assert_eq!(directory.page_size(), 0x1000);
+        assert_eq!(directory.code_limit(), 0x1800);
+        assert_eq!(directory.page_hashes().unwrap(), vec![&[0; 32][..], &[0xaa; 32][..]]);
+        assert_eq!(directory.special_slot_hash(CSSLOT_ENTITLEMENTS).unwrap(), Some(&entitlements_hash[..]));
+        assert_eq!(directory.special_slot_hash(CSSLOT_INFOSLOT).unwrap(), Some(&[0; 32][..]));
+        assert_eq!(directory.special_slot_hash(6).unwrap(), None);
+        assert_eq!(&directory.cdhash().unwrap()[..], &digest::sha256(&bytes[cd..cd + length])[..20]);
+        assert_eq!(signature.code_directories().unwrap().len(), 1);
+        assert_eq!(signature.entitlements().unwrap(), Some("<plist/>"));
+        assert_eq!(signature.der_entitlements().unwrap(), None);
+        assert_eq!(signature.cms_signature().unwrap(), Some(&[][..]));
+        assert_eq!(signature.requirements().unwrap(), vec![]);
+    }
+}
@@ -247,6 +247,19 @@ impl<'a> ExportTrie<'a> {
             location: start..end,
         }
     }
+
+    /// Create a new, lazy, zero-copy export trie from the `LC_DYLD_EXPORTS_TRIE` `command`
+    pub fn new_from_linkedit_data_command(bytes: &'a [u8], command: &load_command::LinkeditDataCommand) -> error::Result<Self> {
+        let start = command.dataoff as usize;
+        let end = match start.checked_add(command.datasize as usize) {
+            Some(end) => end,
+            None => return Err(error::Error::Malformed(format!("Exports trie at {:#x} of size {:#x} overflows", start, command.datasize))),
+        };
+        Ok(ExportTrie {
+            data: bytes.as_ref(),
+            location: start..end,
+        })
+    }
 }
 
 impl<'a> Debug for ExportTrie<'a> {
@@ -25,14 +25,14 @@ struct BindInformation<'a> {
   symbol_flags:           u8,
   addend:                 i64,
   special_dylib:          u8, // seeing self = 0 assuming this means the symbol is imported from itself, because its... libSystem.B.dylib?
-  is_lazy:                bool,
+  kind:                   ImportKind,
 }
 
 impl<'a> BindInformation<'a> {
-    pub fn new (is_lazy: bool) -> Self {
+    pub fn new (kind: ImportKind) -> Self {
         let mut bind_info = BindInformation::default();
-        if is_lazy {
-            bind_info.is_lazy = true;
+        bind_info.kind = kind;
+        if kind == ImportKind::Lazy {
             bind_info.bind_type = bind_opcodes::BIND_TYPE_POINTER;
         }
         bind_info
@@ -53,11 +53,22 @@ impl<'a> Default for BindInformation<'a> {
             symbol_name: "",
             symbol_flags: 0,
             addend: 0,
-            is_lazy: false
+            kind: ImportKind::NonLazy,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Which stream of bind opcodes an import is bound by
+pub enum ImportKind {
+    /// Bound when the binary is loaded, by the bind opcodes or a fixup chain
+    NonLazy,
+    /// Bound on first use, by the lazy bind opcodes
+    Lazy,
+    /// Bound to the one definition of a weak symbol coalesced across every loaded image, by the weak bind opcodes
+    Weak,
+}
+
 #[derive(Debug)]
 /// An dynamically linked symbolic import
 pub struct Import<'a> {
@@ -67,6 +78,8 @@ pub struct Import<'a> {
     pub dylib:   &'a str,
     ///  Whether the symbol is lazily resolved or not
     pub is_lazy: bool,
+    /// Which stream of bind opcodes this import is bound by
+    pub kind: ImportKind,
     /// The offset in the binary this import is found
     pub offset:  u64,
     /// The size of this import
@@ -91,11 +104,15 @@ impl<'a> Import<'a> {
                 segment.vmaddr + bi.seg_offset
             )
         };
-        let size = if bi.is_lazy { 8 } else { 0 };
+        let is_lazy = bi.kind == ImportKind::Lazy;
+        let size = if is_lazy { 8 } else { 0 };
+        // weak binds have no library ordinal, dyld looks the symbol up in every image
+        let dylib = if bi.kind == ImportKind::Weak { "weak lookup" } else { libs[bi.symbol_library_ordinal as usize] };
         Import {
             name: bi.symbol_name,
-            dylib: libs[bi.symbol_library_ordinal as usize],
-            is_lazy: bi.is_lazy,
+            dylib: dylib,
+            is_lazy: is_lazy,
+            kind: bi.kind,
             offset: offset,
             size: size,
             address: address,
@@ -108,11 +125,12 @@ impl<'a> Import<'a> {
 
 /// An interpreter for mach BIND opcodes.
 /// Runs on prebound (non lazy) symbols (usually dylib extern consts and extern variables),
-/// and lazy symbols (usually dylib functions)
+/// lazy symbols (usually dylib functions) and weak symbols (usually C++ inline functions and template instantiations)
 pub struct BindInterpreter<'a> {
     data: &'a [u8],
     location: Range<usize>,
     lazy_location: Range<usize>,
+    weak_location: Range<usize>,
 }
 
 impl<'a> Debug for BindInterpreter<'a> {
@@ -120,6 +138,7 @@ impl<'a> Debug for BindInterpreter<'a> {
         writeln!(fmt, "BindInterpreter {{")?;
         writeln!(fmt, "  Location: {:#x}..{:#x}", self.location.start, self.location.end)?;
         writeln!(fmt, "  Lazy Location: {:#x}..{:#x}", self.lazy_location.start, self.lazy_location.end)?;
+        writeln!(fmt, "  Weak Location: {:#x}..{:#x}", self.weak_location.start, self.weak_location.end)?;
         writeln!(fmt, "}}")
     }
 }
@@ -133,27 +152,30 @@ impl<'a> BindInterpreter<'a> {
         };
         let location = get_pos(command.bind_off, command.bind_size);
         let lazy_location = get_pos(command.lazy_bind_off, command.lazy_bind_size);
+        let weak_location = get_pos(command.weak_bind_off, command.weak_bind_size);
         BindInterpreter {
             data: bytes.as_ref(),
             location: location,
             lazy_location: lazy_location,
+            weak_location: weak_location,
         }
     }
     /// Return the imports in this binary
     pub fn imports(&self, libs: &[&'a str], segments: &[segment::Segment], ctx: &container::Ctx) -> error::Result<Vec<Import<'a>>>{
         let mut imports = Vec::new();
-        self.run(false, libs, segments, ctx, &mut imports)?;
-        self.run( true, libs, segments, ctx, &mut imports)?;
+        self.run(ImportKind::NonLazy, libs, segments, ctx, &mut imports)?;
+        self.run(ImportKind::Lazy,    libs, segments, ctx, &mut imports)?;
+        self.run(ImportKind::Weak,    libs, segments, ctx, &mut imports)?;
         Ok(imports)
     }
-    fn run(&self, is_lazy: bool, libs: &[&'a str], segments: &[segment::Segment], ctx: &container::Ctx, imports: &mut Vec<Import<'a>>) -> error::Result<()>{
+    fn run(&self, kind: ImportKind, libs: &[&'a str], segments: &[segment::Segment], ctx: &container::Ctx, imports: &mut Vec<Import<'a>>) -> error::Result<()>{
         use mach::bind_opcodes::*;
-        let location = if is_lazy {
-            &self.lazy_location
-        } else {
-            &self.location
+        let location = match kind {
+            ImportKind::NonLazy => &self.location,
+            ImportKind::Lazy => &self.lazy_location,
+            ImportKind::Weak => &self.weak_location,
         };
-        let mut bind_info = BindInformation::new(is_lazy);
+        let mut bind_info = BindInformation::new(kind);
         let offset = &mut location.start.clone();
         let mut start_of_sequence: usize = 0;
         while *offset < location.end {
@@ -164,7 +186,7 @@ impl<'a> BindInterpreter<'a> {
             match opcode & BIND_OPCODE_MASK {
                 // we do nothing, don't update our records, and add a new, fresh record
                 BIND_OPCODE_DONE => {
-                    bind_info = BindInformation::new(is_lazy);
+                    bind_info = BindInformation::new(kind);
                     start_of_sequence = *offset - location.start;
                 },
                 BIND_OPCODE_SET_DYLIB_ORDINAL_IMM => {
@@ -0,0 +1,109 @@
+//! The indirect symbol table of `LC_DYSYMTAB`, which names the symbol every symbol pointer and stub refers to
+//!
+//! Symbol pointer sections (`__got`, `__nl_symbol_ptr`, `__la_symbol_ptr`, ...) and symbol stub sections (`__stubs`)
+//! hold one indirect symbol table entry per element, in the same order, starting at the index in the `reserved1` of
+//! the section. The elements of a symbol pointer section are pointer sized, those of a symbol stub section are
+//! `reserved2` bytes long.
+
+use scroll::Pread;
+
+use error;
+use container;
+use mach::constants::*;
+use mach::load_command;
+use mach::segment;
+use mach::symbols::{Symbols, Nlist};
+
+/// The indirect symbol table entry of a pointer to a local symbol, which was stripped
+pub const INDIRECT_SYMBOL_LOCAL: u32 = 0x80000000;
+/// The indirect symbol table entry of a pointer to an absolute symbol, which was stripped
+pub const INDIRECT_SYMBOL_ABS: u32 = 0x40000000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A symbol pointer or stub, and the symbol it refers to
+pub struct IndirectSymbol<'a> {
+    /// The virtual memory address of the pointer or stub
+    pub address: u64,
+    /// The `S_*` type of the section the pointer or stub is in, e.g., `S_SYMBOL_STUBS`
+    pub section_type: u32,
+    /// The index of the entry in the indirect symbol table
+    pub index: usize,
+    /// The entry itself, i.e., the index of the symbol in the symbol table, or `INDIRECT_SYMBOL_LOCAL` and/or `INDIRECT_SYMBOL_ABS`
+    pub entry: u32,
+    /// The symbol referred to, unless it was stripped
+    pub symbol: Option<(&'a str, Nlist)>,
+}
+
+impl<'a> IndirectSymbol<'a> {
+    /// Whether this refers to a local symbol which was stripped
+    pub fn is_local(&self) -> bool {
+        self.entry & INDIRECT_SYMBOL_LOCAL != 0
+    }
+    /// Whether this refers to an absolute symbol which was stripped
+    pub fn is_absolute(&self) -> bool {
+        self.entry & INDIRECT_SYMBOL_ABS != 0
+    }
+    /// Whether this is a stub rather than a pointer
+    pub fn is_stub(&self) -> bool {
+        self.section_type == S_SYMBOL_STUBS
+    }
+    /// The name of the symbol referred to, unless it was stripped
+    pub fn name(&self) -> Option<&'a str> {
+        self.symbol.map(|(name, _)| name)
+    }
+}
+
+/// Returns the indirect symbol table `dysymtab` points to
+pub fn indirect_symbol_table(bytes: &[u8], dysymtab: &load_command::DysymtabCommand, le: ::scroll::Endian) -> error::Result<Vec<u32>> {
+    let offset = &mut (dysymtab.indirectsymoff as usize);
+    let mut table = Vec::new();
+    for _ in 0..dysymtab.nindirectsyms {
+        table.push(bytes.gread_with(offset, le)?);
+    }
+    Ok(table)
+}
+
+/// Returns the size of an element of `section`, if it has indirect symbol table entries
+fn element_size(section: &segment::Section, ctx: &container::Ctx) -> Option<u64> {
+    match section.flags & SECTION_TYPE {
+        S_SYMBOL_STUBS => Some(section.reserved2 as u64),
+          S_NON_LAZY_SYMBOL_POINTERS
+        | S_LAZY_SYMBOL_POINTERS
+        | S_LAZY_DYLIB_SYMBOL_POINTERS
+        | S_THREAD_LOCAL_VARIABLE_POINTERS => Some(ctx.size() as u64),
+        _ => None,
+    }
+}
+
+/// Resolves every symbol pointer and stub in `segments` to the symbol it refers to
+pub fn resolve<'a>(bytes: &'a [u8], dysymtab: &load_command::DysymtabCommand, segments: &[segment::Segment<'a>], symbols: Option<&Symbols<'a>>, ctx: &container::Ctx) -> error::Result<Vec<IndirectSymbol<'a>>> {
+    let table = indirect_symbol_table(bytes, dysymtab, ctx.le)?;
+    let mut indirect_symbols = Vec::new();
+    for segment in segments {
+        for (section, _) in segment.sections()? {
+            let size = match element_size(&section, ctx) {
+                Some(size) if size > 0 => size,
+                _ => continue,
+            };
+            for i in 0..section.size / size {
+                let index = section.reserved1 as usize + i as usize;
+                let entry = match table.get(index) {
+                    Some(entry) => *entry,
+                    None => return Err(error::Error::Malformed(format!("Section {} has indirect symbol {} but there are only {}", section.name().unwrap_or(""), index, table.len()))),
+                };
+                let symbol = match symbols {
+                    Some(symbols) if entry & (INDIRECT_SYMBOL_LOCAL | INDIRECT_SYMBOL_ABS) == 0 => Some(symbols.get(entry as usize)?),
+                    _ => None,
+                };
+                indirect_symbols.push(IndirectSymbol {
+                    address: section.addr + i * size,
+                    section_type: section.flags & SECTION_TYPE,
+                    index: index,
+                    entry: entry,
+                    symbol: symbol,
+                });
+            }
+        }
+    }
+    Ok(indirect_symbols)
+}
@@ -1,7 +1,9 @@
 //! Load commands tell the kernel and dynamic linker anything from how to load this binary into memory, what the entry point is, apple specific information, to which libraries it requires for dynamic linking
 
 use error;
+use std::cmp;
 use std::fmt::{self, Display};
+use std::ops::Range;
 use scroll::{self, ctx, Endian, Pread};
 
 ///////////////////////////////////////
@@ -217,7 +219,7 @@ pub struct DylibCommand {
     pub dylib: Dylib,
   }
 
-pub const SIZEOF_DYLIB_COMMAND: usize = 20;
+pub const SIZEOF_DYLIB_COMMAND: usize = 24;
 
 /// A dynamically linked shared library may be a subframework of an umbrella
 /// framework.  If so it will be linked with "-umbrella umbrella_name" where
@@ -384,7 +386,8 @@ pub struct ThreadCommand {
     /// number of elements in `thread_state` that are valid
     pub count: u32,
 
-    /// The raw thread state, details of which varies by CPU
+    /// The first 70 words of the raw thread state, details of which varies by CPU; see `mach::thread_status` to
+    /// decode every state of the command
     pub thread_state: [u32; 70],
 }
 
@@ -495,19 +498,14 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for ThreadCommand {
 
         // get a byte slice of the thread state
         let thread_state_byte_length = count as usize * 4;
-        let thread_state_bytes = &bytes[16..16+thread_state_byte_length];
-
-        // check the length
-        if thread_state_bytes.len() < thread_state_byte_length {
-            return Err(error::Error::Malformed(format!("thread command specifies {} bytes for thread state but has only {}", thread_state_byte_length, thread_state_bytes.len())));
-        }
-        if count > 70 {
-            return Err(error::Error::Malformed(format!("thread command specifies {} longs for thread state but we handle only 70", count)));
-        }
+        let thread_state_bytes = match bytes.get(16..16+thread_state_byte_length) {
+            Some(thread_state_bytes) => thread_state_bytes,
+            None => return Err(error::Error::Malformed(format!("thread command specifies {} bytes for thread state but has only {}", thread_state_byte_length, bytes.len().saturating_sub(16)))),
+        };
 
-        // read the thread state
+        // read the thread state; larger states, e.g., the float states of core files, are only decoded by `thread_status`
         let mut thread_state: [u32; 70] = [ 0; 70 ];
-        for i in 0..count as usize {
+        for i in 0..cmp::min(count as usize, 70) {
             thread_state[i] = thread_state_bytes.pread_with(i*4, le)?;
         }
 
@@ -717,11 +715,22 @@ impl DysymtabCommand {
             nlocrel:        0,
         }
     }
+    /// The indices of the local symbols in the symbol table
+    pub fn local_symbols(&self) -> Range<usize> {
+        self.ilocalsym as usize..self.ilocalsym as usize + self.nlocalsym as usize
+    }
+    /// The indices of the externally defined symbols in the symbol table
+    pub fn external_symbols(&self) -> Range<usize> {
+        self.iextdefsym as usize..self.iextdefsym as usize + self.nextdefsym as usize
+    }
+    /// The indices of the undefined symbols in the symbol table
+    pub fn undefined_symbols(&self) -> Range<usize> {
+        self.iundefsym as usize..self.iundefsym as usize + self.nundefsym as usize
+    }
 }
 
 pub const SIZEOF_DYSYMTAB_COMMAND: usize = 80;
 
-// TODO: unimplemented
 /// a table of contents entry
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
@@ -732,7 +741,8 @@ pub struct DylibTableOfContents {
     pub module_index: u32,
 }
 
-// TODO: unimplemented
+pub const SIZEOF_DYLIB_TABLE_OF_CONTENTS: usize = 8;
+
 /// a module table entry
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
@@ -767,7 +777,8 @@ pub struct DylibModule {
     pub objc_module_info_size: u32,
 }
 
-// TODO: unimplemented
+pub const SIZEOF_DYLIB_MODULE: usize = 52;
+
 /// a 64-bit module table entry
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
@@ -804,19 +815,57 @@ pub struct DylibModule64 {
     pub objc_module_info_addr: u64,
 }
 
+pub const SIZEOF_DYLIB_MODULE_64: usize = 56;
+
+impl From<DylibModule> for DylibModule64 {
+    fn from(module: DylibModule) -> Self {
+        DylibModule64 {
+            module_name: module.module_name,
+            iextdefsym: module.iextdefsym,
+            nextdefsym: module.nextdefsym,
+            irefsym: module.irefsym,
+            nrefsym: module.nrefsym,
+            ilocalsym: module.ilocalsym,
+            nlocalsym: module.nlocalsym,
+            iextrel: module.iextrel,
+            nextrel: module.nextrel,
+            iinit_iterm: module.iinit_iterm,
+            ninit_nterm: module.ninit_nterm,
+            objc_module_info_size: module.objc_module_info_size,
+            objc_module_info_addr: module.objc_module_info_addr as u64,
+        }
+    }
+}
+
 /// The entries in the reference symbol table are used when loading the module
 /// (both by the static and dynamic link editors) and if the module is unloaded
 /// or replaced.  Therefore all external symbols (defined and undefined) are
 /// listed in the module's reference table.  The flags describe the type of
 /// reference that is being made.  The constants for the flags are defined in
 /// <mach-o/nlist.h> as they are also used for symbol table entries.
-#[repr(C)]
-#[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DylibReference {
     /// 24 bits bit-field index into the symbol table
-    pub isym: [u8; 24],
+    pub isym: u32,
     /// flags to indicate the type of reference
-    pub flags: u64,
+    pub flags: u8,
+}
+
+pub const SIZEOF_DYLIB_REFERENCE: usize = 4;
+
+impl<'a> ctx::TryFromCtx<'a, Endian> for DylibReference {
+    type Error = ::error::Error;
+    type Size = usize;
+    fn try_from_ctx(bytes: &'a [u8], le: Endian) -> error::Result<(Self, Self::Size)> {
+        let bits = bytes.pread_with::<u32>(0, le)?;
+        // the bit-fields are allocated from the least significant bit on little endian targets, and the most on big
+        let reference = if le.is_little() {
+            DylibReference { isym: bits & 0xffffff, flags: (bits >> 24) as u8 }
+        } else {
+            DylibReference { isym: bits >> 8, flags: bits as u8 }
+        };
+        Ok((reference, SIZEOF_DYLIB_REFERENCE))
+    }
 }
 
 /// The twolevel_hints_command contains the offset and number of hints in the
@@ -912,7 +961,7 @@ pub const SIZEOF_RPATH_COMMAND: usize = 12;
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
 pub struct LinkeditDataCommand {
-    /// LC_CODE_SIGNATURE, LC_SEGMENT_SPLIT_INFO, LC_FUNCTION_STARTS, LC_DATA_IN_CODE, LC_DYLIB_CODE_SIGN_DRS or LC_LINKER_OPTIMIZATION_HINT.
+    /// LC_CODE_SIGNATURE, LC_SEGMENT_SPLIT_INFO, LC_FUNCTION_STARTS, LC_DATA_IN_CODE, LC_DYLIB_CODE_SIGN_DRS, LC_LINKER_OPTIMIZATION_HINT, LC_DYLD_EXPORTS_TRIE, LC_DYLD_CHAINED_FIXUPS, LC_ATOM_INFO, LC_FUNCTION_VARIANTS or LC_FUNCTION_VARIANT_FIXUPS.
     pub cmd: u32,
     /// sizeof(struct linkedit_data_command)
     pub cmdsize: u32,
@@ -964,10 +1013,24 @@ pub struct EncryptionInfoCommand64 {
 
 pub const SIZEOF_ENCRYPTION_INFO_COMMAND_64: usize = 24;
 
+impl EncryptionInfoCommand32 {
+    /// Whether the range is still encrypted, i.e., has not been decrypted and had its `cryptid` zeroed
+    pub fn is_encrypted(&self) -> bool {
+        self.cryptid != 0
+    }
+}
+
+impl EncryptionInfoCommand64 {
+    /// Whether the range is still encrypted, i.e., has not been decrypted and had its `cryptid` zeroed
+    pub fn is_encrypted(&self) -> bool {
+        self.cryptid != 0
+    }
+}
+
 /// The version_min_command contains the min OS version on which this
 /// binary was built to run.
 ///
-/// LC_VERSION_MIN_MACOSX or LC_VERSION_MIN_IPHONEOS
+/// LC_VERSION_MIN_MACOSX, LC_VERSION_MIN_IPHONEOS, LC_VERSION_MIN_TVOS or LC_VERSION_MIN_WATCHOS
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
 pub struct VersionMinCommand {
@@ -992,6 +1055,189 @@ impl VersionMinCommand {
 
 pub const SIZEOF_VERSION_MIN_COMMAND: usize = 16;
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// An X.Y.Z version, as it is encoded in nibbles xxxx.yy.zz by the version load commands
+pub struct Version {
+    pub major: u16,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl From<u32> for Version {
+    fn from(version: u32) -> Self {
+        Version {
+            major: (version >> 16) as u16,
+            minor: (version >> 8) as u8,
+            patch: version as u8,
+        }
+    }
+}
+
+impl From<Version> for u32 {
+    fn from(version: Version) -> Self {
+        (version.major as u32) << 16 | (version.minor as u32) << 8 | version.patch as u32
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+pub const PLATFORM_MACOS: u32 = 1;
+pub const PLATFORM_IOS: u32 = 2;
+pub const PLATFORM_TVOS: u32 = 3;
+pub const PLATFORM_WATCHOS: u32 = 4;
+pub const PLATFORM_BRIDGEOS: u32 = 5;
+pub const PLATFORM_MACCATALYST: u32 = 6;
+pub const PLATFORM_IOSSIMULATOR: u32 = 7;
+pub const PLATFORM_TVOSSIMULATOR: u32 = 8;
+pub const PLATFORM_WATCHOSSIMULATOR: u32 = 9;
+pub const PLATFORM_DRIVERKIT: u32 = 10;
+pub const PLATFORM_VISIONOS: u32 = 11;
+pub const PLATFORM_VISIONOSSIMULATOR: u32 = 12;
+pub const PLATFORM_FIRMWARE: u32 = 13;
+pub const PLATFORM_SEPOS: u32 = 14;
+
+pub fn platform_to_str(platform: u32) -> &'static str {
+    match platform {
+        PLATFORM_MACOS => "PLATFORM_MACOS",
+        PLATFORM_IOS => "PLATFORM_IOS",
+        PLATFORM_TVOS => "PLATFORM_TVOS",
+        PLATFORM_WATCHOS => "PLATFORM_WATCHOS",
+        PLATFORM_BRIDGEOS => "PLATFORM_BRIDGEOS",
+        PLATFORM_MACCATALYST => "PLATFORM_MACCATALYST",
+        PLATFORM_IOSSIMULATOR => "PLATFORM_IOSSIMULATOR",
+        PLATFORM_TVOSSIMULATOR => "PLATFORM_TVOSSIMULATOR",
+        PLATFORM_WATCHOSSIMULATOR => "PLATFORM_WATCHOSSIMULATOR",
+        PLATFORM_DRIVERKIT => "PLATFORM_DRIVERKIT",
+        PLATFORM_VISIONOS => "PLATFORM_VISIONOS",
+        PLATFORM_VISIONOSSIMULATOR => "PLATFORM_VISIONOSSIMULATOR",
+        PLATFORM_FIRMWARE => "PLATFORM_FIRMWARE",
+        PLATFORM_SEPOS => "PLATFORM_SEPOS",
+        _ => "PLATFORM_UNKNOWN",
+    }
+}
+
+pub const TOOL_CLANG: u32 = 1;
+pub const TOOL_SWIFT: u32 = 2;
+pub const TOOL_LD: u32 = 3;
+pub const TOOL_LLD: u32 = 4;
+pub const TOOL_METAL: u32 = 1024;
+pub const TOOL_AIRLLD: u32 = 1025;
+pub const TOOL_AIRNT: u32 = 1026;
+pub const TOOL_AIRNT_PLUGIN: u32 = 1027;
+pub const TOOL_AIRPACK: u32 = 1028;
+pub const TOOL_GPUARCHIVER: u32 = 1031;
+pub const TOOL_METAL_FRAMEWORK: u32 = 1032;
+
+pub fn tool_to_str(tool: u32) -> &'static str {
+    match tool {
+        TOOL_CLANG => "TOOL_CLANG",
+        TOOL_SWIFT => "TOOL_SWIFT",
+        TOOL_LD => "TOOL_LD",
+        TOOL_LLD => "TOOL_LLD",
+        TOOL_METAL => "TOOL_METAL",
+        TOOL_AIRLLD => "TOOL_AIRLLD",
+        TOOL_AIRNT => "TOOL_AIRNT",
+        TOOL_AIRNT_PLUGIN => "TOOL_AIRNT_PLUGIN",
+        TOOL_AIRPACK => "TOOL_AIRPACK",
+        TOOL_GPUARCHIVER => "TOOL_GPUARCHIVER",
+        TOOL_METAL_FRAMEWORK => "TOOL_METAL_FRAMEWORK",
+        _ => "TOOL_UNKNOWN",
+    }
+}
+
+/// The build_version_command contains the min OS version on which this
+/// binary was built to run for its platform, followed by `ntools`
+/// build_tool_version entries.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct BuildVersionCommand {
+    /// LC_BUILD_VERSION
+    pub cmd: u32,
+    /// sizeof(struct build_version_command) plus ntools * sizeof(struct build_tool_version)
+    pub cmdsize: u32,
+    /// PLATFORM_*
+    pub platform: u32,
+    /// X.Y.Z is encoded in nibbles xxxx.yy.zz
+    pub minos: u32,
+    /// X.Y.Z is encoded in nibbles xxxx.yy.zz
+    pub sdk: u32,
+    /// number of tool entries following this
+    pub ntools: u32,
+}
+
+pub const SIZEOF_BUILD_VERSION_COMMAND: usize = 24;
+
+/// A tool used to build the binary, following its build_version_command
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct BuildToolVersion {
+    /// TOOL_*
+    pub tool: u32,
+    /// version number of the tool
+    pub version: u32,
+}
+
+pub const SIZEOF_BUILD_TOOL_VERSION: usize = 8;
+
+/// The note_command points to arbitrary data, e.g., in core files, whose
+/// format is determined by its owner.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct NoteCommand {
+    /// LC_NOTE
+    pub cmd: u32,
+    /// sizeof(struct note_command)
+    pub cmdsize: u32,
+    /// owner name for this LC_NOTE
+    pub data_owner: [u8; 16],
+    /// file offset of this data
+    pub offset: u64,
+    /// length of data region
+    pub size: u64,
+}
+
+pub const SIZEOF_NOTE_COMMAND: usize = 40;
+
+/// The fileset_entry_command describes a Mach-O embedded in a MH_FILESET,
+/// e.g., a kernel extension in a kernel collection.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct FilesetEntryCommand {
+    /// LC_FILESET_ENTRY
+    pub cmd: u32,
+    /// includes id string
+    pub cmdsize: u32,
+    /// memory address of the entry
+    pub vmaddr: u64,
+    /// file offset of the entry
+    pub fileoff: u64,
+    /// contained entry id
+    pub entry_id: LcStr,
+    /// reserved
+    pub reserved: u32,
+}
+
+pub const SIZEOF_FILESET_ENTRY_COMMAND: usize = 32;
+
+/// The target_triple_command contains the LLVM target triple the binary
+/// was built for.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pread, Pwrite, IOread, IOwrite, SizeWith)]
+pub struct TargetTripleCommand {
+    /// LC_TARGET_TRIPLE
+    pub cmd: u32,
+    /// including string
+    pub cmdsize: u32,
+    /// target triple string
+    pub triple: LcStr,
+}
+
+pub const SIZEOF_TARGET_TRIPLE_COMMAND: usize = 12;
+
 #[repr(C)]
 #[derive(Default, Debug, Clone, Copy, Pread, Pwrite, SizeWith)]
 pub struct DyldInfoCommand {
@@ -1135,6 +1381,51 @@ pub struct DataInCodeEntry {
     pub kind: u16,
 }
 
+pub const SIZEOF_DATA_IN_CODE_ENTRY: usize = 8;
+
+pub const DICE_KIND_DATA: u16 = 0x0001;
+pub const DICE_KIND_JUMP_TABLE8: u16 = 0x0002;
+pub const DICE_KIND_JUMP_TABLE16: u16 = 0x0003;
+pub const DICE_KIND_JUMP_TABLE32: u16 = 0x0004;
+pub const DICE_KIND_ABS_JUMP_TABLE32: u16 = 0x0005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What a range of data in a code section holds
+pub enum DataInCodeKind {
+    /// Data, e.g., a literal pool
+    Data,
+    /// A jump table of 8-bit offsets
+    JumpTable8,
+    /// A jump table of 16-bit offsets
+    JumpTable16,
+    /// A jump table of 32-bit offsets
+    JumpTable32,
+    /// A jump table of 32-bit absolute addresses
+    AbsJumpTable32,
+    /// A `DICE_KIND_*` this does not know of
+    Unknown(u16),
+}
+
+impl From<u16> for DataInCodeKind {
+    fn from(kind: u16) -> Self {
+        match kind {
+            DICE_KIND_DATA => DataInCodeKind::Data,
+            DICE_KIND_JUMP_TABLE8 => DataInCodeKind::JumpTable8,
+            DICE_KIND_JUMP_TABLE16 => DataInCodeKind::JumpTable16,
+            DICE_KIND_JUMP_TABLE32 => DataInCodeKind::JumpTable32,
+            DICE_KIND_ABS_JUMP_TABLE32 => DataInCodeKind::AbsJumpTable32,
+            kind => DataInCodeKind::Unknown(kind),
+        }
+    }
+}
+
+impl DataInCodeEntry {
+    /// What this range of data holds
+    pub fn kind(&self) -> DataInCodeKind {
+        self.kind.into()
+    }
+}
+
 ///////////////////////////////////////
 // Constants, et. al
 ///////////////////////////////////////
@@ -1187,6 +1478,17 @@ pub const LC_DYLIB_CODE_SIGN_DRS: u32 = 0x2B;
 pub const LC_ENCRYPTION_INFO_64: u32 = 0x2C;
 pub const LC_LINKER_OPTION: u32 = 0x2D;
 pub const LC_LINKER_OPTIMIZATION_HINT: u32 = 0x2E;
+pub const LC_VERSION_MIN_TVOS: u32 = 0x2F;
+pub const LC_VERSION_MIN_WATCHOS: u32 = 0x30;
+pub const LC_NOTE: u32 = 0x31;
+pub const LC_BUILD_VERSION: u32 = 0x32;
+pub const LC_DYLD_EXPORTS_TRIE: u32 = 0x33 | LC_REQ_DYLD;
+pub const LC_DYLD_CHAINED_FIXUPS: u32 = 0x34 | LC_REQ_DYLD;
+pub const LC_FILESET_ENTRY: u32 = 0x35 | LC_REQ_DYLD;
+pub const LC_ATOM_INFO: u32 = 0x36;
+pub const LC_FUNCTION_VARIANTS: u32 = 0x37;
+pub const LC_FUNCTION_VARIANT_FIXUPS: u32 = 0x38;
+pub const LC_TARGET_TRIPLE: u32 = 0x39;
 
 pub fn cmd_to_str(cmd: u32) -> &'static str {
     match cmd {
@@ -1237,6 +1539,17 @@ pub fn cmd_to_str(cmd: u32) -> &'static str {
         LC_ENCRYPTION_INFO_64 => "LC_ENCRYPTION_INFO_64",
         LC_LINKER_OPTION => "LC_LINKER_OPTION",
         LC_LINKER_OPTIMIZATION_HINT => "LC_LINKER_OPTIMIZATION_HINT",
+        LC_VERSION_MIN_TVOS => "LC_VERSION_MIN_TVOS",
+        LC_VERSION_MIN_WATCHOS => "LC_VERSION_MIN_WATCHOS",
+        LC_NOTE => "LC_NOTE",
+        LC_BUILD_VERSION => "LC_BUILD_VERSION",
+        LC_DYLD_EXPORTS_TRIE => "LC_DYLD_EXPORTS_TRIE",
+        LC_DYLD_CHAINED_FIXUPS => "LC_DYLD_CHAINED_FIXUPS",
+        LC_FILESET_ENTRY => "LC_FILESET_ENTRY",
+        LC_ATOM_INFO => "LC_ATOM_INFO",
+        LC_FUNCTION_VARIANTS => "LC_FUNCTION_VARIANTS",
+        LC_FUNCTION_VARIANT_FIXUPS => "LC_FUNCTION_VARIANT_FIXUPS",
+        LC_TARGET_TRIPLE => "LC_TARGET_TRIPLE",
         _ => "LC_UNKNOWN",
     }
 }
@@ -1293,8 +1606,19 @@ pub enum CommandVariant {
     DataInCode             (LinkeditDataCommand),
     SourceVersion          (SourceVersionCommand),
     DylibCodeSignDrs       (LinkeditDataCommand),
-    LinkerOption           (LinkeditDataCommand),
+    LinkerOption           (LinkerOptionCommand),
     LinkerOptimizationHint (LinkeditDataCommand),
+    DyldExportsTrie        (LinkeditDataCommand),
+    DyldChainedFixups      (LinkeditDataCommand),
+    VersionMinTvos         (VersionMinCommand),
+    VersionMinWatchos      (VersionMinCommand),
+    Note                   (NoteCommand),
+    BuildVersion           (BuildVersionCommand),
+    FilesetEntry           (FilesetEntryCommand),
+    AtomInfo               (LinkeditDataCommand),
+    FunctionVariants       (LinkeditDataCommand),
+    FunctionVariantFixups  (LinkeditDataCommand),
+    TargetTriple           (TargetTripleCommand),
     Unimplemented          (LoadCommandHeader),
 }
 
@@ -1354,8 +1678,19 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for CommandVariant {
             LC_DATA_IN_CODE => {            let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((DataInCode             (comm), size))},
             LC_SOURCE_VERSION => {          let comm = bytes.pread_with::<SourceVersionCommand>   (0, le)?;  Ok((SourceVersion          (comm), size))},
             LC_DYLIB_CODE_SIGN_DRS => {     let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((DylibCodeSignDrs       (comm), size))},
-            LC_LINKER_OPTION => {           let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((LinkerOption           (comm), size))},
+            LC_LINKER_OPTION => {           let comm = bytes.pread_with::<LinkerOptionCommand>    (0, le)?;  Ok((LinkerOption           (comm), size))},
             LC_LINKER_OPTIMIZATION_HINT => {let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((LinkerOptimizationHint (comm), size))},
+            LC_DYLD_EXPORTS_TRIE => {       let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((DyldExportsTrie        (comm), size))},
+            LC_DYLD_CHAINED_FIXUPS => {     let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((DyldChainedFixups      (comm), size))},
+            LC_VERSION_MIN_TVOS => {        let comm = bytes.pread_with::<VersionMinCommand>      (0, le)?;  Ok((VersionMinTvos         (comm), size))},
+            LC_VERSION_MIN_WATCHOS => {     let comm = bytes.pread_with::<VersionMinCommand>      (0, le)?;  Ok((VersionMinWatchos      (comm), size))},
+            LC_NOTE => {                    let comm = bytes.pread_with::<NoteCommand>            (0, le)?;  Ok((Note                   (comm), size))},
+            LC_BUILD_VERSION => {           let comm = bytes.pread_with::<BuildVersionCommand>    (0, le)?;  Ok((BuildVersion           (comm), size))},
+            LC_FILESET_ENTRY => {           let comm = bytes.pread_with::<FilesetEntryCommand>    (0, le)?;  Ok((FilesetEntry           (comm), size))},
+            LC_ATOM_INFO => {               let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((AtomInfo               (comm), size))},
+            LC_FUNCTION_VARIANTS => {       let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((FunctionVariants       (comm), size))},
+            LC_FUNCTION_VARIANT_FIXUPS => { let comm = bytes.pread_with::<LinkeditDataCommand>    (0, le)?;  Ok((FunctionVariantFixups  (comm), size))},
+            LC_TARGET_TRIPLE => {           let comm = bytes.pread_with::<TargetTripleCommand>    (0, le)?;  Ok((TargetTriple           (comm), size))},
             _ =>                                                                                             Ok((Unimplemented          (lc.clone()), size)),
         }
     }
@@ -1412,6 +1747,17 @@ impl CommandVariant {
             DylibCodeSignDrs       (comm) => comm.cmdsize,
             LinkerOption           (comm) => comm.cmdsize,
             LinkerOptimizationHint (comm) => comm.cmdsize,
+            DyldExportsTrie        (comm) => comm.cmdsize,
+            DyldChainedFixups      (comm) => comm.cmdsize,
+            VersionMinTvos         (comm) => comm.cmdsize,
+            VersionMinWatchos      (comm) => comm.cmdsize,
+            Note                   (comm) => comm.cmdsize,
+            BuildVersion           (comm) => comm.cmdsize,
+            FilesetEntry           (comm) => comm.cmdsize,
+            AtomInfo               (comm) => comm.cmdsize,
+            FunctionVariants       (comm) => comm.cmdsize,
+            FunctionVariantFixups  (comm) => comm.cmdsize,
+            TargetTriple           (comm) => comm.cmdsize,
             Unimplemented          (comm) => comm.cmdsize,
         };
         cmdsize as usize
@@ -1466,6 +1812,17 @@ impl CommandVariant {
             DylibCodeSignDrs       (comm) => comm.cmd,
             LinkerOption           (comm) => comm.cmd,
             LinkerOptimizationHint (comm) => comm.cmd,
+            DyldExportsTrie        (comm) => comm.cmd,
+            DyldChainedFixups      (comm) => comm.cmd,
+            VersionMinTvos         (comm) => comm.cmd,
+            VersionMinWatchos      (comm) => comm.cmd,
+            Note                   (comm) => comm.cmd,
+            BuildVersion           (comm) => comm.cmd,
+            FilesetEntry           (comm) => comm.cmd,
+            AtomInfo               (comm) => comm.cmd,
+            FunctionVariants       (comm) => comm.cmd,
+            FunctionVariantFixups  (comm) => comm.cmd,
+            TargetTriple           (comm) => comm.cmd,
             Unimplemented          (comm) => comm.cmd,
         }
     }
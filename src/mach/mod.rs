@@ -1,7 +1,8 @@
 //! The Mach-o, mostly zero-copy, binary format parser and raw struct definitions
 use core::fmt;
+use std::collections::BTreeMap;
 
-use scroll::{self, Pread};
+use scroll::{self, Pread, Uleb128};
 
 use error;
 use container;
@@ -16,6 +17,13 @@ pub mod imports;
 pub mod bind_opcodes;
 pub mod relocation;
 pub mod segment;
+pub mod chained_fixups;
+pub mod rebase_opcodes;
+pub mod rebases;
+pub mod codesign;
+pub mod indirect_symbols;
+pub mod writer;
+pub mod thread_status;
 
 pub use self::constants::cputype as cputype;
 
@@ -50,6 +58,12 @@ pub struct MachO<'a> {
     ctx: container::Ctx,
     export_trie: Option<exports::ExportTrie<'a>>,
     bind_interpreter: Option<imports::BindInterpreter<'a>>,
+    chained_fixups: Option<chained_fixups::ChainedFixups<'a>>,
+    rebase_interpreter: Option<rebases::RebaseInterpreter<'a>>,
+    code_signature: Option<codesign::CodeSignature<'a>>,
+    dysymtab: Option<load_command::DysymtabCommand>,
+    function_starts: Option<load_command::LinkeditDataCommand>,
+    data_in_code: Option<load_command::LinkeditDataCommand>,
 }
 
 #[cfg(feature = "std")]
@@ -68,6 +82,7 @@ impl<'a> fmt::Debug for MachO<'a> {
             .field("symbols()",       &self.symbols().collect::<Vec<_>>())
             .field("exports()",       &self.exports())
             .field("imports()",       &self.imports())
+            .field("rebases()",       &self.rebases())
             .finish()
     }
 }
@@ -83,6 +98,20 @@ impl<'a> MachO<'a> {
             symbols::SymbolIterator::default()
         }
     }
+    /// Returns the symbol at or nearest before `address`, if this binary has a symbol table
+    pub fn lookup_address(&self, address: u64) -> error::Result<Option<(&'a str, symbols::Nlist)>> {
+        match self.symbols {
+            Some(ref symbols) => symbols.lookup_address(address),
+            None => Ok(None),
+        }
+    }
+    /// Returns the debug map of this binary, i.e., the object files it was linked from, if it was not stripped
+    pub fn debug_map(&self) -> error::Result<Vec<symbols::DebugMapObject<'a>>> {
+        match self.symbols {
+            Some(ref symbols) => symbols.debug_map(),
+            None => Ok(Vec::new()),
+        }
+    }
     pub fn relocations(&self) -> error::Result<Vec<(usize, segment::RelocationIterator, segment::Section)>> {
         debug!("Iterating relocations");
         let mut relocs = Vec::new();
@@ -96,6 +125,163 @@ impl<'a> MachO<'a> {
         }
         Ok(relocs)
     }
+    /// Return the relocations of every section which has any, typed for the `cputype`, with pairs (e.g.,
+    /// `X86_64_RELOC_SUBTRACTOR` and `ARM64_RELOC_ADDEND`) combined, and resolved against the symbol table and sections
+    pub fn resolved_relocations(&self) -> error::Result<Vec<(segment::Section, Vec<relocation::Relocation<'a>>)>> {
+        let mut sections = Vec::new();
+        for segment in &self.segments {
+            for (section, _data) in segment.sections()? {
+                sections.push(section);
+            }
+        }
+        let mut relocs = Vec::new();
+        for section in &sections {
+            if section.nreloc > 0 {
+                let relocations = section.iter_relocations(self.data, self.ctx).collect::<error::Result<Vec<_>>>()?;
+                let resolved = relocation::resolve(&relocations, self.header.cputype, self.symbols.as_ref(), &sections)?;
+                relocs.push((section.clone(), resolved));
+            }
+        }
+        Ok(relocs)
+    }
+    /// The dynamic symbol table command, which partitions the symbol table and points to the indirect symbol table (if any)
+    pub fn dysymtab(&self) -> Option<&load_command::DysymtabCommand> {
+        self.dysymtab.as_ref()
+    }
+    fn symbols_in(&self, range: ::core::ops::Range<usize>) -> error::Result<Vec<(&'a str, symbols::Nlist)>> {
+        let mut partition = Vec::new();
+        if let Some(ref symbols) = self.symbols {
+            for index in range {
+                partition.push(symbols.get(index)?);
+            }
+        }
+        Ok(partition)
+    }
+    /// Return the local symbols, as partitioned by `LC_DYSYMTAB` (if any)
+    pub fn local_symbols(&self) -> error::Result<Vec<(&'a str, symbols::Nlist)>> {
+        match self.dysymtab {
+            Some(dysymtab) => self.symbols_in(dysymtab.local_symbols()),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// Return the externally defined symbols, as partitioned by `LC_DYSYMTAB` (if any)
+    pub fn external_symbols(&self) -> error::Result<Vec<(&'a str, symbols::Nlist)>> {
+        match self.dysymtab {
+            Some(dysymtab) => self.symbols_in(dysymtab.external_symbols()),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// Return the undefined symbols, as partitioned by `LC_DYSYMTAB` (if any)
+    pub fn undefined_symbols(&self) -> error::Result<Vec<(&'a str, symbols::Nlist)>> {
+        match self.dysymtab {
+            Some(dysymtab) => self.symbols_in(dysymtab.undefined_symbols()),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// Return every symbol pointer and stub, with the symbol the indirect symbol table says it refers to
+    pub fn indirect_symbols(&self) -> error::Result<Vec<indirect_symbols::IndirectSymbol<'a>>> {
+        match self.dysymtab {
+            Some(dysymtab) => indirect_symbols::resolve(self.data, &dysymtab, self.segments.as_slice(), self.symbols.as_ref(), &self.ctx),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// Return the name of the symbol every symbol pointer and stub refers to, by its address, e.g., to name the
+    /// target of a call to a stub
+    pub fn indirect_symbol_names(&self) -> error::Result<BTreeMap<u64, &'a str>> {
+        let mut names = BTreeMap::new();
+        for indirect_symbol in self.indirect_symbols()? {
+            if let Some(name) = indirect_symbol.name() {
+                names.insert(indirect_symbol.address, name);
+            }
+        }
+        Ok(names)
+    }
+    /// Return the module table of this (pre-10.4 style) dylib (if any)
+    pub fn modules(&self) -> error::Result<Vec<load_command::DylibModule64>> {
+        let mut modules = Vec::new();
+        if let Some(dysymtab) = self.dysymtab {
+            let offset = &mut (dysymtab.modtaboff as usize);
+            for _ in 0..dysymtab.nmodtab {
+                if self.is_64 {
+                    modules.push(self.data.gread_with::<load_command::DylibModule64>(offset, self.ctx.le)?);
+                } else {
+                    modules.push(self.data.gread_with::<load_command::DylibModule>(offset, self.ctx.le)?.into());
+                }
+            }
+        }
+        Ok(modules)
+    }
+    /// Return the table of contents of this (pre-10.4 style) dylib (if any)
+    pub fn table_of_contents(&self) -> error::Result<Vec<load_command::DylibTableOfContents>> {
+        let mut toc = Vec::new();
+        if let Some(dysymtab) = self.dysymtab {
+            let offset = &mut (dysymtab.tocoff as usize);
+            for _ in 0..dysymtab.ntoc {
+                toc.push(self.data.gread_with(offset, self.ctx.le)?);
+            }
+        }
+        Ok(toc)
+    }
+    /// Return the reference symbol table of this (pre-10.4 style) dylib (if any)
+    pub fn reference_symbols(&self) -> error::Result<Vec<load_command::DylibReference>> {
+        let mut references = Vec::new();
+        if let Some(dysymtab) = self.dysymtab {
+            let offset = &mut (dysymtab.extrefsymoff as usize);
+            for _ in 0..dysymtab.nextrefsyms {
+                references.push(self.data.gread_with(offset, self.ctx.le)?);
+            }
+        }
+        Ok(references)
+    }
+    /// Return the address of every function `LC_FUNCTION_STARTS` lists (if any), in ascending order, including those
+    /// of stripped local symbols
+    pub fn function_starts(&self) -> error::Result<Vec<u64>> {
+        let mut starts = Vec::new();
+        if let Some(command) = self.function_starts {
+            // the first delta is from the start of __TEXT
+            let mut address = self.segments.iter()
+                .find(|segment| segment.fileoff == 0 && segment.filesize != 0)
+                .map(|segment| segment.vmaddr)
+                .unwrap_or(0);
+            let end = command.dataoff as usize + command.datasize as usize;
+            let offset = &mut (command.dataoff as usize);
+            while *offset < end {
+                let delta = Uleb128::read(&self.data, offset)?;
+                if delta == 0 {
+                    break;
+                }
+                address = address.wrapping_add(delta);
+                starts.push(address);
+            }
+        }
+        Ok(starts)
+    }
+    /// Return the thread states of every `LC_THREAD` and `LC_UNIXTHREAD`, one list per thread; for a core file
+    /// (`MH_CORE`), these are the registers of every thread at the time it was dumped
+    pub fn threads(&self) -> error::Result<Vec<Vec<thread_status::ThreadState>>> {
+        let mut threads = Vec::new();
+        for lc in &self.load_commands {
+            match lc.command {
+                  load_command::CommandVariant::Thread(_)
+                | load_command::CommandVariant::Unixthread(_) => {
+                    threads.push(thread_status::parse(&self.data[lc.offset..], self.header.cputype, self.ctx.le)?);
+                },
+                _ => (),
+            }
+        }
+        Ok(threads)
+    }
+    /// Return the ranges of data, e.g., jump tables, in code sections `LC_DATA_IN_CODE` lists (if any)
+    pub fn data_in_code(&self) -> error::Result<Vec<load_command::DataInCodeEntry>> {
+        let mut entries = Vec::new();
+        if let Some(command) = self.data_in_code {
+            let offset = &mut (command.dataoff as usize);
+            for _ in 0..command.datasize as usize / load_command::SIZEOF_DATA_IN_CODE_ENTRY {
+                entries.push(self.data.gread_with(offset, self.ctx.le)?);
+            }
+        }
+        Ok(entries)
+    }
     /// Return the exported symbols in this binary (if any)
     pub fn exports(&self) -> error::Result<Vec<exports::Export>> {
         if let Some(ref trie) = self.export_trie {
@@ -104,13 +290,74 @@ impl<'a> MachO<'a> {
             Ok(vec![])
         }
     }
-    /// Return the imported symbols in this binary that dyld knows about (if any)
+    /// Return the imported symbols in this binary that dyld knows about (if any), from its bind opcodes or fixup chains
     pub fn imports(&self) -> error::Result<Vec<imports::Import>> {
+        let mut imports = Vec::new();
         if let Some(ref interpreter) = self.bind_interpreter {
-            interpreter.imports(self.libs.as_slice(), self.segments.as_slice(), &self.ctx)
-        } else {
-            Ok(vec![])
+            imports.extend(interpreter.imports(self.libs.as_slice(), self.segments.as_slice(), &self.ctx)?);
+        }
+        if let Some(ref chained_fixups) = self.chained_fixups {
+            imports.extend(chained_fixups.imports(self.libs.as_slice(), self.segments.as_slice())?);
         }
+        Ok(imports)
+    }
+    /// Return the pointers in this binary that dyld rebases (if any), from its rebase opcodes or fixup chains
+    pub fn rebases(&self) -> error::Result<Vec<rebases::Rebase>> {
+        let mut rebases = Vec::new();
+        if let Some(ref interpreter) = self.rebase_interpreter {
+            rebases.extend(interpreter.rebases(self.segments.as_slice(), &self.ctx)?);
+        }
+        if let Some(ref chained_fixups) = self.chained_fixups {
+            rebases.extend(chained_fixups.rebases(self.segments.as_slice())?);
+        }
+        Ok(rebases)
+    }
+    /// The `PLATFORM_*`, minimum OS version and SDK version this binary was built for, from its first
+    /// `LC_BUILD_VERSION`, or else its `LC_VERSION_MIN_*`
+    fn version_command(&self) -> Option<(u32, u32, u32)> {
+        use self::load_command::CommandVariant::*;
+        let mut version_min = None;
+        for cmd in &self.load_commands {
+            match cmd.command {
+                BuildVersion(command) => return Some((command.platform, command.minos, command.sdk)),
+                VersionMinMacosx(command) if version_min.is_none() => version_min = Some((load_command::PLATFORM_MACOS, command.version, command.sdk)),
+                VersionMinIphoneos(command) if version_min.is_none() => version_min = Some((load_command::PLATFORM_IOS, command.version, command.sdk)),
+                VersionMinTvos(command) if version_min.is_none() => version_min = Some((load_command::PLATFORM_TVOS, command.version, command.sdk)),
+                VersionMinWatchos(command) if version_min.is_none() => version_min = Some((load_command::PLATFORM_WATCHOS, command.version, command.sdk)),
+                _ => (),
+            }
+        }
+        version_min
+    }
+    /// The `PLATFORM_*` this binary was built for (if known)
+    pub fn platform(&self) -> Option<u32> {
+        self.version_command().map(|(platform, _, _)| platform)
+    }
+    /// The minimum OS version this binary runs on (if known)
+    pub fn min_os_version(&self) -> Option<load_command::Version> {
+        self.version_command().map(|(_, minos, _)| minos.into())
+    }
+    /// The version of the SDK this binary was built with (if known)
+    pub fn sdk_version(&self) -> Option<load_command::Version> {
+        self.version_command().map(|(_, _, sdk)| sdk.into())
+    }
+    /// Return the tools the first `LC_BUILD_VERSION` says built this binary (if any)
+    pub fn build_tools(&self) -> error::Result<Vec<load_command::BuildToolVersion>> {
+        let mut tools = Vec::new();
+        for cmd in &self.load_commands {
+            if let load_command::CommandVariant::BuildVersion(command) = cmd.command {
+                let offset = &mut (cmd.offset + load_command::SIZEOF_BUILD_VERSION_COMMAND);
+                for _ in 0..command.ntools {
+                    tools.push(self.data.gread_with(offset, self.ctx.le)?);
+                }
+                break;
+            }
+        }
+        Ok(tools)
+    }
+    /// Return the embedded code signature (if any)
+    pub fn code_signature(&self) -> Option<&codesign::CodeSignature<'a>> {
+        self.code_signature.as_ref()
     }
     /// Parses the Mach-o binary from `bytes` at `offset`
     pub fn parse(bytes: &'a [u8], mut offset: usize) -> error::Result<MachO<'a>> {
@@ -126,6 +373,12 @@ impl<'a> MachO<'a> {
         let mut libs = vec!["self"];
         let mut export_trie = None;
         let mut bind_interpreter = None;
+        let mut chained_fixups = None;
+        let mut rebase_interpreter = None;
+        let mut code_signature = None;
+        let mut dysymtab = None;
+        let mut function_starts = None;
+        let mut data_in_code = None;
         let mut unixthread_entry_address = None;
         let mut main_entry_offset = None;
         let mut name = None;
@@ -141,8 +394,18 @@ impl<'a> MachO<'a> {
                 },
                 load_command::CommandVariant::Symtab(command) => {
                     symbols = Some(symbols::Symbols::parse(bytes, &command, ctx)?);
+                },
+                load_command::CommandVariant::Dysymtab(command) => {
+                    dysymtab = Some(command);
+                },
+                load_command::CommandVariant::FunctionStarts(command) => {
+                    function_starts = Some(command);
+                },
+                load_command::CommandVariant::DataInCode(command) => {
+                    data_in_code = Some(command);
                 },
                   load_command::CommandVariant::LoadDylib      (command)
+                | load_command::CommandVariant::LoadWeakDylib  (command)
                 | load_command::CommandVariant::LoadUpwardDylib(command)
                 | load_command::CommandVariant::ReexportDylib  (command)
                 | load_command::CommandVariant::LazyLoadDylib  (command) => {
@@ -153,6 +416,16 @@ impl<'a> MachO<'a> {
                 | load_command::CommandVariant::DyldInfoOnly(command) => {
                     export_trie = Some(exports::ExportTrie::new(bytes, &command));
                     bind_interpreter = Some(imports::BindInterpreter::new(bytes, &command));
+                    rebase_interpreter = Some(rebases::RebaseInterpreter::new(bytes, &command));
+                },
+                load_command::CommandVariant::DyldExportsTrie(command) => {
+                    export_trie = Some(exports::ExportTrie::new_from_linkedit_data_command(bytes, &command)?);
+                },
+                load_command::CommandVariant::DyldChainedFixups(command) => {
+                    chained_fixups = Some(chained_fixups::ChainedFixups::new(bytes, &command, ctx.le));
+                },
+                load_command::CommandVariant::CodeSignature(command) => {
+                    code_signature = Some(codesign::CodeSignature::new(bytes, &command));
                 },
                 load_command::CommandVariant::Unixthread(command) => {
                     // dyld cares only about the first LC_UNIXTHREAD
@@ -203,6 +476,12 @@ impl<'a> MachO<'a> {
             libs: libs,
             export_trie: export_trie,
             bind_interpreter: bind_interpreter,
+            chained_fixups: chained_fixups,
+            rebase_interpreter: rebase_interpreter,
+            code_signature: code_signature,
+            dysymtab: dysymtab,
+            function_starts: function_starts,
+            data_in_code: data_in_code,
             entry: entry,
             old_style_entry: old_style_entry,
             name: name,
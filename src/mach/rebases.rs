@@ -0,0 +1,245 @@
+//! Pointers in this binary which the dynamic linker slides when the binary is not loaded at its preferred address
+
+use core::ops::Range;
+use core::fmt::{self, Debug};
+use scroll::{Pread, Uleb128};
+
+use container;
+use error;
+use mach::load_command;
+use mach::rebase_opcodes;
+use mach::segment;
+use mach::chained_fixups::PointerAuth;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A pointer in this binary the dynamic linker slides by the difference between the actual and preferred load address
+pub struct Rebase {
+    /// The index of the segment this pointer is found in
+    pub seg_index: usize,
+    /// The offset of this pointer in its segment
+    pub seg_offset: u64,
+    /// The `REBASE_TYPE_*` of this pointer
+    pub rebase_type: u8,
+    /// The offset in the binary this pointer is found
+    pub offset: u64,
+    /// The virtual memory address at which this pointer is found
+    pub address: u64,
+    /// The virtual memory address this pointer points to, at the preferred load address
+    pub target: u64,
+    /// The size of this pointer
+    pub size: usize,
+    /// How this pointer is signed, if it is an arm64e authenticated pointer
+    pub auth: Option<PointerAuth>,
+}
+
+impl Rebase {
+    /// Create a new rebase of the pointer at `seg_offset` in segment `seg_index`, reading its target from `bytes`
+    fn new(bytes: &[u8], seg_index: usize, seg_offset: u64, rebase_type: u8, segments: &[segment::Segment], ctx: &container::Ctx) -> error::Result<Self> {
+        let segment = match segments.get(seg_index) {
+            Some(segment) => segment,
+            None => return Err(error::Error::Malformed(format!("Rebase in segment {} but there are only {} segments", seg_index, segments.len()))),
+        };
+        let size = if rebase_type == rebase_opcodes::REBASE_TYPE_POINTER && ctx.is_big() { 8 } else { 4 };
+        match seg_offset.checked_add(size as u64) {
+            Some(end) if end <= segment.filesize && end <= segment.vmsize => (),
+            _ => return Err(error::Error::Malformed(format!("Rebase at offset {:#x} is outside of segment {}", seg_offset, seg_index))),
+        }
+        let offset = match segment.fileoff.checked_add(seg_offset) {
+            Some(offset) => offset,
+            None => return Err(error::Error::Malformed(format!("Rebase at offset {:#x} of segment {} overflows", seg_offset, seg_index))),
+        };
+        let address = segment.vmaddr.wrapping_add(seg_offset);
+        let target = match rebase_type {
+            rebase_opcodes::REBASE_TYPE_POINTER if ctx.is_big() => bytes.pread_with::<u64>(offset as usize, ctx.le)?,
+            rebase_opcodes::REBASE_TYPE_TEXT_PCREL32 => {
+                let displacement = bytes.pread_with::<i32>(offset as usize, ctx.le)?;
+                address.wrapping_add(4).wrapping_add(displacement as i64 as u64)
+            },
+            _ => bytes.pread_with::<u32>(offset as usize, ctx.le)? as u64,
+        };
+        Ok(Rebase {
+            seg_index: seg_index,
+            seg_offset: seg_offset,
+            rebase_type: rebase_type,
+            offset: offset,
+            address: address,
+            target: target,
+            size: size,
+            auth: None,
+        })
+    }
+}
+
+/// Advances `seg_offset` by `by`, which must not overflow
+fn advance(seg_offset: u64, by: u64) -> error::Result<u64> {
+    seg_offset.checked_add(by).ok_or_else(|| error::Error::Malformed(format!("Rebase offset {:#x} plus {:#x} overflows", seg_offset, by)))
+}
+
+/// Checks that `count` pointers of `size`, `stride` bytes apart from `seg_offset` on, fit in segment `seg_index`
+fn check_count(segments: &[segment::Segment], seg_index: usize, seg_offset: u64, count: u64, size: u64, stride: u64) -> error::Result<()> {
+    let left = match segments.get(seg_index) {
+        Some(segment) if seg_offset.saturating_add(size) <= segment.filesize => (segment.filesize - seg_offset - size) / stride + 1,
+        _ => 0,
+    };
+    if count > left {
+        return Err(error::Error::Malformed(format!("{} rebases at offset {:#x} do not fit in segment {}", count, seg_offset, seg_index)));
+    }
+    Ok(())
+}
+
+/// An interpreter for mach REBASE opcodes, which finds every pointer `LC_DYLD_INFO(_ONLY)` says must be slid
+pub struct RebaseInterpreter<'a> {
+    data: &'a [u8],
+    location: Range<usize>,
+}
+
+impl<'a> Debug for RebaseInterpreter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "RebaseInterpreter {{")?;
+        writeln!(fmt, "  Location: {:#x}..{:#x}", self.location.start, self.location.end)?;
+        writeln!(fmt, "}}")
+    }
+}
+
+impl<'a> RebaseInterpreter<'a> {
+    /// Construct a new rebase interpreter from `bytes` and the load `command`
+    pub fn new(bytes: &'a [u8], command: &load_command::DyldInfoCommand) -> Self {
+        let start = command.rebase_off as usize;
+        RebaseInterpreter {
+            data: bytes,
+            location: start..start + command.rebase_size as usize,
+        }
+    }
+    /// Return the rebased pointers in this binary
+    pub fn rebases(&self, segments: &[segment::Segment], ctx: &container::Ctx) -> error::Result<Vec<Rebase>> {
+        use mach::rebase_opcodes::*;
+        let mut rebases = Vec::new();
+        let mut rebase_type = REBASE_TYPE_POINTER;
+        let mut seg_index = 0;
+        let mut seg_offset = 0u64;
+        let size = ctx.size() as u64;
+        let offset = &mut self.location.start.clone();
+        while *offset < self.location.end {
+            let opcode = self.data.gread::<u8>(offset)?;
+            let immediate = opcode & REBASE_IMMEDIATE_MASK;
+            match opcode & REBASE_OPCODE_MASK {
+                REBASE_OPCODE_DONE => break,
+                REBASE_OPCODE_SET_TYPE_IMM => {
+                    rebase_type = immediate;
+                },
+                REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB => {
+                    seg_index = immediate as usize;
+                    seg_offset = Uleb128::read(&self.data, offset)?;
+                },
+                REBASE_OPCODE_ADD_ADDR_ULEB => {
+                    seg_offset = advance(seg_offset, Uleb128::read(&self.data, offset)?)?;
+                },
+                REBASE_OPCODE_ADD_ADDR_IMM_SCALED => {
+                    seg_offset = advance(seg_offset, immediate as u64 * size)?;
+                },
+                REBASE_OPCODE_DO_REBASE_IMM_TIMES => {
+                    for _ in 0..immediate {
+                        rebases.push(Rebase::new(self.data, seg_index, seg_offset, rebase_type, segments, ctx)?);
+                        seg_offset = advance(seg_offset, size)?;
+                    }
+                },
+                REBASE_OPCODE_DO_REBASE_ULEB_TIMES => {
+                    let count = Uleb128::read(&self.data, offset)?;
+                    check_count(segments, seg_index, seg_offset, count, size, size)?;
+                    for _ in 0..count {
+                        rebases.push(Rebase::new(self.data, seg_index, seg_offset, rebase_type, segments, ctx)?);
+                        seg_offset = advance(seg_offset, size)?;
+                    }
+                },
+                REBASE_OPCODE_DO_REBASE_ADD_ADDR_ULEB => {
+                    rebases.push(Rebase::new(self.data, seg_index, seg_offset, rebase_type, segments, ctx)?);
+                    seg_offset = advance(seg_offset, Uleb128::read(&self.data, offset)?)?;
+                    seg_offset = advance(seg_offset, size)?;
+                },
+                REBASE_OPCODE_DO_REBASE_ULEB_TIMES_SKIPPING_ULEB => {
+                    let count = Uleb128::read(&self.data, offset)?;
+                    let skip = Uleb128::read(&self.data, offset)?;
+                    let stride = advance(skip, size)?;
+                    check_count(segments, seg_index, seg_offset, count, size, stride)?;
+                    for _ in 0..count {
+                        rebases.push(Rebase::new(self.data, seg_index, seg_offset, rebase_type, segments, ctx)?);
+                        seg_offset = advance(seg_offset, stride)?;
+                    }
+                },
+                _ => {
+                    return Err(error::Error::Malformed(format!("Unknown rebase opcode {:#x} at {:#x}", opcode, *offset - 1)));
+                }
+            }
+        }
+        Ok(rebases)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::{self, Pwrite};
+
+    #[test]
+    fn rebase_opcodes() {
+        use mach::rebase_opcodes::*;
+        let ctx = container::Ctx { container: container::Container::Big, le: scroll::LE };
+        let mut bytes = vec![0u8; 0x100];
+        for (i, target) in [0x1000u64, 0x1008, 0x1010, 0x1040].iter().enumerate() {
+            bytes.pwrite_with(*target, 0x80 + i * 8, scroll::LE).unwrap();
+        }
+        // four consecutive pointers at the start of __DATA, in three runs; interpretation stops at DONE
+        let opcodes = [
+            REBASE_OPCODE_SET_TYPE_IMM | REBASE_TYPE_POINTER,
+            REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 1, 0x00,
+            REBASE_OPCODE_DO_REBASE_IMM_TIMES | 2,
+            REBASE_OPCODE_DO_REBASE_ADD_ADDR_ULEB, 0x00,
+            REBASE_OPCODE_DO_REBASE_ULEB_TIMES, 0x01,
+            REBASE_OPCODE_DONE,
+            REBASE_OPCODE_DO_REBASE_IMM_TIMES | 1,
+        ];
+        bytes[..opcodes.len()].copy_from_slice(&opcodes);
+        let mut command = load_command::DyldInfoCommand::default();
+        command.rebase_size = opcodes.len() as u32;
+
+        let mut text = segment::Segment::new(ctx, &[]);
+        text.vmaddr = 0x1000;
+        let mut data = segment::Segment::new(ctx, &[]);
+        data.vmaddr = 0x2000;
+        data.vmsize = 0x1000;
+        data.fileoff = 0x80;
+        data.filesize = 0x20;
+        let segments = [text, data];
+        let rebases = RebaseInterpreter::new(&bytes, &command).rebases(&segments, &ctx).unwrap();
+        assert_eq!(rebases.len(), 4);
+        assert_eq!(rebases.iter().map(|rebase| rebase.address).collect::<Vec<_>>(), vec![0x2000, 0x2008, 0x2010, 0x2018]);
+        assert_eq!(rebases[3].seg_index, 1);
+        assert_eq!(rebases[3].offset, 0x98);
+        assert_eq!(rebases[3].target, 0x1040);
+
+        // a skip wrapping around to the same pointer, many times over
+        let opcodes = [
+            REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 1, 0x00,
+            REBASE_OPCODE_DO_REBASE_ULEB_TIMES_SKIPPING_ULEB, 0xff, 0xff, 0xff, 0xff, 0x0f, 0xf8, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01,
+        ];
+        bytes[..opcodes.len()].copy_from_slice(&opcodes);
+        command.rebase_size = opcodes.len() as u32;
+        assert!(RebaseInterpreter::new(&bytes, &command).rebases(&segments, &ctx).is_err());
+        // more pointers than the segment holds
+        let opcodes = [
+            REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 1, 0x08,
+            REBASE_OPCODE_DO_REBASE_ULEB_TIMES, 0x04,
+        ];
+        bytes[..opcodes.len()].copy_from_slice(&opcodes);
+        command.rebase_size = opcodes.len() as u32;
+        assert!(RebaseInterpreter::new(&bytes, &command).rebases(&segments, &ctx).is_err());
+        // past the end of the segment
+        let opcodes = [
+            REBASE_OPCODE_SET_SEGMENT_AND_OFFSET_ULEB | 1, 0x20,
+            REBASE_OPCODE_DO_REBASE_IMM_TIMES | 1,
+        ];
+        bytes[..opcodes.len()].copy_from_slice(&opcodes);
+        command.rebase_size = opcodes.len() as u32;
+        assert!(RebaseInterpreter::new(&bytes, &command).rebases(&segments, &ctx).is_err());
+    }
+}
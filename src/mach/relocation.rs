@@ -22,10 +22,36 @@
 // sections.  And further could have still different ordinals when combined
 // by the link-editor.  The value R_ABS is used for relocation entries for
 // absolute symbols which need no further relocation.
+
+// To make scattered loading by the link editor work correctly "local"
+// relocation entries can't be used when the item to be relocated is the value
+// of a symbol plus an offset (where the resulting expression is outside the
+// block the link editor is moving, a blocks are divided at symbol addresses).
+// In this case, where the item is a symbol value plus offset, the link editor
+// needs to know more than just the section the symbol was defined.  What is
+// needed is the actual value of the symbol without the offset so it can do the
+// relocation correctly based on where the value of the symbol got relocated to
+// not the value of the expression (with the offset added to the symbol value).
+// So for the NeXT 2.0 release no "local" relocation entries are ever used when
+// there is a non-zero offset added to a symbol.  The "external" and "local"
+// relocation entries remain unchanged.
+//
+// The implementation is quite messy given the compatibility with the existing
+// relocation entry format.  The ASSUMPTION is that a section will never be
+// bigger than 2**24 - 1 (0x00ffffff or 16,777,215) bytes.  This assumption
+// allows the r_address (which is really an offset) to fit in 24 bits and high
+// bit of the r_address field in the relocation_info structure to indicate
+// it is really a scattered_relocation_info structure.  Since these are only
+// used in places where "local" relocation entries are used and not where
+// "external" relocation entries are used the r_extern field has been removed.
+// Scattered relocation entries are only used by 32-bit architectures.
 use core::fmt;
 use mach;
+use mach::cputype::{CpuType, CPU_ARCH_ABI64};
+use mach::segment;
+use mach::symbols::{Symbols, Nlist};
+use error;
 
-// TODO: armv7 relocations are scattered, must and r_address with 0x8000_0000 to check if its scattered or not
 #[derive(Copy, Clone, Pread, Pwrite, IOwrite, IOread)]
 #[repr(C)]
 pub struct RelocationInfo {
@@ -74,12 +100,52 @@ impl RelocationInfo {
     }
     /// Returns a string representation of this relocation, given the machine `cputype`
     pub fn to_str(&self, cputype: mach::cputype::CpuType) -> &'static str {
-        reloc_to_str(self.r_type(), cputype)
+        match self.scattered() {
+            Some(scattered) if cputype & CPU_ARCH_ABI64 == 0 => reloc_to_str(scattered.r_type, cputype),
+            _ => reloc_to_str(self.r_type(), cputype),
+        }
+    }
+    /// If true, this is a `scattered_relocation_info`, which only 32-bit architectures use
+    #[inline]
+    pub fn is_scattered(&self) -> bool {
+        self.r_address as u32 & R_SCATTERED != 0
+    }
+    /// Reinterprets this as a `scattered_relocation_info`, if it is one
+    pub fn scattered(&self) -> Option<ScatteredRelocationInfo> {
+        if self.is_scattered() {
+            let info = self.r_address as u32;
+            Some(ScatteredRelocationInfo {
+                r_address: info & 0x00ff_ffff,
+                r_type: ((info >> 24) & 0xf) as u8,
+                r_length: ((info >> 28) & 0x3) as u8,
+                r_pcrel: ((info >> 30) & 0x1) as u8,
+                r_value: self.r_info,
+            })
+        } else {
+            None
+        }
     }
 }
 
 /// Absolute relocation type for Mach-O files
 pub const R_ABS: u8 = 0;
+/// The bit of `r_address` set in a scattered relocation
+pub const R_SCATTERED: u32 = 0x8000_0000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A scattered relocation, which relocates an address rather than a symbol or section
+pub struct ScatteredRelocationInfo {
+    /// Offset in the section to what is being relocated, 24 bits
+    pub r_address: u32,
+    /// Machine specific relocation type, 4 bits
+    pub r_type: u8,
+    /// The length of the relocation, 0=byte, 1=word, 2=long, 3=quad, 2 bits
+    pub r_length: u8,
+    /// Was relocated pc relative already, 1 bit
+    pub r_pcrel: u8,
+    /// The address of the item being relocated, before relocation
+    pub r_value: u32,
+}
 
 impl fmt::Debug for RelocationInfo {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -122,6 +188,7 @@ pub const GENERIC_RELOC_PAIR: u8 = 1;
 pub const GENERIC_RELOC_SECTDIFF: u8 = 2;
 pub const GENERIC_RELOC_LOCAL_SECTDIFF: u8 = 3;
 pub const GENERIC_RELOC_PB_LA_P: u8 = 4;
+pub const GENERIC_RELOC_TLV: u8 = 5;
 
 // arm relocations
 pub const ARM_RELOC_VANILLA: u8 = GENERIC_RELOC_VANILLA;
@@ -158,6 +225,8 @@ pub const ARM64_RELOC_TLVP_LOAD_PAGE21: u8 = 8;
 pub const ARM64_RELOC_TLVP_LOAD_PAGEOFF12: u8 = 9;
 /// Must be followed by ARM64_RELOC_PAGE21 or ARM64_RELOC_PAGEOFF12.
 pub const ARM64_RELOC_ADDEND: u8 = 10;
+/// An authenticated pointer, for arm64e.
+pub const ARM64_RELOC_AUTHENTICATED_POINTER: u8 = 11;
 
 pub fn reloc_to_str(reloc: u8, cputype: mach::cputype::CpuType) -> &'static str {
     use mach::constants::cputype::*;
@@ -175,6 +244,7 @@ pub fn reloc_to_str(reloc: u8, cputype: mach::cputype::CpuType) -> &'static str
                 ARM64_RELOC_TLVP_LOAD_PAGE21 => "ARM64_RELOC_TLVP_LOAD_PAGE21",
                 ARM64_RELOC_TLVP_LOAD_PAGEOFF12 => "ARM64_RELOC_TLVP_LOAD_PAGEOFF12",
                 ARM64_RELOC_ADDEND => "ARM64_RELOC_ADDEND",
+                ARM64_RELOC_AUTHENTICATED_POINTER => "ARM64_RELOC_AUTHENTICATED_POINTER",
                 _ => "UNKNOWN",
             }
         },
@@ -215,9 +285,469 @@ pub fn reloc_to_str(reloc: u8, cputype: mach::cputype::CpuType) -> &'static str
                 GENERIC_RELOC_SECTDIFF => "GENERIC_RELOC_SECTDIFF",
                 GENERIC_RELOC_LOCAL_SECTDIFF => "GENERIC_RELOC_LOCAL_SECTDIFF",
                 GENERIC_RELOC_PB_LA_P => "GENERIC_RELOC_PB_LA_P",
+                GENERIC_RELOC_TLV => "GENERIC_RELOC_TLV",
                 _ => "UNKNOWN",
             }
         },
         _ => "BAD_CPUTYPE"
     }
 }
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The `GENERIC_RELOC_*` relocation types of x86
+pub enum GenericRelocation {
+    Vanilla,
+    Pair,
+    SectDiff,
+    LocalSectDiff,
+    PbLaPtr,
+    Tlv,
+    Unknown(u8),
+}
+
+impl From<u8> for GenericRelocation {
+    fn from(r_type: u8) -> Self {
+        match r_type {
+            GENERIC_RELOC_VANILLA => GenericRelocation::Vanilla,
+            GENERIC_RELOC_PAIR => GenericRelocation::Pair,
+            GENERIC_RELOC_SECTDIFF => GenericRelocation::SectDiff,
+            GENERIC_RELOC_LOCAL_SECTDIFF => GenericRelocation::LocalSectDiff,
+            GENERIC_RELOC_PB_LA_P => GenericRelocation::PbLaPtr,
+            GENERIC_RELOC_TLV => GenericRelocation::Tlv,
+            r_type => GenericRelocation::Unknown(r_type),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The `X86_64_RELOC_*` relocation types
+pub enum X86_64Relocation {
+    Unsigned,
+    Signed,
+    Branch,
+    GotLoad,
+    Got,
+    Subtractor,
+    Signed1,
+    Signed2,
+    Signed4,
+    Tlv,
+    Unknown(u8),
+}
+
+impl From<u8> for X86_64Relocation {
+    fn from(r_type: u8) -> Self {
+        match r_type {
+            X86_64_RELOC_UNSIGNED => X86_64Relocation::Unsigned,
+            X86_64_RELOC_SIGNED => X86_64Relocation::Signed,
+            X86_64_RELOC_BRANCH => X86_64Relocation::Branch,
+            X86_64_RELOC_GOT_LOAD => X86_64Relocation::GotLoad,
+            X86_64_RELOC_GOT => X86_64Relocation::Got,
+            X86_64_RELOC_SUBTRACTOR => X86_64Relocation::Subtractor,
+            X86_64_RELOC_SIGNED_1 => X86_64Relocation::Signed1,
+            X86_64_RELOC_SIGNED_2 => X86_64Relocation::Signed2,
+            X86_64_RELOC_SIGNED_4 => X86_64Relocation::Signed4,
+            X86_64_RELOC_TLV => X86_64Relocation::Tlv,
+            r_type => X86_64Relocation::Unknown(r_type),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The `ARM_RELOC_*` relocation types
+pub enum ArmRelocation {
+    Vanilla,
+    Pair,
+    SectDiff,
+    LocalSectDiff,
+    PbLaPtr,
+    Br24,
+    ThumbBr22,
+    Thumb32BitBranch,
+    Half,
+    HalfSectDiff,
+    Unknown(u8),
+}
+
+impl From<u8> for ArmRelocation {
+    fn from(r_type: u8) -> Self {
+        match r_type {
+            ARM_RELOC_VANILLA => ArmRelocation::Vanilla,
+            ARM_RELOC_PAIR => ArmRelocation::Pair,
+            ARM_RELOC_SECTDIFF => ArmRelocation::SectDiff,
+            ARM_RELOC_LOCAL_SECTDIFF => ArmRelocation::LocalSectDiff,
+            ARM_RELOC_PB_LA_PTR => ArmRelocation::PbLaPtr,
+            ARM_RELOC_BR24 => ArmRelocation::Br24,
+            ARM_THUMB_RELOC_BR22 => ArmRelocation::ThumbBr22,
+            ARM_THUMB_32BIT_BRANCH => ArmRelocation::Thumb32BitBranch,
+            ARM_RELOC_HALF => ArmRelocation::Half,
+            ARM_RELOC_HALF_SECTDIFF => ArmRelocation::HalfSectDiff,
+            r_type => ArmRelocation::Unknown(r_type),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// The `ARM64_RELOC_*` relocation types
+pub enum Arm64Relocation {
+    Unsigned,
+    Subtractor,
+    Branch26,
+    Page21,
+    PageOff12,
+    GotLoadPage21,
+    GotLoadPageOff12,
+    PointerToGot,
+    TlvpLoadPage21,
+    TlvpLoadPageOff12,
+    Addend,
+    AuthenticatedPointer,
+    Unknown(u8),
+}
+
+impl From<u8> for Arm64Relocation {
+    fn from(r_type: u8) -> Self {
+        match r_type {
+            ARM64_RELOC_UNSIGNED => Arm64Relocation::Unsigned,
+            ARM64_RELOC_SUBTRACTOR => Arm64Relocation::Subtractor,
+            ARM64_RELOC_BRANCH26 => Arm64Relocation::Branch26,
+            ARM64_RELOC_PAGE21 => Arm64Relocation::Page21,
+            ARM64_RELOC_PAGEOFF12 => Arm64Relocation::PageOff12,
+            ARM64_RELOC_GOT_LOAD_PAGE21 => Arm64Relocation::GotLoadPage21,
+            ARM64_RELOC_GOT_LOAD_PAGEOFF12 => Arm64Relocation::GotLoadPageOff12,
+            ARM64_RELOC_POINTER_TO_GOT => Arm64Relocation::PointerToGot,
+            ARM64_RELOC_TLVP_LOAD_PAGE21 => Arm64Relocation::TlvpLoadPage21,
+            ARM64_RELOC_TLVP_LOAD_PAGEOFF12 => Arm64Relocation::TlvpLoadPageOff12,
+            ARM64_RELOC_ADDEND => Arm64Relocation::Addend,
+            ARM64_RELOC_AUTHENTICATED_POINTER => Arm64Relocation::AuthenticatedPointer,
+            r_type => Arm64Relocation::Unknown(r_type),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A relocation type, typed for the machine `cputype`
+pub enum RelocationKind {
+    X86(GenericRelocation),
+    X86_64(X86_64Relocation),
+    Arm(ArmRelocation),
+    Arm64(Arm64Relocation),
+    /// A relocation type of another machine
+    Unknown(u8),
+}
+
+impl RelocationKind {
+    /// Types the relocation type `r_type` for the machine `cputype`
+    pub fn new(cputype: CpuType, r_type: u8) -> Self {
+        use mach::cputype::*;
+        match cputype {
+            CPU_TYPE_X86 => RelocationKind::X86(r_type.into()),
+            CPU_TYPE_X86_64 => RelocationKind::X86_64(r_type.into()),
+            CPU_TYPE_ARM => RelocationKind::Arm(r_type.into()),
+            CPU_TYPE_ARM64 => RelocationKind::Arm64(r_type.into()),
+            _ => RelocationKind::Unknown(r_type),
+        }
+    }
+    /// Whether a relocation of this kind must be followed by another one, which it is combined with
+    pub fn is_paired(&self) -> bool {
+        match *self {
+              RelocationKind::X86(GenericRelocation::SectDiff)
+            | RelocationKind::X86(GenericRelocation::LocalSectDiff)
+            | RelocationKind::X86_64(X86_64Relocation::Subtractor)
+            | RelocationKind::Arm(ArmRelocation::SectDiff)
+            | RelocationKind::Arm(ArmRelocation::LocalSectDiff)
+            | RelocationKind::Arm(ArmRelocation::Half)
+            | RelocationKind::Arm(ArmRelocation::HalfSectDiff)
+            | RelocationKind::Arm64(Arm64Relocation::Subtractor)
+            | RelocationKind::Arm64(Arm64Relocation::Addend) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// What a relocation refers to
+pub enum RelocationTarget<'a> {
+    /// A symbol (`r_extern`), by its index in the symbol table, and the symbol itself if the symbol table is known
+    Symbol { index: usize, symbol: Option<(&'a str, Nlist)> },
+    /// A section, by its ordinal (starting at 1), and the address it starts at
+    Section { ordinal: usize, address: u64 },
+    /// An address, for scattered relocations
+    Address(u64),
+    /// Nothing, for relocations of absolute values (`R_ABS`)
+    Absolute,
+}
+
+impl<'a> RelocationTarget<'a> {
+    /// The name of the symbol referred to, if known
+    pub fn name(&self) -> Option<&'a str> {
+        match *self {
+            RelocationTarget::Symbol { symbol: Some((name, _)), .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// A relocation, with the relocation it is paired with (if any) combined into it
+pub struct Relocation<'a> {
+    /// Offset in the section to what is being relocated
+    pub offset: u32,
+    /// The type of the relocation; the second one of a `*_SUBTRACTOR` pair and the first one of an `ARM64_RELOC_ADDEND`
+    /// pair are folded into `target` and `addend`
+    pub kind: RelocationKind,
+    /// Was relocated pc relative already
+    pub pcrel: bool,
+    /// The length of the relocation, 0=byte, 1=word, 2=long, 3=quad; for `ARM_RELOC_HALF*`, bit 0 selects the high
+    /// 16 bits and bit 1 a Thumb instruction
+    pub length: u8,
+    /// Whether this was a scattered relocation
+    pub scattered: bool,
+    /// What the relocated item refers to
+    pub target: RelocationTarget<'a>,
+    /// What is subtracted from `target`, for `*_SUBTRACTOR` and `*_SECTDIFF` pairs
+    pub subtrahend: Option<RelocationTarget<'a>>,
+    /// The explicit addend of an `ARM64_RELOC_ADDEND` pair; other addends are in the relocated item itself
+    pub addend: i64,
+    /// The other 16 bits of the address, for `ARM_RELOC_HALF` and `ARM_RELOC_HALF_SECTDIFF` pairs
+    pub other_half: Option<u16>,
+}
+
+/// The fields of a plain or scattered relocation
+struct Fields {
+    offset: u32,
+    r_type: u8,
+    length: u8,
+    pcrel: bool,
+    scattered: Option<u32>,
+}
+
+fn fields(reloc: &RelocationInfo, cputype: CpuType) -> Fields {
+    match reloc.scattered() {
+        Some(scattered) if cputype & CPU_ARCH_ABI64 == 0 => Fields {
+            offset: scattered.r_address,
+            r_type: scattered.r_type,
+            length: scattered.r_length,
+            pcrel: scattered.r_pcrel != 0,
+            scattered: Some(scattered.r_value),
+        },
+        _ => Fields {
+            offset: reloc.r_address as u32,
+            r_type: reloc.r_type(),
+            length: reloc.r_length(),
+            pcrel: reloc.is_pic(),
+            scattered: None,
+        },
+    }
+}
+
+fn target<'a>(reloc: &RelocationInfo, fields: &Fields, symbols: Option<&Symbols<'a>>, sections: &[segment::Section]) -> error::Result<RelocationTarget<'a>> {
+    if let Some(value) = fields.scattered {
+        return Ok(RelocationTarget::Address(value as u64));
+    }
+    let index = reloc.r_symbolnum();
+    if reloc.is_extern() {
+        let symbol = match symbols {
+            Some(symbols) => Some(symbols.get(index)?),
+            None => None,
+        };
+        Ok(RelocationTarget::Symbol { index: index, symbol: symbol })
+    } else if index == R_ABS as usize {
+        Ok(RelocationTarget::Absolute)
+    } else {
+        match sections.get(index - 1) {
+            Some(section) => Ok(RelocationTarget::Section { ordinal: index, address: section.addr }),
+            None => Err(error::Error::Malformed(format!("Relocation at {:#x} refers to section {} but there are only {}", fields.offset, index, sections.len()))),
+        }
+    }
+}
+
+/// Types the `relocations` of a section for the machine `cputype`, combines pairs, and resolves them against the
+/// `symbols` and the `sections` (in order, for section ordinals) of the binary
+pub fn resolve<'a>(relocations: &[RelocationInfo], cputype: CpuType, symbols: Option<&Symbols<'a>>, sections: &[segment::Section]) -> error::Result<Vec<Relocation<'a>>> {
+    let mut resolved = Vec::with_capacity(relocations.len());
+    let mut i = 0;
+    while i < relocations.len() {
+        let reloc = &relocations[i];
+        let first = fields(reloc, cputype);
+        let kind = RelocationKind::new(cputype, first.r_type);
+        // an ARM64_RELOC_ADDEND holds its addend instead of a symbol or section
+        let first_target = if kind == RelocationKind::Arm64(Arm64Relocation::Addend) {
+            RelocationTarget::Absolute
+        } else {
+            target(reloc, &first, symbols, sections)?
+        };
+        let mut relocation = Relocation {
+            offset: first.offset,
+            kind: kind,
+            pcrel: first.pcrel,
+            length: first.length,
+            scattered: first.scattered.is_some(),
+            target: first_target,
+            subtrahend: None,
+            addend: 0,
+            other_half: None,
+        };
+        i += 1;
+        if kind.is_paired() {
+            let pair = match relocations.get(i) {
+                Some(pair) => pair,
+                None => return Err(error::Error::Malformed(format!("Relocation {} at {:#x} is missing its pair", reloc_to_str(first.r_type, cputype), first.offset))),
+            };
+            let second = fields(pair, cputype);
+            let pair_kind = RelocationKind::new(cputype, second.r_type);
+            i += 1;
+            match (kind, pair_kind) {
+                  (RelocationKind::X86_64(X86_64Relocation::Subtractor), RelocationKind::X86_64(X86_64Relocation::Unsigned))
+                | (RelocationKind::Arm64(Arm64Relocation::Subtractor), RelocationKind::Arm64(Arm64Relocation::Unsigned)) => {
+                    // target - subtrahend, the first relocation names the subtrahend
+                    relocation.subtrahend = Some(relocation.target);
+                    relocation.target = target(pair, &second, symbols, sections)?;
+                    relocation.length = second.length;
+                },
+                (RelocationKind::Arm64(Arm64Relocation::Addend), RelocationKind::Arm64(pair_type)) if pair_type != Arm64Relocation::Addend => {
+                    // the addend is the sign extended 24 bit r_symbolnum of the first relocation
+                    relocation = Relocation {
+                        offset: second.offset,
+                        kind: pair_kind,
+                        pcrel: second.pcrel,
+                        length: second.length,
+                        scattered: false,
+                        target: target(pair, &second, symbols, sections)?,
+                        subtrahend: None,
+                        addend: (((reloc.r_symbolnum() as u32) << 8) as i32 >> 8) as i64,
+                        other_half: None,
+                    };
+                },
+                  (RelocationKind::X86(_), RelocationKind::X86(GenericRelocation::Pair))
+                | (RelocationKind::Arm(_), RelocationKind::Arm(ArmRelocation::Pair)) => {
+                    if kind == RelocationKind::Arm(ArmRelocation::Half) || kind == RelocationKind::Arm(ArmRelocation::HalfSectDiff) {
+                        // the pair's address holds the other half of the relocated address
+                        relocation.other_half = Some(second.offset as u16);
+                    }
+                    if kind != RelocationKind::Arm(ArmRelocation::Half) {
+                        relocation.subtrahend = Some(target(pair, &second, symbols, sections)?);
+                    }
+                },
+                _ => return Err(error::Error::Malformed(format!("Relocation {} at {:#x} is paired with {}", reloc_to_str(first.r_type, cputype), first.offset, reloc_to_str(second.r_type, cputype)))),
+            }
+        }
+        resolved.push(relocation);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::{Pwrite, LE};
+    use container;
+    use mach::cputype::{CPU_TYPE_X86, CPU_TYPE_X86_64, CPU_TYPE_ARM, CPU_TYPE_ARM64};
+    use mach::load_command::SymtabCommand;
+    use mach::segment::Section;
+    use mach::symbols::{Nlist64, Symbols, SIZEOF_NLIST_64, N_SECT, N_EXT, N_UNDF};
+    use super::*;
+
+    fn reloc(r_address: u32, r_symbolnum: u32, pcrel: bool, length: u32, r_extern: bool, r_type: u8) -> RelocationInfo {
+        RelocationInfo {
+            r_address: r_address as i32,
+            r_info: r_symbolnum | (pcrel as u32) << 24 | length << 25 | (r_extern as u32) << 27 | (r_type as u32) << 28,
+        }
+    }
+
+    fn scattered(r_address: u32, pcrel: bool, length: u32, r_type: u8, r_value: u32) -> RelocationInfo {
+        RelocationInfo {
+            r_address: (R_SCATTERED | (pcrel as u32) << 30 | length << 28 | (r_type as u32) << 24 | r_address) as i32,
+            r_info: r_value,
+        }
+    }
+
+    fn sections() -> Vec<Section> {
+        let mut text = Section::default();
+        text.addr = 0x0;
+        let mut data = Section::default();
+        data.addr = 0x100;
+        vec![text, data]
+    }
+
+    #[test]
+    fn x86_64_subtractor() {
+        // _start, defined in __text, and _puts, undefined
+        let strtab = b"\0_start\0_puts\0";
+        let mut bytes = vec![0u8; 2 * SIZEOF_NLIST_64 + strtab.len()];
+        bytes.pwrite_with(Nlist64 { n_strx: 1, n_type: N_SECT | N_EXT, n_sect: 1, n_desc: 0, n_value: 0x10 }, 0, LE).unwrap();
+        bytes.pwrite_with(Nlist64 { n_strx: 8, n_type: N_UNDF | N_EXT, n_sect: 0, n_desc: 0, n_value: 0 }, SIZEOF_NLIST_64, LE).unwrap();
+        bytes[2 * SIZEOF_NLIST_64..].copy_from_slice(strtab);
+        let mut symtab = SymtabCommand::new();
+        symtab.nsyms = 2;
+        symtab.stroff = 2 * SIZEOF_NLIST_64 as u32;
+        let symbols = Symbols::parse(&bytes, &symtab, container::Ctx::new(container::Container::Big, LE)).unwrap();
+
+        let relocs = [
+            reloc(0x8, 0, false, 3, true, X86_64_RELOC_SUBTRACTOR),
+            reloc(0x8, 2, false, 3, false, X86_64_RELOC_UNSIGNED),
+            reloc(0x21, 1, true, 2, true, X86_64_RELOC_BRANCH),
+            reloc(0x30, 0, false, 3, false, X86_64_RELOC_UNSIGNED),
+        ];
+        let resolved = resolve(&relocs, CPU_TYPE_X86_64, Some(&symbols), &sections()).unwrap();
+        assert_eq!(resolved.len(), 3);
+        assert_eq!(resolved[0].kind, RelocationKind::X86_64(X86_64Relocation::Subtractor));
+        assert_eq!(resolved[0].target, RelocationTarget::Section { ordinal: 2, address: 0x100 });
+        assert_eq!(resolved[0].subtrahend.unwrap().name(), Some("_start"));
+        assert_eq!(resolved[1].kind, RelocationKind::X86_64(X86_64Relocation::Branch));
+        assert_eq!((resolved[1].offset, resolved[1].pcrel, resolved[1].length), (0x21, true, 2));
+        assert_eq!(resolved[1].target.name(), Some("_puts"));
+        assert_eq!(resolved[2].target, RelocationTarget::Absolute);
+
+        // a subtractor must be followed by an unsigned relocation
+        assert!(resolve(&relocs[..1], CPU_TYPE_X86_64, Some(&symbols), &sections()).is_err());
+        assert!(resolve(&relocs[1..2], CPU_TYPE_X86_64, Some(&symbols), &[]).is_err());
+    }
+
+    #[test]
+    fn arm64_addend() {
+        let relocs = [
+            reloc(0x4, 0xff_fff0, false, 2, false, ARM64_RELOC_ADDEND),
+            reloc(0x4, 3, true, 2, true, ARM64_RELOC_PAGE21),
+            reloc(0x8, 0x10, false, 2, false, ARM64_RELOC_ADDEND),
+            reloc(0x8, 3, false, 2, true, ARM64_RELOC_PAGEOFF12),
+        ];
+        let resolved = resolve(&relocs, CPU_TYPE_ARM64, None, &[]).unwrap();
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].kind, RelocationKind::Arm64(Arm64Relocation::Page21));
+        assert_eq!(resolved[0].addend, -0x10);
+        assert!(resolved[0].pcrel);
+        assert_eq!(resolved[0].target, RelocationTarget::Symbol { index: 3, symbol: None });
+        assert_eq!(resolved[1].kind, RelocationKind::Arm64(Arm64Relocation::PageOff12));
+        assert_eq!(resolved[1].addend, 0x10);
+    }
+
+    #[test]
+    fn scattered_pairs() {
+        let relocs = [
+            scattered(0x10, false, 2, GENERIC_RELOC_SECTDIFF, 0x120),
+            scattered(0x0, false, 2, GENERIC_RELOC_PAIR, 0x20),
+            reloc(0x14, 2, false, 2, false, GENERIC_RELOC_VANILLA),
+        ];
+        assert!(relocs[0].is_scattered() && !relocs[2].is_scattered());
+        assert_eq!(relocs[0].to_str(CPU_TYPE_X86), "GENERIC_RELOC_SECTDIFF");
+        let resolved = resolve(&relocs, CPU_TYPE_X86, None, &sections()).unwrap();
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[0].kind, RelocationKind::X86(GenericRelocation::SectDiff));
+        assert!(resolved[0].scattered);
+        assert_eq!((resolved[0].offset, resolved[0].length), (0x10, 2));
+        assert_eq!(resolved[0].target, RelocationTarget::Address(0x120));
+        assert_eq!(resolved[0].subtrahend, Some(RelocationTarget::Address(0x20)));
+        assert_eq!(resolved[1].target, RelocationTarget::Section { ordinal: 2, address: 0x100 });
+
+        // a movw of the low half of a symbol's address, with the high half in the pair
+        let relocs = [
+            reloc(0x8, 1, false, 0, true, ARM_RELOC_HALF),
+            reloc(0x1234, 0, false, 0, false, ARM_RELOC_PAIR),
+        ];
+        let resolved = resolve(&relocs, CPU_TYPE_ARM, None, &[]).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].kind, RelocationKind::Arm(ArmRelocation::Half));
+        assert_eq!(resolved[0].other_half, Some(0x1234));
+        assert_eq!(resolved[0].subtrahend, None);
+    }
+}
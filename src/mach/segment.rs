@@ -34,7 +34,7 @@ impl<'a> Iterator for RelocationIterator<'a> {
 }
 
 /// Generalized 32/64 bit Section
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Section {
     /// name of this section
     pub sectname:  [u8; 16],
@@ -54,6 +54,12 @@ pub struct Section {
     pub nreloc:    u32,
     /// flags (section type and attributes
     pub flags:     u32,
+    /// for symbol pointers and stubs, the index of its first entry in the indirect symbol table
+    pub reserved1: u32,
+    /// for symbol stubs, the size of a stub
+    pub reserved2: u32,
+    /// reserved (64-bit only)
+    pub reserved3: u32,
 }
 
 impl Section {
@@ -91,9 +97,9 @@ impl From<Section> for Section64 {
             reloff:   section.reloff,
             nreloc:   section.nreloc,
             flags:    section.flags,
-            reserved1: 0,
-            reserved2: 0,
-            reserved3: 0,
+            reserved1: section.reserved1,
+            reserved2: section.reserved2,
+            reserved3: section.reserved3,
         }
     }
 }
@@ -110,8 +116,8 @@ impl From<Section> for Section32 {
             reloff:   section.reloff,
             nreloc:   section.nreloc,
             flags:    section.flags,
-            reserved1: 0,
-            reserved2: 0,
+            reserved1: section.reserved1,
+            reserved2: section.reserved2,
         }
     }
 }
@@ -128,6 +134,9 @@ impl fmt::Debug for Section {
             .field("reloff",   &self.reloff)
             .field("nreloc",   &self.nreloc)
             .field("flags",    &self.flags)
+            .field("reserved1", &self.reserved1)
+            .field("reserved2", &self.reserved2)
+            .field("reserved3", &self.reserved3)
             .finish()
     }
 }
@@ -144,6 +153,9 @@ impl From<Section32> for Section {
             reloff:   section.reloff,
             nreloc:   section.nreloc,
             flags:    section.flags,
+            reserved1: section.reserved1,
+            reserved2: section.reserved2,
+            reserved3: 0,
         }
     }
 }
@@ -160,6 +172,9 @@ impl From<Section64> for Section {
             reloff:   section.reloff,
             nreloc:   section.nreloc,
             flags:    section.flags,
+            reserved1: section.reserved1,
+            reserved2: section.reserved2,
+            reserved3: section.reserved3,
         }
     }
 }
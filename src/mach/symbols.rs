@@ -8,6 +8,7 @@ use error;
 use container::{self, Container};
 use mach::load_command;
 use core::fmt::{self, Debug};
+use core::cmp::Ordering;
 
 // The n_type field really contains four fields which are used via the following masks.
 /// if any of these bits set, a symbolic debugging entry
@@ -49,6 +50,150 @@ pub const NLIST_TYPE_MASK: u8 = 0xe;
 pub const NLIST_TYPE_GLOBAL: u8 = 0x1;
 pub const NLIST_TYPE_LOCAL: u8 = 0x0;
 
+// For undefined symbols in two-level namespace images, the high 8 bits of n_desc hold the
+// ordinal of the library (in LC_LOAD_DYLIB order, starting at 1) the symbol is expected in.
+/// the symbol is defined in this image
+pub const SELF_LIBRARY_ORDINAL: u8 = 0x0;
+/// the largest ordinal of an actual library
+pub const MAX_LIBRARY_ORDINAL: u8 = 0xfd;
+/// the symbol is looked up dynamically, in flat namespace fashion
+pub const DYNAMIC_LOOKUP_ORDINAL: u8 = 0xfe;
+/// the symbol is defined in the main executable
+pub const EXECUTABLE_ORDINAL: u8 = 0xff;
+
+/// mask for the reference type of an undefined or defined symbol in n_desc
+pub const REFERENCE_TYPE: u16 = 0x7;
+pub const REFERENCE_FLAG_UNDEFINED_NON_LAZY: u16 = 0;
+pub const REFERENCE_FLAG_UNDEFINED_LAZY: u16 = 1;
+pub const REFERENCE_FLAG_DEFINED: u16 = 2;
+pub const REFERENCE_FLAG_PRIVATE_DEFINED: u16 = 3;
+pub const REFERENCE_FLAG_PRIVATE_UNDEFINED_NON_LAZY: u16 = 4;
+pub const REFERENCE_FLAG_PRIVATE_UNDEFINED_LAZY: u16 = 5;
+
+/// the symbol must stay in dynamically linked images, even when stripped
+pub const REFERENCED_DYNAMICALLY: u16 = 0x0010;
+/// the symbol must not be dead stripped, in relocatable objects
+pub const N_NO_DEAD_STRIP: u16 = 0x0020;
+/// the symbol was discarded by the link editor, in linked images
+pub const N_DESC_DISCARDED: u16 = 0x0020;
+/// the undefined symbol may be missing at runtime, in which case its address is 0
+pub const N_WEAK_REF: u16 = 0x0040;
+/// the defined symbol is weak, and is overridden by a non-weak definition
+pub const N_WEAK_DEF: u16 = 0x0080;
+/// the undefined symbol refers to a weak definition
+pub const N_REF_TO_WEAK: u16 = 0x0080;
+/// the defined symbol is a Thumb function
+pub const N_ARM_THUMB_DEF: u16 = 0x0008;
+/// the defined symbol is a resolver function, which returns the actual address
+pub const N_SYMBOL_RESOLVER: u16 = 0x0100;
+/// the defined symbol is an alternate entry point into the preceding symbol
+pub const N_ALT_ENTRY: u16 = 0x0200;
+/// the defined symbol is a cold function
+pub const N_COLD_FUNC: u16 = 0x0400;
+
+// Symbolic debugging entries, see <mach-o/stab.h>; if any of the N_STAB bits are set, the whole
+// n_type is one of the following. The debug map of a linked image consists of N_SO, N_OSO, N_FUN,
+// N_STSYM, N_GSYM, N_BNSYM and N_ENSYM entries.
+/// global symbol: name,,NO_SECT,type,0
+pub const N_GSYM: u8 = 0x20;
+/// procedure name (f77 kludge): name,,NO_SECT,0,0
+pub const N_FNAME: u8 = 0x22;
+/// procedure: name,,n_sect,linenumber,address; the closing one, with an empty name, holds the size
+pub const N_FUN: u8 = 0x24;
+/// static symbol: name,,n_sect,type,address
+pub const N_STSYM: u8 = 0x26;
+/// .lcomm symbol: name,,n_sect,type,address
+pub const N_LCSYM: u8 = 0x28;
+/// begin nsect sym: 0,,n_sect,0,address
+pub const N_BNSYM: u8 = 0x2e;
+/// AST file path: name,,NO_SECT,0,0
+pub const N_AST: u8 = 0x32;
+/// emitted with gcc2_compiled and in gcc source
+pub const N_OPT: u8 = 0x3c;
+/// register sym: name,,NO_SECT,type,register
+pub const N_RSYM: u8 = 0x40;
+/// src line: 0,,n_sect,linenumber,address
+pub const N_SLINE: u8 = 0x44;
+/// end nsect sym: 0,,n_sect,0,address
+pub const N_ENSYM: u8 = 0x4e;
+/// structure elt: name,,NO_SECT,type,struct_offset
+pub const N_SSYM: u8 = 0x60;
+/// source file name: name,,n_sect,0,address
+pub const N_SO: u8 = 0x64;
+/// object file name: name,,0,0,st_mtime
+pub const N_OSO: u8 = 0x66;
+/// dynamic library file name: name,,NO_SECT,0,0
+pub const N_LIB: u8 = 0x68;
+/// local sym: name,,NO_SECT,type,offset
+pub const N_LSYM: u8 = 0x80;
+/// include file beginning: name,,NO_SECT,0,sum
+pub const N_BINCL: u8 = 0x82;
+/// #included file name: name,,n_sect,0,address
+pub const N_SOL: u8 = 0x84;
+/// compiler parameters: name,,NO_SECT,0,0
+pub const N_PARAMS: u8 = 0x86;
+/// compiler version: name,,NO_SECT,0,0
+pub const N_VERSION: u8 = 0x88;
+/// compiler -O level: name,,NO_SECT,0,0
+pub const N_OLEVEL: u8 = 0x8a;
+/// parameter: name,,NO_SECT,type,offset
+pub const N_PSYM: u8 = 0xa0;
+/// include file end: name,,NO_SECT,0,0
+pub const N_EINCL: u8 = 0xa2;
+/// alternate entry: name,,n_sect,linenumber,address
+pub const N_ENTRY: u8 = 0xa4;
+/// left bracket: 0,,NO_SECT,nesting level,address
+pub const N_LBRAC: u8 = 0xc0;
+/// deleted include file: name,,NO_SECT,0,sum
+pub const N_EXCL: u8 = 0xc2;
+/// right bracket: 0,,NO_SECT,nesting level,address
+pub const N_RBRAC: u8 = 0xe0;
+/// begin common: name,,NO_SECT,0,0
+pub const N_BCOMM: u8 = 0xe2;
+/// end common: name,,n_sect,0,0
+pub const N_ECOMM: u8 = 0xe4;
+/// end common (local name): 0,,n_sect,0,address
+pub const N_ECOML: u8 = 0xe8;
+/// second stab entry with length information
+pub const N_LENG: u8 = 0xfe;
+
+pub fn stab_to_str(n_type: u8) -> &'static str {
+    match n_type {
+        N_GSYM => "N_GSYM",
+        N_FNAME => "N_FNAME",
+        N_FUN => "N_FUN",
+        N_STSYM => "N_STSYM",
+        N_LCSYM => "N_LCSYM",
+        N_BNSYM => "N_BNSYM",
+        N_AST => "N_AST",
+        N_OPT => "N_OPT",
+        N_RSYM => "N_RSYM",
+        N_SLINE => "N_SLINE",
+        N_ENSYM => "N_ENSYM",
+        N_SSYM => "N_SSYM",
+        N_SO => "N_SO",
+        N_OSO => "N_OSO",
+        N_LIB => "N_LIB",
+        N_LSYM => "N_LSYM",
+        N_BINCL => "N_BINCL",
+        N_SOL => "N_SOL",
+        N_PARAMS => "N_PARAMS",
+        N_VERSION => "N_VERSION",
+        N_OLEVEL => "N_OLEVEL",
+        N_PSYM => "N_PSYM",
+        N_EINCL => "N_EINCL",
+        N_ENTRY => "N_ENTRY",
+        N_LBRAC => "N_LBRAC",
+        N_EXCL => "N_EXCL",
+        N_RBRAC => "N_RBRAC",
+        N_BCOMM => "N_BCOMM",
+        N_ECOMM => "N_ECOMM",
+        N_ECOML => "N_ECOML",
+        N_LENG => "N_LENG",
+        _ => "UNKNOWN_STAB",
+    }
+}
+
 pub fn n_type_to_str(n_type: u8) -> &'static str {
     match n_type {
         N_UNDF => "N_UNDF",
@@ -118,7 +263,7 @@ impl Debug for Nlist64 {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Nlist {
     /// index into the string table
     pub n_strx: usize,
@@ -153,6 +298,64 @@ impl Nlist {
     pub fn is_stab(&self) -> bool {
         self.n_type & N_STAB != 0
     }
+    /// Whether this symbol is defined in a section
+    pub fn is_defined(&self) -> bool {
+        !self.is_stab() && self.get_type() == N_SECT && self.n_sect as u8 != NO_SECT
+    }
+    /// Whether this symbol is a private external, i.e., was external before being scoped to its image
+    pub fn is_private_external(&self) -> bool {
+        self.n_type & N_PEXT != 0
+    }
+    /// The stab type of this symbolic debugging entry, e.g., `N_OSO`, or `None` if this is a regular symbol
+    pub fn stab(&self) -> Option<u8> {
+        if self.is_stab() { Some(self.n_type) } else { None }
+    }
+    /// Gets the str representation of the type of this symbol, or of its stab type for a symbolic debugging entry
+    pub fn stab_str(&self) -> Option<&'static str> {
+        self.stab().map(stab_to_str)
+    }
+    /// The ordinal of the library this undefined symbol is expected in, for two-level namespace images:
+    /// an index into the imported libraries starting at 1, or one of `SELF_LIBRARY_ORDINAL`,
+    /// `DYNAMIC_LOOKUP_ORDINAL` or `EXECUTABLE_ORDINAL`
+    pub fn library_ordinal(&self) -> u8 {
+        (self.n_desc >> 8) as u8
+    }
+    /// The `REFERENCE_FLAG_*` type of this symbol
+    pub fn reference_type(&self) -> u16 {
+        self.n_desc & REFERENCE_TYPE
+    }
+    /// Whether this undefined symbol is weakly referenced, i.e., may be missing at runtime
+    pub fn is_weak_ref(&self) -> bool {
+        self.n_desc & N_WEAK_REF != 0
+    }
+    /// Whether this defined symbol is a weak definition
+    pub fn is_weak_def(&self) -> bool {
+        !self.is_undefined() && self.n_desc & N_WEAK_DEF != 0
+    }
+    /// Whether this undefined symbol refers to a weak definition
+    pub fn is_ref_to_weak(&self) -> bool {
+        self.is_undefined() && self.n_desc & N_REF_TO_WEAK != 0
+    }
+    /// Whether this symbol must not be dead stripped
+    pub fn is_no_dead_strip(&self) -> bool {
+        self.n_desc & N_NO_DEAD_STRIP != 0
+    }
+    /// Whether this symbol must stay in the image, even when stripped
+    pub fn is_referenced_dynamically(&self) -> bool {
+        self.n_desc & REFERENCED_DYNAMICALLY != 0
+    }
+    /// Whether this defined symbol is a Thumb function
+    pub fn is_thumb_def(&self) -> bool {
+        self.n_desc & N_ARM_THUMB_DEF != 0
+    }
+    /// Whether this defined symbol is a resolver function
+    pub fn is_symbol_resolver(&self) -> bool {
+        self.n_desc & N_SYMBOL_RESOLVER != 0
+    }
+    /// Whether this defined symbol is an alternate entry point into the preceding symbol
+    pub fn is_alt_entry(&self) -> bool {
+        self.n_desc & N_ALT_ENTRY != 0
+    }
 }
 
 impl ctx::SizeWith<container::Ctx> for Nlist {
@@ -266,6 +469,32 @@ impl<'a> Iterator for SymbolIterator<'a> {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+/// A symbol of an object file in the debug map, see `Symbols::debug_map`
+pub struct DebugMapSymbol<'a> {
+    /// The name of the symbol
+    pub name: &'a str,
+    /// `N_FUN`, `N_STSYM` or `N_GSYM`
+    pub stab: u8,
+    /// The address of the symbol in the linked image; 0 for `N_GSYM`, which must be looked up by name
+    pub address: u64,
+    /// The size of the function, for `N_FUN`
+    pub size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// An object file the linked image was built from, as recorded by `N_OSO` in the debug map
+pub struct DebugMapObject<'a> {
+    /// The path of the object file, or of the archive with the member in parentheses
+    pub path: &'a str,
+    /// The modification time of the object file, to check it is the one that was linked
+    pub mtime: u64,
+    /// The `N_SO` entries preceding the object, i.e., the compilation directory and the source file
+    pub sources: Vec<&'a str>,
+    /// The symbols the object contributed to the linked image
+    pub symbols: Vec<DebugMapSymbol<'a>>,
+}
+
 /// A zero-copy "nlist" style symbol table ("stab"), including the string table
 pub struct Symbols<'a> {
     data: &'a [u8],
@@ -321,6 +550,91 @@ impl<'a> Symbols<'a> {
         let name = self.data.pread(self.strtab + sym.n_strx)?;
         Ok((name, sym))
     }
+
+    /// Returns the symbols defined in a section, sorted by address, for looking up many addresses
+    pub fn by_address(&self) -> error::Result<Vec<(&'a str, Nlist)>> {
+        let mut symbols = Vec::new();
+        for symbol in self.iter() {
+            let (name, nlist) = symbol?;
+            if nlist.is_defined() {
+                symbols.push((name, nlist));
+            }
+        }
+        symbols.sort_by_key(|&(_, nlist)| nlist.n_value);
+        Ok(symbols)
+    }
+
+    /// Returns the symbol defined in a section at or nearest before `address`, if any.
+    ///
+    /// This sorts the symbol table first; to look up many addresses, sort it once with `by_address` and use
+    /// `lookup_address_in`.
+    pub fn lookup_address(&self, address: u64) -> error::Result<Option<(&'a str, Nlist)>> {
+        Ok(lookup_address_in(&self.by_address()?, address))
+    }
+
+    /// Decodes the debug map of a linked image from its `N_SO`, `N_OSO`, `N_FUN`, `N_STSYM` and
+    /// `N_GSYM` symbolic debugging entries, i.e., the object files it was built from and the
+    /// symbols each contributed, which is what `dsymutil` uses to find their DWARF
+    pub fn debug_map(&self) -> error::Result<Vec<DebugMapObject<'a>>> {
+        let mut objects = Vec::new();
+        let mut sources = Vec::new();
+        let mut object: Option<DebugMapObject<'a>> = None;
+        for symbol in self.iter() {
+            let (name, nlist) = symbol?;
+            match nlist.stab() {
+                Some(N_SO) if name.is_empty() => {
+                    // closes the compilation unit
+                    if let Some(object) = object.take() {
+                        objects.push(object);
+                    }
+                    sources.clear();
+                },
+                Some(N_SO) => {
+                    if let Some(object) = object.take() {
+                        objects.push(object);
+                        sources.clear();
+                    }
+                    sources.push(name);
+                },
+                Some(N_OSO) => {
+                    if let Some(object) = object.take() {
+                        objects.push(object);
+                    }
+                    object = Some(DebugMapObject {
+                        path: name,
+                        mtime: nlist.n_value,
+                        sources: sources.clone(),
+                        symbols: Vec::new(),
+                    });
+                },
+                Some(stab @ N_FUN) | Some(stab @ N_STSYM) | Some(stab @ N_GSYM) => {
+                    let object = match object.as_mut() {
+                        Some(object) => object,
+                        // not part of an object file's debug map, e.g., from a hand written assembly file
+                        None => continue,
+                    };
+                    if stab == N_FUN && name.is_empty() {
+                        // the closing N_FUN holds the size of the function it follows
+                        if let Some(function) = object.symbols.iter_mut().rev().find(|symbol| symbol.stab == N_FUN) {
+                            function.size = Some(nlist.n_value);
+                        }
+                    } else {
+                        object.symbols.push(DebugMapSymbol {
+                            name: name,
+                            stab: stab,
+                            address: nlist.n_value,
+                            size: None,
+                        });
+                    }
+                },
+                _ => (),
+            }
+        }
+        if let Some(object) = object.take() {
+            objects.push(object);
+        }
+        Ok(objects)
+    }
 }
 
 impl<'a> Debug for Symbols<'a> {
@@ -340,3 +654,95 @@ impl<'a> Debug for Symbols<'a> {
         writeln!(fmt, "}}")
     }
 }
+
+/// Returns the symbol of `by_address`, symbols sorted as `Symbols::by_address` sorts them, at or nearest before `address`
+pub fn lookup_address_in<'a>(by_address: &[(&'a str, Nlist)], address: u64) -> Option<(&'a str, Nlist)> {
+    let end = match by_address.binary_search_by(|&(_, nlist)| if nlist.n_value <= address { Ordering::Less } else { Ordering::Greater }) {
+        Ok(end) | Err(end) => end,
+    };
+    if end == 0 {
+        return None;
+    }
+    // prefer the first (usually the global) symbol of aliases, which the stable sort kept in symbol table order
+    let value = by_address[end - 1].1.n_value;
+    let first = by_address[..end].iter().rposition(|&(_, nlist)| nlist.n_value != value).map(|i| i + 1).unwrap_or(0);
+    Some(by_address[first])
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::{Pwrite, LE};
+    use container;
+    use mach::load_command;
+    use super::*;
+
+    #[test]
+    fn debug_map_and_lookup() {
+        let strtab = b"\0/src/\0main.c\0/obj/main.o\0_main\0_count\0_helper\0";
+        let name = |s: &str| -> u32 {
+            let needle = format!("\0{}\0", s);
+            strtab.windows(needle.len()).position(|w| w == needle.as_bytes()).unwrap() as u32 + 1
+        };
+        let nlists = [
+            (name("/src/"), N_SO, 0, 0, 0),
+            (name("main.c"), N_SO, 0, 0, 0),
+            (name("/obj/main.o"), N_OSO, 0, 1, 0x5f00_0000),
+            (0, N_BNSYM, 1, 0, 0x1000),
+            (name("_main"), N_FUN, 1, 0, 0x1000),
+            (0, N_FUN, 0, 0, 0x40),
+            (0, N_ENSYM, 1, 0, 0x1000),
+            (name("_count"), N_STSYM, 2, 0, 0x2000),
+            (0, N_SO, 1, 0, 0),
+            (name("_main"), N_SECT | N_EXT, 1, 0, 0x1000),
+            (name("_helper"), N_SECT, 1, N_NO_DEAD_STRIP, 0x1020),
+            (name("_count"), N_SECT | N_PEXT, 2, N_WEAK_DEF, 0x2000),
+            (0, N_UNDF | N_EXT, 0, (2 << 8) | N_WEAK_REF, 0),
+            (name("_helper"), N_FUN, 1, 0, 0x1020),
+            (name("_count"), N_GSYM, 0, 0, 0),
+        ];
+        let mut bytes = vec![0u8; nlists.len() * SIZEOF_NLIST_64 + strtab.len()];
+        for (i, &(n_strx, n_type, n_sect, n_desc, n_value)) in nlists.iter().enumerate() {
+            let nlist = Nlist64 { n_strx: n_strx, n_type: n_type, n_sect: n_sect, n_desc: n_desc, n_value: n_value };
+            bytes.pwrite_with(nlist, i * SIZEOF_NLIST_64, LE).unwrap();
+        }
+        let stroff = nlists.len() * SIZEOF_NLIST_64;
+        bytes[stroff..].copy_from_slice(strtab);
+        let mut symtab = load_command::SymtabCommand::new();
+        symtab.nsyms = nlists.len() as u32;
+        symtab.stroff = stroff as u32;
+        symtab.strsize = strtab.len() as u32;
+        let ctx = container::Ctx::new(container::Container::Big, LE);
+        let symbols = Symbols::parse(&bytes, &symtab, ctx).unwrap();
+
+        let map = symbols.debug_map().unwrap();
+        assert_eq!(map.len(), 1);
+        assert_eq!(map[0].path, "/obj/main.o");
+        assert_eq!(map[0].mtime, 0x5f00_0000);
+        assert_eq!(map[0].sources, vec!["/src/", "main.c"]);
+        assert_eq!(map[0].symbols, vec![
+            DebugMapSymbol { name: "_main", stab: N_FUN, address: 0x1000, size: Some(0x40) },
+            DebugMapSymbol { name: "_count", stab: N_STSYM, address: 0x2000, size: None },
+        ]);
+
+        let (_, oso) = symbols.get(2).unwrap();
+        assert_eq!(oso.stab_str(), Some("N_OSO"));
+        let (_, helper) = symbols.get(10).unwrap();
+        assert!(helper.is_defined() && helper.is_no_dead_strip() && !helper.is_weak_def());
+        let (_, count) = symbols.get(11).unwrap();
+        assert!(count.is_private_external() && count.is_weak_def());
+        let (_, undefined) = symbols.get(12).unwrap();
+        assert!(undefined.is_undefined() && undefined.is_weak_ref() && !undefined.is_defined());
+        assert_eq!(undefined.library_ordinal(), 2);
+
+        assert_eq!(symbols.lookup_address(0xfff).unwrap(), None);
+        assert_eq!(symbols.lookup_address(0x1000).unwrap().unwrap().0, "_main");
+        assert_eq!(symbols.lookup_address(0x101f).unwrap().unwrap().0, "_main");
+        assert_eq!(symbols.lookup_address(0x1020).unwrap().unwrap().0, "_helper");
+        assert_eq!(symbols.lookup_address(0x3000).unwrap().unwrap().0, "_count");
+        let sorted = symbols.by_address().unwrap().into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(sorted, vec!["_main", "_helper", "_count"]);
+        let by_address = symbols.by_address().unwrap();
+        assert_eq!(lookup_address_in(&by_address, 0x1fff).unwrap().0, "_helper");
+        assert_eq!(lookup_address_in(&[], 0x1000), None);
+    }
+}
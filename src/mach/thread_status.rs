@@ -0,0 +1,619 @@
+//! Machine specific thread states, as held by `LC_THREAD` and `LC_UNIXTHREAD`, see <mach/thread_status.h>
+//!
+//! A thread command holds one or more thread states, each preceded by its `flavor` and its `count` of 32-bit words;
+//! what a flavor means depends on the `cputype` of the binary. The `LC_UNIXTHREAD` of an executable holds the initial
+//! registers of its main thread, while every `LC_THREAD` of a core file (`MH_CORE`) holds the general purpose,
+//! floating point and exception state of one thread at the time it was dumped.
+
+use scroll::{self, Pread};
+
+use error;
+use mach::cputype::{CpuType, CPU_TYPE_X86, CPU_TYPE_X86_64, CPU_TYPE_ARM, CPU_TYPE_ARM64};
+
+// x86 and x86_64 flavors, see <mach/i386/thread_status.h>
+pub const X86_THREAD_STATE32: u32 = 1;
+pub const X86_FLOAT_STATE32: u32 = 2;
+pub const X86_EXCEPTION_STATE32: u32 = 3;
+pub const X86_THREAD_STATE64: u32 = 4;
+pub const X86_FLOAT_STATE64: u32 = 5;
+pub const X86_EXCEPTION_STATE64: u32 = 6;
+/// either of `X86_THREAD_STATE32` or `X86_THREAD_STATE64`, preceded by its own flavor and count
+pub const X86_THREAD_STATE: u32 = 7;
+/// either of `X86_FLOAT_STATE32` or `X86_FLOAT_STATE64`, preceded by its own flavor and count
+pub const X86_FLOAT_STATE: u32 = 8;
+/// either of `X86_EXCEPTION_STATE32` or `X86_EXCEPTION_STATE64`, preceded by its own flavor and count
+pub const X86_EXCEPTION_STATE: u32 = 9;
+pub const X86_DEBUG_STATE32: u32 = 10;
+pub const X86_DEBUG_STATE64: u32 = 11;
+pub const X86_DEBUG_STATE: u32 = 12;
+pub const X86_AVX_STATE32: u32 = 16;
+pub const X86_AVX_STATE64: u32 = 17;
+pub const X86_AVX_STATE: u32 = 18;
+pub const X86_AVX512_STATE32: u32 = 19;
+pub const X86_AVX512_STATE64: u32 = 20;
+pub const X86_AVX512_STATE: u32 = 21;
+pub const X86_PAGEIN_STATE: u32 = 22;
+pub const X86_THREAD_FULL_STATE64: u32 = 23;
+
+// ARM and ARM64 flavors, see <mach/arm/thread_status.h>
+/// `ARM_THREAD_STATE32` for ARM; for ARM64, either of `ARM_THREAD_STATE32` or `ARM_THREAD_STATE64`, preceded by its own flavor and count
+pub const ARM_THREAD_STATE: u32 = 1;
+pub const ARM_UNIFIED_THREAD_STATE: u32 = ARM_THREAD_STATE;
+pub const ARM_VFP_STATE: u32 = 2;
+pub const ARM_EXCEPTION_STATE: u32 = 3;
+pub const ARM_DEBUG_STATE: u32 = 4;
+pub const ARM_THREAD_STATE64: u32 = 6;
+pub const ARM_EXCEPTION_STATE64: u32 = 7;
+pub const ARM_THREAD_STATE32: u32 = 9;
+pub const ARM_DEBUG_STATE32: u32 = 14;
+pub const ARM_DEBUG_STATE64: u32 = 15;
+pub const ARM_NEON_STATE: u32 = 16;
+pub const ARM_NEON_STATE64: u32 = 17;
+pub const ARM_CPMU_STATE64: u32 = 18;
+pub const ARM_PAGEIN_STATE: u32 = 27;
+
+/// Returns the str representation of the thread state `flavor` for `cputype`
+pub fn flavor_to_str(cputype: CpuType, flavor: u32) -> &'static str {
+    match cputype {
+        CPU_TYPE_X86 | CPU_TYPE_X86_64 => match flavor {
+            X86_THREAD_STATE32 => "x86_THREAD_STATE32",
+            X86_FLOAT_STATE32 => "x86_FLOAT_STATE32",
+            X86_EXCEPTION_STATE32 => "x86_EXCEPTION_STATE32",
+            X86_THREAD_STATE64 => "x86_THREAD_STATE64",
+            X86_FLOAT_STATE64 => "x86_FLOAT_STATE64",
+            X86_EXCEPTION_STATE64 => "x86_EXCEPTION_STATE64",
+            X86_THREAD_STATE => "x86_THREAD_STATE",
+            X86_FLOAT_STATE => "x86_FLOAT_STATE",
+            X86_EXCEPTION_STATE => "x86_EXCEPTION_STATE",
+            X86_DEBUG_STATE32 => "x86_DEBUG_STATE32",
+            X86_DEBUG_STATE64 => "x86_DEBUG_STATE64",
+            X86_DEBUG_STATE => "x86_DEBUG_STATE",
+            X86_AVX_STATE32 => "x86_AVX_STATE32",
+            X86_AVX_STATE64 => "x86_AVX_STATE64",
+            X86_AVX_STATE => "x86_AVX_STATE",
+            X86_AVX512_STATE32 => "x86_AVX512_STATE32",
+            X86_AVX512_STATE64 => "x86_AVX512_STATE64",
+            X86_AVX512_STATE => "x86_AVX512_STATE",
+            X86_PAGEIN_STATE => "x86_PAGEIN_STATE",
+            X86_THREAD_FULL_STATE64 => "x86_THREAD_FULL_STATE64",
+            _ => "UNKNOWN_FLAVOR",
+        },
+        CPU_TYPE_ARM | CPU_TYPE_ARM64 => match flavor {
+            ARM_THREAD_STATE => "ARM_THREAD_STATE",
+            ARM_VFP_STATE => "ARM_VFP_STATE",
+            ARM_EXCEPTION_STATE => "ARM_EXCEPTION_STATE",
+            ARM_DEBUG_STATE => "ARM_DEBUG_STATE",
+            ARM_THREAD_STATE64 => "ARM_THREAD_STATE64",
+            ARM_EXCEPTION_STATE64 => "ARM_EXCEPTION_STATE64",
+            ARM_THREAD_STATE32 => "ARM_THREAD_STATE32",
+            ARM_DEBUG_STATE32 => "ARM_DEBUG_STATE32",
+            ARM_DEBUG_STATE64 => "ARM_DEBUG_STATE64",
+            ARM_NEON_STATE => "ARM_NEON_STATE",
+            ARM_NEON_STATE64 => "ARM_NEON_STATE64",
+            ARM_CPMU_STATE64 => "ARM_CPMU_STATE64",
+            ARM_PAGEIN_STATE => "ARM_PAGEIN_STATE",
+            _ => "UNKNOWN_FLAVOR",
+        },
+        _ => "UNKNOWN_FLAVOR",
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `x86_thread_state32_t`, also known as `i386_thread_state_t`
+pub struct X86ThreadState32 {
+    pub eax:    u32,
+    pub ebx:    u32,
+    pub ecx:    u32,
+    pub edx:    u32,
+    pub edi:    u32,
+    pub esi:    u32,
+    pub ebp:    u32,
+    pub esp:    u32,
+    pub ss:     u32,
+    pub eflags: u32,
+    pub eip:    u32,
+    pub cs:     u32,
+    pub ds:     u32,
+    pub es:     u32,
+    pub fs:     u32,
+    pub gs:     u32,
+}
+
+pub const SIZEOF_X86_THREAD_STATE32: usize = 64;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `x86_thread_state64_t`
+pub struct X86ThreadState64 {
+    pub rax:    u64,
+    pub rbx:    u64,
+    pub rcx:    u64,
+    pub rdx:    u64,
+    pub rdi:    u64,
+    pub rsi:    u64,
+    pub rbp:    u64,
+    pub rsp:    u64,
+    pub r8:     u64,
+    pub r9:     u64,
+    pub r10:    u64,
+    pub r11:    u64,
+    pub r12:    u64,
+    pub r13:    u64,
+    pub r14:    u64,
+    pub r15:    u64,
+    pub rip:    u64,
+    pub rflags: u64,
+    pub cs:     u64,
+    pub fs:     u64,
+    pub gs:     u64,
+}
+
+pub const SIZEOF_X86_THREAD_STATE64: usize = 168;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `x86_exception_state32_t`, also known as `i386_exception_state_t`
+pub struct X86ExceptionState32 {
+    /// the trap number
+    pub trapno:     u16,
+    /// the cpu the exception was taken on
+    pub cpu:        u16,
+    /// the error code
+    pub err:        u32,
+    /// the faulting address, for page faults
+    pub faultvaddr: u32,
+}
+
+pub const SIZEOF_X86_EXCEPTION_STATE32: usize = 12;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `x86_exception_state64_t`
+pub struct X86ExceptionState64 {
+    /// the trap number
+    pub trapno:     u16,
+    /// the cpu the exception was taken on
+    pub cpu:        u16,
+    /// the error code
+    pub err:        u32,
+    /// the faulting address, for page faults
+    pub faultvaddr: u64,
+}
+
+pub const SIZEOF_X86_EXCEPTION_STATE64: usize = 16;
+
+#[derive(Debug, Clone, PartialEq)]
+/// `x86_float_state32_t` and `x86_float_state64_t`, i.e., the `fxsave` area, which only differ in their number of
+/// `xmm` registers
+pub struct X86FloatState {
+    /// the x87 control word
+    pub fcw:       u16,
+    /// the x87 status word
+    pub fsw:       u16,
+    /// the abridged x87 tag word
+    pub ftw:       u8,
+    /// the last x87 opcode
+    pub fop:       u16,
+    /// the offset of the last x87 instruction
+    pub ip:        u32,
+    /// the segment selector of the last x87 instruction
+    pub cs:        u16,
+    /// the offset of the last x87 operand
+    pub dp:        u32,
+    /// the segment selector of the last x87 operand
+    pub ds:        u16,
+    /// the SSE control and status register
+    pub mxcsr:     u32,
+    pub mxcsrmask: u32,
+    /// the 80-bit x87 or 64-bit MMX registers `st0` to `st7`
+    pub stmm:      [[u8; 10]; 8],
+    /// the 128-bit SSE registers, `xmm0` to `xmm7` for 32-bit and `xmm0` to `xmm15` for 64-bit
+    pub xmm:       Vec<[u8; 16]>,
+}
+
+/// The size of both `x86_float_state32_t` and `x86_float_state64_t`
+pub const SIZEOF_X86_FLOAT_STATE: usize = 524;
+
+impl X86FloatState {
+    /// Parses an `x86_float_state32_t`, when `is_64` is false, or an `x86_float_state64_t` from `bytes`
+    pub fn parse(bytes: &[u8], is_64: bool, le: scroll::Endian) -> error::Result<Self> {
+        if bytes.len() < SIZEOF_X86_FLOAT_STATE {
+            return Err(error::Error::Malformed(format!("x86 float state has {} bytes but needs {}", bytes.len(), SIZEOF_X86_FLOAT_STATE)));
+        }
+        // skips fpu_reserved[2]
+        let offset = &mut 8;
+        let fcw = bytes.gread_with(offset, le)?;
+        let fsw = bytes.gread_with(offset, le)?;
+        let ftw = bytes.gread_with(offset, le)?;
+        *offset += 1;
+        let fop = bytes.gread_with(offset, le)?;
+        let ip = bytes.gread_with(offset, le)?;
+        let cs = bytes.gread_with(offset, le)?;
+        *offset += 2;
+        let dp = bytes.gread_with(offset, le)?;
+        let ds = bytes.gread_with(offset, le)?;
+        *offset += 2;
+        let mxcsr = bytes.gread_with(offset, le)?;
+        let mxcsrmask = bytes.gread_with(offset, le)?;
+        let mut stmm = [[0; 10]; 8];
+        for register in &mut stmm {
+            register.copy_from_slice(&bytes[*offset..*offset + 10]);
+            // each register is padded to 16 bytes
+            *offset += 16;
+        }
+        let count = if is_64 { 16 } else { 8 };
+        let mut xmm = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut register = [0; 16];
+            register.copy_from_slice(&bytes[*offset..*offset + 16]);
+            xmm.push(register);
+            *offset += 16;
+        }
+        Ok(X86FloatState {
+            fcw: fcw,
+            fsw: fsw,
+            ftw: ftw,
+            fop: fop,
+            ip: ip,
+            cs: cs,
+            dp: dp,
+            ds: ds,
+            mxcsr: mxcsr,
+            mxcsrmask: mxcsrmask,
+            stmm: stmm,
+            xmm: xmm,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `arm_thread_state32_t`
+pub struct ArmThreadState32 {
+    /// the general purpose registers `r0` to `r12`; `r7` is the frame pointer
+    pub r:    [u32; 13],
+    pub sp:   u32,
+    pub lr:   u32,
+    pub pc:   u32,
+    pub cpsr: u32,
+}
+
+pub const SIZEOF_ARM_THREAD_STATE32: usize = 68;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `arm_thread_state64_t`
+pub struct ArmThreadState64 {
+    /// the general purpose registers `x0` to `x28`
+    pub x:     [u64; 29],
+    /// the frame pointer, `x29`
+    pub fp:    u64,
+    /// the link register, `x30`
+    pub lr:    u64,
+    pub sp:    u64,
+    pub pc:    u64,
+    pub cpsr:  u32,
+    /// padding, or the pointer authentication flags of `fp`, `lr`, `sp` and `pc` on arm64e
+    pub flags: u32,
+}
+
+pub const SIZEOF_ARM_THREAD_STATE64: usize = 272;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `arm_exception_state32_t`
+pub struct ArmExceptionState32 {
+    pub exception: u32,
+    /// the fault status register
+    pub fsr:       u32,
+    /// the fault address register
+    pub far:       u32,
+}
+
+pub const SIZEOF_ARM_EXCEPTION_STATE32: usize = 12;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `arm_exception_state64_t`
+pub struct ArmExceptionState64 {
+    /// the fault address register
+    pub far:       u64,
+    /// the exception syndrome register
+    pub esr:       u32,
+    pub exception: u32,
+}
+
+pub const SIZEOF_ARM_EXCEPTION_STATE64: usize = 16;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pread, Pwrite, SizeWith)]
+/// `arm_vfp_state_t`
+pub struct ArmVfpState {
+    /// the single precision registers `s0` to `s63`, which alias the double precision `d0` to `d31`
+    pub r:     [u32; 64],
+    pub fpscr: u32,
+}
+
+pub const SIZEOF_ARM_VFP_STATE: usize = 260;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// `arm_neon_state64_t`
+pub struct ArmNeonState64 {
+    /// the 128-bit SIMD and floating point registers `v0` to `v31`
+    pub v:    [[u8; 16]; 32],
+    pub fpsr: u32,
+    pub fpcr: u32,
+}
+
+/// The size of `arm_neon_state64_t` without its trailing alignment padding
+pub const SIZEOF_ARM_NEON_STATE64: usize = 520;
+
+impl ArmNeonState64 {
+    /// Parses an `arm_neon_state64_t` from `bytes`
+    pub fn parse(bytes: &[u8], le: scroll::Endian) -> error::Result<Self> {
+        if bytes.len() < SIZEOF_ARM_NEON_STATE64 {
+            return Err(error::Error::Malformed(format!("ARM64 NEON state has {} bytes but needs {}", bytes.len(), SIZEOF_ARM_NEON_STATE64)));
+        }
+        let mut v = [[0; 16]; 32];
+        for (i, register) in v.iter_mut().enumerate() {
+            register.copy_from_slice(&bytes[i * 16..(i + 1) * 16]);
+        }
+        Ok(ArmNeonState64 {
+            v: v,
+            fpsr: bytes.pread_with(512, le)?,
+            fpcr: bytes.pread_with(516, le)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A decoded thread state of a thread command
+pub enum ThreadState {
+    X86Thread32(X86ThreadState32),
+    X86Thread64(X86ThreadState64),
+    X86Float32(X86FloatState),
+    X86Float64(X86FloatState),
+    X86Exception32(X86ExceptionState32),
+    X86Exception64(X86ExceptionState64),
+    ArmThread32(ArmThreadState32),
+    ArmThread64(ArmThreadState64),
+    ArmVfp(ArmVfpState),
+    ArmNeon64(ArmNeonState64),
+    ArmException32(ArmExceptionState32),
+    ArmException64(ArmExceptionState64),
+    /// A flavor which is not decoded, e.g., a debug state, as its raw 32-bit words
+    Unknown { flavor: u32, state: Vec<u32> },
+}
+
+impl ThreadState {
+    /// Parses the state of `flavor` for `cputype` from `bytes`, i.e., the `count` words following the flavor and count
+    pub fn parse(bytes: &[u8], flavor: u32, cputype: CpuType, le: scroll::Endian) -> error::Result<Self> {
+        use self::ThreadState::*;
+        let state = match (cputype, flavor) {
+              (CPU_TYPE_X86, X86_THREAD_STATE)
+            | (CPU_TYPE_X86, X86_FLOAT_STATE)
+            | (CPU_TYPE_X86, X86_EXCEPTION_STATE)
+            | (CPU_TYPE_X86_64, X86_THREAD_STATE)
+            | (CPU_TYPE_X86_64, X86_FLOAT_STATE)
+            | (CPU_TYPE_X86_64, X86_EXCEPTION_STATE)
+            | (CPU_TYPE_ARM64, ARM_UNIFIED_THREAD_STATE) => {
+                // a state header, followed by the actual 32 or 64-bit state, which is never another header
+                let (actual, state) = flavored(bytes, 0, le)?;
+                let inner = match flavor {
+                    X86_THREAD_STATE => [X86_THREAD_STATE32, X86_THREAD_STATE64],
+                    X86_FLOAT_STATE => [X86_FLOAT_STATE32, X86_FLOAT_STATE64],
+                    X86_EXCEPTION_STATE => [X86_EXCEPTION_STATE32, X86_EXCEPTION_STATE64],
+                    _ => [ARM_THREAD_STATE32, ARM_THREAD_STATE64],
+                };
+                if !inner.contains(&actual) {
+                    return Err(error::Error::Malformed(format!("Thread state header of flavor {} wraps flavor {}", flavor_to_str(cputype, flavor), actual)));
+                }
+                return ThreadState::parse(state, actual, cputype, le);
+            },
+            (CPU_TYPE_X86, X86_THREAD_STATE32) | (CPU_TYPE_X86_64, X86_THREAD_STATE32) => X86Thread32(bytes.pread_with(0, le)?),
+            (CPU_TYPE_X86, X86_THREAD_STATE64) | (CPU_TYPE_X86_64, X86_THREAD_STATE64) => X86Thread64(bytes.pread_with(0, le)?),
+            (CPU_TYPE_X86, X86_FLOAT_STATE32) | (CPU_TYPE_X86_64, X86_FLOAT_STATE32) => X86Float32(X86FloatState::parse(bytes, false, le)?),
+            (CPU_TYPE_X86, X86_FLOAT_STATE64) | (CPU_TYPE_X86_64, X86_FLOAT_STATE64) => X86Float64(X86FloatState::parse(bytes, true, le)?),
+            (CPU_TYPE_X86, X86_EXCEPTION_STATE32) | (CPU_TYPE_X86_64, X86_EXCEPTION_STATE32) => X86Exception32(bytes.pread_with(0, le)?),
+            (CPU_TYPE_X86, X86_EXCEPTION_STATE64) | (CPU_TYPE_X86_64, X86_EXCEPTION_STATE64) => X86Exception64(bytes.pread_with(0, le)?),
+            (CPU_TYPE_ARM, ARM_THREAD_STATE) | (CPU_TYPE_ARM, ARM_THREAD_STATE32) | (CPU_TYPE_ARM64, ARM_THREAD_STATE32) => ArmThread32(bytes.pread_with(0, le)?),
+            (CPU_TYPE_ARM, ARM_VFP_STATE) => ArmVfp(bytes.pread_with(0, le)?),
+            (CPU_TYPE_ARM, ARM_EXCEPTION_STATE) | (CPU_TYPE_ARM64, ARM_EXCEPTION_STATE) => ArmException32(bytes.pread_with(0, le)?),
+            (CPU_TYPE_ARM64, ARM_THREAD_STATE64) => ArmThread64(bytes.pread_with(0, le)?),
+            (CPU_TYPE_ARM64, ARM_NEON_STATE64) => ArmNeon64(ArmNeonState64::parse(bytes, le)?),
+            (CPU_TYPE_ARM64, ARM_EXCEPTION_STATE64) => ArmException64(bytes.pread_with(0, le)?),
+            _ => {
+                let mut state = Vec::with_capacity(bytes.len() / 4);
+                let offset = &mut 0;
+                while *offset + 4 <= bytes.len() {
+                    state.push(bytes.gread_with(offset, le)?);
+                }
+                Unknown { flavor: flavor, state: state }
+            },
+        };
+        Ok(state)
+    }
+
+    /// The program counter, if this is a general purpose register state
+    pub fn instruction_pointer(&self) -> Option<u64> {
+        match *self {
+            ThreadState::X86Thread32(ref state) => Some(state.eip as u64),
+            ThreadState::X86Thread64(ref state) => Some(state.rip),
+            ThreadState::ArmThread32(ref state) => Some(state.pc as u64),
+            ThreadState::ArmThread64(ref state) => Some(state.pc),
+            _ => None,
+        }
+    }
+
+    /// The stack pointer, if this is a general purpose register state
+    pub fn stack_pointer(&self) -> Option<u64> {
+        match *self {
+            ThreadState::X86Thread32(ref state) => Some(state.esp as u64),
+            ThreadState::X86Thread64(ref state) => Some(state.rsp),
+            ThreadState::ArmThread32(ref state) => Some(state.sp as u64),
+            ThreadState::ArmThread64(ref state) => Some(state.sp),
+            _ => None,
+        }
+    }
+
+    /// The frame pointer, if this is a general purpose register state
+    pub fn frame_pointer(&self) -> Option<u64> {
+        match *self {
+            ThreadState::X86Thread32(ref state) => Some(state.ebp as u64),
+            ThreadState::X86Thread64(ref state) => Some(state.rbp),
+            ThreadState::ArmThread32(ref state) => Some(state.r[7] as u64),
+            ThreadState::ArmThread64(ref state) => Some(state.fp),
+            _ => None,
+        }
+    }
+
+    /// The faulting address, if this is an exception state
+    pub fn fault_address(&self) -> Option<u64> {
+        match *self {
+            ThreadState::X86Exception32(ref state) => Some(state.faultvaddr as u64),
+            ThreadState::X86Exception64(ref state) => Some(state.faultvaddr),
+            ThreadState::ArmException32(ref state) => Some(state.far as u64),
+            ThreadState::ArmException64(ref state) => Some(state.far),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the flavor and count at `offset`, returning the flavor and the bytes of its state
+fn flavored(bytes: &[u8], offset: usize, le: scroll::Endian) -> error::Result<(u32, &[u8])> {
+    let flavor: u32 = bytes.pread_with(offset, le)?;
+    let count: u32 = bytes.pread_with(offset + 4, le)?;
+    let start = offset + 8;
+    let end = start + count as usize * 4;
+    match bytes.get(start..end) {
+        Some(state) => Ok((flavor, state)),
+        None => Err(error::Error::Malformed(format!("Thread state of flavor {} has {} words at {:#x} but there are only {} bytes", flavor, count, start, bytes.len()))),
+    }
+}
+
+/// Parses every thread state of the thread command `bytes`, including its `cmd` and `cmdsize`, for `cputype`
+pub fn parse(bytes: &[u8], cputype: CpuType, le: scroll::Endian) -> error::Result<Vec<ThreadState>> {
+    let cmdsize: u32 = bytes.pread_with(4, le)?;
+    let bytes = match bytes.get(..cmdsize as usize) {
+        Some(bytes) => bytes,
+        None => return Err(error::Error::Malformed(format!("Thread command has cmdsize {:#x} but there are only {:#x} bytes", cmdsize, bytes.len()))),
+    };
+    let mut states = Vec::new();
+    let mut offset = 8;
+    // each state is at least a flavor and a count
+    while offset + 8 <= bytes.len() {
+        let (flavor, state) = flavored(bytes, offset, le)?;
+        states.push(ThreadState::parse(state, flavor, cputype, le)?);
+        offset += 8 + state.len();
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::{Pwrite, LE};
+    use mach::cputype::{CPU_TYPE_X86_64, CPU_TYPE_ARM64};
+    use mach::load_command::LC_THREAD;
+    use super::*;
+
+    fn thread_command(states: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = vec![0u8; 8];
+        for &(flavor, state) in states {
+            let mut header = [0u8; 8];
+            header.pwrite_with(flavor, 0, LE).unwrap();
+            header.pwrite_with(state.len() as u32 / 4, 4, LE).unwrap();
+            bytes.extend_from_slice(&header);
+            bytes.extend_from_slice(state);
+        }
+        let cmdsize = bytes.len() as u32;
+        bytes.pwrite_with(LC_THREAD, 0, LE).unwrap();
+        bytes.pwrite_with(cmdsize, 4, LE).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn x86_64_core_thread() {
+        // the generic x86_THREAD_STATE wraps x86_THREAD_STATE64 in its own flavor and count
+        let mut thread = vec![0u8; 8 + SIZEOF_X86_THREAD_STATE64];
+        thread.pwrite_with(X86_THREAD_STATE64, 0, LE).unwrap();
+        thread.pwrite_with(SIZEOF_X86_THREAD_STATE64 as u32 / 4, 4, LE).unwrap();
+        thread.pwrite_with(0x7ffe_0000u64, 8 + 6 * 8, LE).unwrap();
+        thread.pwrite_with(0x7ffd_fff0u64, 8 + 7 * 8, LE).unwrap();
+        thread.pwrite_with(0x1_0000_0f40u64, 8 + 16 * 8, LE).unwrap();
+        let mut float = vec![0u8; SIZEOF_X86_FLOAT_STATE];
+        float.pwrite_with(0x037fu16, 8, LE).unwrap();
+        float.pwrite_with(0x1f80u32, 32, LE).unwrap();
+        float[168 + 15 * 16] = 0xaa;
+        let exception = X86ExceptionState64 { trapno: 14, cpu: 0, err: 4, faultvaddr: 0xdead_beef };
+        let mut exception_bytes = vec![0u8; SIZEOF_X86_EXCEPTION_STATE64];
+        exception_bytes.pwrite_with(exception, 0, LE).unwrap();
+        let debug = [0u8; 64];
+        let bytes = thread_command(&[(X86_THREAD_STATE, &thread), (X86_FLOAT_STATE64, &float), (X86_EXCEPTION_STATE64, &exception_bytes), (X86_DEBUG_STATE64, &debug)]);
+
+        let states = parse(&bytes, CPU_TYPE_X86_64, LE).unwrap();
+        assert_eq!(states.len(), 4);
+        assert_eq!(states[0].instruction_pointer(), Some(0x1_0000_0f40));
+        assert_eq!(states[0].stack_pointer(), Some(0x7ffd_fff0));
+        assert_eq!(states[0].frame_pointer(), Some(0x7ffe_0000));
+        match states[1] {
+            ThreadState::X86Float64(ref float) => {
+                assert_eq!((float.fcw, float.mxcsr), (0x037f, 0x1f80));
+                assert_eq!(float.xmm.len(), 16);
+                assert_eq!(float.xmm[15][0], 0xaa);
+            },
+            ref state => panic!("expected an x86_64 float state, got {:?}", state),
+        }
+        assert_eq!(states[2], ThreadState::X86Exception64(exception));
+        assert_eq!(states[2].fault_address(), Some(0xdead_beef));
+        assert_eq!(states[3], ThreadState::Unknown { flavor: X86_DEBUG_STATE64, state: vec![0; 16] });
+        assert_eq!(flavor_to_str(CPU_TYPE_X86_64, X86_DEBUG_STATE64), "x86_DEBUG_STATE64");
+    }
+
+    #[test]
+    fn arm64_core_thread() {
+        let mut thread = vec![0u8; SIZEOF_ARM_THREAD_STATE64];
+        thread.pwrite_with(0x16fdff000u64, 29 * 8, LE).unwrap();
+        thread.pwrite_with(0x16fdfefe0u64, 31 * 8, LE).unwrap();
+        thread.pwrite_with(0x100003f60u64, 32 * 8, LE).unwrap();
+        // arm_neon_state64_t is padded to 16 bytes
+        let mut neon = vec![0u8; SIZEOF_ARM_NEON_STATE64 + 8];
+        neon[31 * 16] = 0x55;
+        neon.pwrite_with(0x10u32, 512, LE).unwrap();
+        let mut exception = vec![0u8; SIZEOF_ARM_EXCEPTION_STATE64];
+        exception.pwrite_with(0x8u64, 0, LE).unwrap();
+        exception.pwrite_with(0x92000006u32, 8, LE).unwrap();
+        let bytes = thread_command(&[(ARM_THREAD_STATE64, &thread), (ARM_EXCEPTION_STATE64, &exception), (ARM_NEON_STATE64, &neon)]);
+
+        let states = parse(&bytes, CPU_TYPE_ARM64, LE).unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[0].instruction_pointer(), Some(0x100003f60));
+        assert_eq!(states[0].stack_pointer(), Some(0x16fdfefe0));
+        assert_eq!(states[0].frame_pointer(), Some(0x16fdff000));
+        match states[1] {
+            ThreadState::ArmException64(ref exception) => assert_eq!((exception.far, exception.esr), (0x8, 0x92000006)),
+            ref state => panic!("expected an ARM64 exception state, got {:?}", state),
+        }
+        match states[2] {
+            ThreadState::ArmNeon64(ref neon) => assert_eq!((neon.v[31][0], neon.fpsr), (0x55, 0x10)),
+            ref state => panic!("expected an ARM64 NEON state, got {:?}", state),
+        }
+
+        // a state which claims more words than the command holds
+        let mut truncated = bytes.clone();
+        truncated.pwrite_with(1000u32, 12, LE).unwrap();
+        assert!(parse(&truncated, CPU_TYPE_ARM64, LE).is_err());
+    }
+
+    #[test]
+    fn nested_state_headers() {
+        // headers wrapping each other in turn, as a crafted core file could to exhaust the stack
+        let mut bytes = vec![0u8; 8 * 64];
+        for i in 0..64 {
+            let flavor = if i % 2 == 0 { X86_FLOAT_STATE } else { X86_THREAD_STATE };
+            bytes.pwrite_with(flavor, i * 8, LE).unwrap();
+            bytes.pwrite_with((64 - i as u32 - 1) * 2, i * 8 + 4, LE).unwrap();
+        }
+        assert!(ThreadState::parse(&bytes, X86_THREAD_STATE, CPU_TYPE_X86_64, LE).is_err());
+    }
+}
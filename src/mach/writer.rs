@@ -0,0 +1,337 @@
+//! Modifying a parsed Mach-o binary and serializing it back, and combining thin binaries into a fat container
+//!
+//! A [`Writer`](struct.Writer.html) keeps a copy of the binary and of the bytes of every load command, so load commands
+//! can be added, removed or edited before [`Writer::write`](struct.Writer.html#method.write) writes them back in place
+//! of the originals. Nothing after the load commands is moved, so they must still fit before the contents of the
+//! first section, in the padding `ld -headerpad` reserves.
+//!
+//! [`fat`](fn.fat.html) lays thin binaries out in a fat container, each at the alignment `lipo` gives its cputype.
+
+use scroll::{self, Pread, Pwrite};
+
+use error;
+use container;
+use mach::MachO;
+use mach::MultiArch;
+use mach::constants::*;
+use mach::constants::cputype::{CpuType, CPU_TYPE_ARM, CPU_TYPE_ARM64};
+use mach::fat;
+use mach::header;
+use mach::load_command::*;
+
+/// Rounds `value` up to the next multiple of `alignment`
+fn align_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[derive(Debug, Clone)]
+/// A mutable copy of a thin Mach-o binary which can be serialized back
+pub struct Writer {
+    /// The header; `ncmds` and `sizeofcmds` are recomputed from `load_commands`
+    pub header: header::Header,
+    /// The bytes of every load command, including its `cmd` and `cmdsize`
+    pub load_commands: Vec<Vec<u8>>,
+    ctx: container::Ctx,
+    /// The original binary
+    data: Vec<u8>,
+    /// The end of the original load commands
+    commands_end: usize,
+    /// The file offset of the first section or segment contents, before which the load commands must end
+    commands_limit: usize,
+}
+
+impl Writer {
+    /// Copies `macho`, which was parsed from `bytes` at offset 0, into a writer
+    pub fn new(macho: &MachO, bytes: &[u8]) -> error::Result<Self> {
+        let ctx = macho.header.ctx()?;
+        let mut load_commands = Vec::with_capacity(macho.load_commands.len());
+        for command in &macho.load_commands {
+            let size = command.command.cmdsize();
+            load_commands.push(bytes.pread_with::<&[u8]>(command.offset, size)?.to_vec());
+        }
+        let mut commands_limit = bytes.len();
+        for segment in &macho.segments {
+            if segment.fileoff > 0 && segment.filesize > 0 {
+                commands_limit = ::std::cmp::min(commands_limit, segment.fileoff as usize);
+            }
+            for (section, _) in segment.sections()? {
+                match section.flags & SECTION_TYPE {
+                    S_ZEROFILL | S_GB_ZEROFILL | S_THREAD_LOCAL_ZEROFILL => (),
+                    _ if section.offset > 0 => commands_limit = ::std::cmp::min(commands_limit, section.offset as usize),
+                    _ => (),
+                }
+            }
+        }
+        Ok(Writer {
+            header: macho.header,
+            load_commands: load_commands,
+            ctx: ctx,
+            data: bytes.to_vec(),
+            commands_end: macho.header.size() + macho.header.sizeofcmds as usize,
+            commands_limit: commands_limit,
+        })
+    }
+    /// The `LC_*` of the load command `command`
+    fn cmd(&self, command: &[u8]) -> error::Result<u32> {
+        Ok(command.pread_with(0, self.ctx.le)?)
+    }
+    /// The string the `lc_str` at `offset` in the load command `command` points to
+    fn lc_str<'b>(&self, command: &'b [u8], offset: usize) -> error::Result<&'b str> {
+        let start = command.pread_with::<u32>(offset, self.ctx.le)?;
+        Ok(command.pread::<&str>(start as usize)?)
+    }
+    /// Builds the load command `fixed` followed by `string`, padded to the pointer size, and sets its `cmdsize`
+    fn string_command(&self, mut fixed: Vec<u8>, string: &str) -> error::Result<Vec<u8>> {
+        let size = align_up(fixed.len() + string.len() + 1, self.ctx.size());
+        fixed.extend_from_slice(string.as_bytes());
+        fixed.resize(size, 0);
+        fixed.pwrite_with::<u32>(size as u32, 4, self.ctx.le)?;
+        Ok(fixed)
+    }
+    /// Builds a `dylib_command` of `cmd` for `name`, with the timestamp and versions of `dylib`
+    fn dylib_command(&self, cmd: u32, name: &str, mut dylib: Dylib) -> error::Result<Vec<u8>> {
+        dylib.name = SIZEOF_DYLIB_COMMAND as u32;
+        let command = DylibCommand {
+            cmd: cmd,
+            cmdsize: 0,
+            dylib: dylib,
+        };
+        let mut fixed = vec![0; SIZEOF_DYLIB_COMMAND];
+        fixed.pwrite_with(command, 0, self.ctx.le)?;
+        self.string_command(fixed, name)
+    }
+    /// Appends the load command `command`, whose `cmdsize` must be its length, a multiple of the pointer size
+    pub fn add_load_command(&mut self, command: Vec<u8>) -> error::Result<()> {
+        let cmdsize = command.pread_with::<u32>(4, self.ctx.le)? as usize;
+        if cmdsize != command.len() || cmdsize % self.ctx.size() != 0 {
+            return Err(error::Error::Malformed(format!("Load command of {} bytes has cmdsize {}, which must be its length and a multiple of {}", command.len(), cmdsize, self.ctx.size())));
+        }
+        self.load_commands.push(command);
+        Ok(())
+    }
+    /// Removes every load command of `cmd`, returning how many there were
+    pub fn remove_load_commands(&mut self, cmd: u32) -> usize {
+        let le = self.ctx.le;
+        let count = self.load_commands.len();
+        self.load_commands.retain(|command| command.pread_with::<u32>(0, le).map(|c| c != cmd).unwrap_or(true));
+        count - self.load_commands.len()
+    }
+    /// Returns the paths of the `LC_RPATH` load commands
+    pub fn rpaths(&self) -> error::Result<Vec<&str>> {
+        let mut rpaths = Vec::new();
+        for command in &self.load_commands {
+            if self.cmd(command)? == LC_RPATH {
+                rpaths.push(self.lc_str(command, 8)?);
+            }
+        }
+        Ok(rpaths)
+    }
+    /// Adds an `LC_RPATH` for `path`, as `install_name_tool -add_rpath` does
+    pub fn add_rpath(&mut self, path: &str) -> error::Result<()> {
+        if self.rpaths()?.contains(&path) {
+            return Err(error::Error::Malformed(format!("The binary already has the rpath {}", path)));
+        }
+        let mut fixed = vec![0; SIZEOF_RPATH_COMMAND];
+        fixed.pwrite_with(RpathCommand { cmd: LC_RPATH, cmdsize: 0, path: SIZEOF_RPATH_COMMAND as u32 }, 0, self.ctx.le)?;
+        let command = self.string_command(fixed, path)?;
+        self.load_commands.push(command);
+        Ok(())
+    }
+    /// Removes the `LC_RPATH` for `path`, as `install_name_tool -delete_rpath` does, returning whether there was one
+    pub fn remove_rpath(&mut self, path: &str) -> error::Result<bool> {
+        let mut index = None;
+        for (i, command) in self.load_commands.iter().enumerate() {
+            if self.cmd(command)? == LC_RPATH && self.lc_str(command, 8)? == path {
+                index = Some(i);
+                break;
+            }
+        }
+        Ok(index.map(|i| self.load_commands.remove(i)).is_some())
+    }
+    /// Appends an `LC_LOAD_DYLIB`, `LC_LOAD_WEAK_DYLIB`, `LC_REEXPORT_DYLIB`, `LC_LAZY_LOAD_DYLIB` or
+    /// `LC_LOAD_UPWARD_DYLIB` `cmd` for the dylib `name`; being last, it does not change the ordinals of the others
+    pub fn add_dylib(&mut self, cmd: u32, name: &str, current_version: u32, compatibility_version: u32) -> error::Result<()> {
+        match cmd {
+            LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB | LC_REEXPORT_DYLIB | LC_LAZY_LOAD_DYLIB | LC_LOAD_UPWARD_DYLIB => (),
+            _ => return Err(error::Error::Malformed(format!("{} does not load a dylib", cmd_to_str(cmd)))),
+        }
+        // the timestamp `ld` writes
+        let dylib = Dylib { name: 0, timestamp: 2, current_version: current_version, compatibility_version: compatibility_version };
+        let command = self.dylib_command(cmd, name, dylib)?;
+        self.load_commands.push(command);
+        Ok(())
+    }
+    /// Removes the load command of the dylib `name`, returning whether there was one.
+    ///
+    /// **Beware**, the library ordinals of the dylibs loaded after it, which binds and two-level namespace symbols
+    /// refer to, are shifted down by one
+    pub fn remove_dylib(&mut self, name: &str) -> error::Result<bool> {
+        let mut index = None;
+        for (i, command) in self.load_commands.iter().enumerate() {
+            match self.cmd(command)? {
+                LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB | LC_REEXPORT_DYLIB | LC_LAZY_LOAD_DYLIB | LC_LOAD_UPWARD_DYLIB => {
+                    if self.lc_str(command, 8)? == name {
+                        index = Some(i);
+                        break;
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(index.map(|i| self.load_commands.remove(i)).is_some())
+    }
+    /// Changes the dylib `old` this binary loads to `new`, as `install_name_tool -change` does, returning whether it
+    /// loads `old`
+    pub fn change_dylib(&mut self, old: &str, new: &str) -> error::Result<bool> {
+        let mut changed = false;
+        for i in 0..self.load_commands.len() {
+            let cmd = self.cmd(&self.load_commands[i])?;
+            match cmd {
+                LC_LOAD_DYLIB | LC_LOAD_WEAK_DYLIB | LC_REEXPORT_DYLIB | LC_LAZY_LOAD_DYLIB | LC_LOAD_UPWARD_DYLIB => {
+                    if self.lc_str(&self.load_commands[i], 8)? == old {
+                        let dylib = self.load_commands[i].pread_with::<DylibCommand>(0, self.ctx.le)?.dylib;
+                        self.load_commands[i] = self.dylib_command(cmd, new, dylib)?;
+                        changed = true;
+                    }
+                },
+                _ => (),
+            }
+        }
+        Ok(changed)
+    }
+    /// Sets the install name in the `LC_ID_DYLIB` of this dylib, as `install_name_tool -id` does
+    pub fn set_install_name(&mut self, name: &str) -> error::Result<()> {
+        for i in 0..self.load_commands.len() {
+            if self.cmd(&self.load_commands[i])? == LC_ID_DYLIB {
+                let dylib = self.load_commands[i].pread_with::<DylibCommand>(0, self.ctx.le)?.dylib;
+                self.load_commands[i] = self.dylib_command(LC_ID_DYLIB, name, dylib)?;
+                return Ok(())
+            }
+        }
+        Err(error::Error::Malformed(format!("Cannot set the install name {} of a binary without LC_ID_DYLIB", name)))
+    }
+    /// Removes the code signature, truncating it from the end of `__LINKEDIT` and the binary, as
+    /// `codesign --remove-signature` does; returns whether the binary was signed
+    pub fn remove_signature(&mut self) -> error::Result<bool> {
+        let mut signature = None;
+        for (i, command) in self.load_commands.iter().enumerate() {
+            if self.cmd(command)? == LC_CODE_SIGNATURE {
+                signature = Some((i, command.pread_with::<LinkeditDataCommand>(0, self.ctx.le)?));
+                break;
+            }
+        }
+        let (index, signature) = match signature {
+            Some(signature) => signature,
+            None => return Ok(false),
+        };
+        self.load_commands.remove(index);
+        let dataoff = signature.dataoff as u64;
+        for command in self.load_commands.iter_mut() {
+            match command.pread_with::<u32>(0, self.ctx.le)? {
+                LC_SEGMENT_64 => {
+                    let mut segment = command.pread_with::<SegmentCommand64>(0, self.ctx.le)?;
+                    if segment.name()? == "__LINKEDIT" && dataoff >= segment.fileoff {
+                        segment.filesize = ::std::cmp::min(segment.filesize, dataoff - segment.fileoff);
+                        command.pwrite_with(segment, 0, self.ctx.le)?;
+                    }
+                },
+                LC_SEGMENT => {
+                    let mut segment = command.pread_with::<SegmentCommand32>(0, self.ctx.le)?;
+                    if segment.name()? == "__LINKEDIT" && dataoff >= segment.fileoff as u64 {
+                        segment.filesize = ::std::cmp::min(segment.filesize, (dataoff - segment.fileoff as u64) as u32);
+                        command.pwrite_with(segment, 0, self.ctx.le)?;
+                    }
+                },
+                _ => (),
+            }
+        }
+        if (dataoff as usize) < self.data.len() {
+            self.data.truncate(dataoff as usize);
+        }
+        Ok(true)
+    }
+    /// Serializes the binary, with the header and load commands written in place of the originals
+    pub fn write(&self) -> error::Result<Vec<u8>> {
+        let header_size = self.header.size();
+        let sizeofcmds = self.load_commands.iter().map(|command| command.len()).sum::<usize>();
+        if header_size + sizeofcmds > self.commands_limit {
+            return Err(error::Error::Malformed(format!("Load commands of {} bytes do not fit in the {} bytes before the first section", sizeofcmds, self.commands_limit - header_size)));
+        }
+        let mut bytes = self.data.clone();
+        let end = ::std::cmp::min(::std::cmp::max(self.commands_end, header_size + sizeofcmds), bytes.len());
+        for byte in &mut bytes[header_size..end] {
+            *byte = 0;
+        }
+        let mut header = self.header;
+        header.ncmds = self.load_commands.len();
+        header.sizeofcmds = sizeofcmds as u32;
+        let offset = &mut 0;
+        bytes.gwrite_with(header, offset, self.ctx)?;
+        for command in &self.load_commands {
+            bytes.gwrite_with(command.as_slice(), offset, ())?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Returns the alignment, as a power of 2, `lipo` gives a thin binary of `cputype` in a fat container
+pub fn fat_align(cputype: CpuType) -> u32 {
+    match cputype {
+        CPU_TYPE_ARM | CPU_TYPE_ARM64 => 14,
+        _ => 12,
+    }
+}
+
+/// Combines the thin binaries `binaries` into a fat container, as `lipo -create` does
+pub fn fat(binaries: &[&[u8]]) -> error::Result<Vec<u8>> {
+    let mut arches: Vec<fat::FatArch> = Vec::with_capacity(binaries.len());
+    let mut offset = fat::SIZEOF_FAT_HEADER + binaries.len() * fat::SIZEOF_FAT_ARCH;
+    for bytes in binaries {
+        let header = bytes.pread::<header::Header>(0)?;
+        if arches.iter().any(|arch| arch.cputype() == header.cputype() && arch.cpusubtype() == header.cpusubtype()) {
+            return Err(error::Error::Malformed(format!("Cannot combine two binaries of cputype {:#x} and subtype {:#x}", header.cputype(), header.cpusubtype())));
+        }
+        let align = fat_align(header.cputype());
+        offset = align_up(offset, 1 << align);
+        if offset + bytes.len() > ::std::u32::MAX as usize {
+            return Err(error::Error::Malformed(format!("Fat binary of {} bytes is too large for 32-bit offsets", offset + bytes.len())));
+        }
+        arches.push(fat::FatArch {
+            cputype: header.cputype,
+            cpusubtype: header.cpusubtype,
+            offset: offset as u32,
+            size: bytes.len() as u32,
+            align: align,
+        });
+        offset += bytes.len();
+    }
+    let mut fat = vec![0; offset];
+    let header = fat::FatHeader { magic: fat::FAT_MAGIC, nfat_arch: arches.len() as u32 };
+    fat.pwrite_with(header, 0, scroll::BE)?;
+    for (i, (arch, bytes)) in arches.iter().zip(binaries).enumerate() {
+        fat.pwrite_with(*arch, fat::SIZEOF_FAT_HEADER + i * fat::SIZEOF_FAT_ARCH, scroll::BE)?;
+        fat.pwrite_with(*bytes, arch.offset as usize, ())?;
+    }
+    Ok(fat)
+}
+
+/// Replaces the binary of the cputype and subtype of the thin binary `binary` in the fat container `bytes` with it,
+/// as `lipo -replace` does
+pub fn replace_fat(bytes: &[u8], binary: &[u8]) -> error::Result<Vec<u8>> {
+    let header = binary.pread::<header::Header>(0)?;
+    let multi = MultiArch::new(bytes)?;
+    let mut binaries = Vec::with_capacity(multi.narches);
+    let mut replaced = false;
+    for arch in multi.arches()? {
+        if arch.cputype() == header.cputype() && arch.cpusubtype() == header.cpusubtype() {
+            binaries.push(binary);
+            replaced = true;
+        } else {
+            binaries.push(bytes.pread_with::<&[u8]>(arch.offset as usize, arch.size as usize)?);
+        }
+    }
+    if !replaced {
+        return Err(error::Error::Malformed(format!("Fat binary has no binary of cputype {:#x} and subtype {:#x} to replace", header.cputype(), header.cpusubtype())));
+    }
+    fat(&binaries)
+}
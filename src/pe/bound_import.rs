@@ -0,0 +1,126 @@
+//! The bound import directory, `IMAGE_BOUND_IMPORT_DESCRIPTOR`s, which records the timestamps of the dlls an image's
+//! import address tables were bound (pre-resolved) against
+//!
+//! The directory usually lives in the headers, after the section table, rather than in a section.
+
+use scroll::{self, Pread};
+use error;
+
+use pe::section_table;
+use pe::utils;
+use pe::data_directories;
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageBoundImportDescriptor {
+    pub time_date_stamp: u32,
+    /// The offset of the module name from the start of the bound import directory
+    pub offset_module_name: u16,
+    pub number_of_module_forwarder_refs: u16,
+}
+
+pub const SIZEOF_IMAGE_BOUND_IMPORT_DESCRIPTOR: usize = 8;
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageBoundForwarderRef {
+    pub time_date_stamp: u32,
+    /// The offset of the module name from the start of the bound import directory
+    pub offset_module_name: u16,
+    pub reserved: u16,
+}
+
+pub const SIZEOF_IMAGE_BOUND_FORWARDER_REF: usize = 8;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A dll which a bound module forwards some of the bound exports to
+pub struct BoundForwarderRef<'a> {
+    pub time_date_stamp: u32,
+    pub module_name: &'a str,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// A module the image was bound against
+pub struct BoundImportDescriptor<'a> {
+    /// The timestamp of the module when it was bound against; binding is stale if the module's timestamp differs
+    pub time_date_stamp: u32,
+    pub module_name: &'a str,
+    pub forwarder_refs: Vec<BoundForwarderRef<'a>>,
+}
+
+/// The list of modules an image was bound against
+pub type BoundImportDirectory<'a> = Vec<BoundImportDescriptor<'a>>;
+
+/// Parses the bound import directory described by `dd`, up to its null descriptor or its size, whichever comes first
+pub fn parse<'a>(bytes: &'a [u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<BoundImportDirectory<'a>> {
+    let rva = dd.virtual_address as usize;
+    // the headers are mapped at RVA 0, so an RVA below every section is also a file offset
+    let first_section = sections.iter().map(|section| section.virtual_address as usize).min().unwrap_or(0);
+    let start = match utils::find_offset(rva, sections) {
+        Some(offset) => offset,
+        None if rva < first_section => rva,
+        None => return Err(error::Error::Malformed(format!("Cannot map bound import directory rva {:#x} into offset", rva))),
+    };
+    let name = |offset: u16| -> error::Result<&'a str> {
+        Ok(bytes.pread::<&str>(start + offset as usize)?)
+    };
+    let end = start + dd.size as usize;
+    let offset = &mut start.clone();
+    let mut descriptors = Vec::new();
+    while *offset + SIZEOF_IMAGE_BOUND_IMPORT_DESCRIPTOR <= end {
+        let descriptor: ImageBoundImportDescriptor = bytes.gread_with(offset, scroll::LE)?;
+        if descriptor.offset_module_name == 0 { break }
+        let mut forwarder_refs = Vec::with_capacity(descriptor.number_of_module_forwarder_refs as usize);
+        for _ in 0..descriptor.number_of_module_forwarder_refs {
+            if *offset + SIZEOF_IMAGE_BOUND_FORWARDER_REF > end {
+                return Err(error::Error::Malformed(format!("Bound import forwarder ref at {:#x} runs past the end of the directory ({:#x})", *offset, end)));
+            }
+            let forwarder_ref: ImageBoundForwarderRef = bytes.gread_with(offset, scroll::LE)?;
+            forwarder_refs.push(BoundForwarderRef { time_date_stamp: forwarder_ref.time_date_stamp, module_name: name(forwarder_ref.offset_module_name)? });
+        }
+        let descriptor = BoundImportDescriptor {
+            time_date_stamp: descriptor.time_date_stamp,
+            module_name: name(descriptor.offset_module_name)?,
+            forwarder_refs: forwarder_refs,
+        };
+        debug!("{:?}", descriptor);
+        descriptors.push(descriptor);
+    }
+    Ok(descriptors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bound_imports() {
+        let mut bytes = vec![0u8; 0x100];
+        {
+            use scroll::Pwrite;
+            bytes.pwrite_with(ImageBoundImportDescriptor { time_date_stamp: 0x1111, offset_module_name: 0x20, number_of_module_forwarder_refs: 1 }, 0x40, scroll::LE).unwrap();
+            bytes.pwrite_with(ImageBoundForwarderRef { time_date_stamp: 0x2222, offset_module_name: 0x2d, reserved: 0 }, 0x48, scroll::LE).unwrap();
+            bytes.pwrite_with(ImageBoundImportDescriptor { time_date_stamp: 0x3333, offset_module_name: 0x38, number_of_module_forwarder_refs: 0 }, 0x50, scroll::LE).unwrap();
+            bytes[0x60..0x6d].copy_from_slice(b"KERNEL32.dll\0");
+            bytes[0x6d..0x77].copy_from_slice(b"NTDLL.DLL\0");
+            bytes[0x78..0x83].copy_from_slice(b"USER32.dll\0");
+        }
+        let dd = data_directories::DataDirectory { virtual_address: 0x40, size: 0x48 };
+        let mut text = section_table::SectionTable::default();
+        text.virtual_address = 0x1000;
+        text.virtual_size = 0x100;
+        let bound = parse(&bytes, &dd, &[text]).unwrap();
+        assert_eq!(bound.len(), 2);
+        assert_eq!(bound[0].module_name, "KERNEL32.dll");
+        assert_eq!(bound[0].forwarder_refs, vec![BoundForwarderRef { time_date_stamp: 0x2222, module_name: "NTDLL.DLL" }]);
+        assert_eq!(bound[1].module_name, "USER32.dll");
+        assert_eq!(bound[1].time_date_stamp, 0x3333);
+        // a directory without a null descriptor ends at its size
+        let dd = data_directories::DataDirectory { virtual_address: 0x40, size: 0x10 };
+        let bound = parse(&bytes, &dd, &[text]).unwrap();
+        assert_eq!(bound.len(), 1);
+        assert_eq!(bound[0].module_name, "KERNEL32.dll");
+    }
+}
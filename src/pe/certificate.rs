@@ -0,0 +1,229 @@
+//! The attribute certificate table, i.e., Authenticode signatures, and the image digests computed over a PE binary
+//!
+//! Unlike every other data directory, the certificate table's `virtual_address` is a file offset, and the table is not
+//! mapped into memory by the loader.
+
+use core::ops::Range;
+use scroll::{self, Pread};
+use error;
+
+use pe::header;
+use pe::optional_header;
+use pe::data_directories;
+
+pub const WIN_CERT_REVISION_1_0: u16 = 0x0100;
+pub const WIN_CERT_REVISION_2_0: u16 = 0x0200;
+
+/// An X.509 certificate; not supported by Windows
+pub const WIN_CERT_TYPE_X509: u16 = 0x0001;
+/// A PKCS#7 `SignedData` structure, i.e., an Authenticode signature
+pub const WIN_CERT_TYPE_PKCS_SIGNED_DATA: u16 = 0x0002;
+pub const WIN_CERT_TYPE_RESERVED_1: u16 = 0x0003;
+/// Terminal server protocol stack certificate signing; not supported by Windows
+pub const WIN_CERT_TYPE_TS_STACK_SIGNED: u16 = 0x0004;
+
+/// The size of the `WIN_CERTIFICATE` header preceding the certificate bytes
+pub const SIZEOF_WIN_CERTIFICATE_HEADER: usize = 8;
+
+/// The offset of `CheckSum` from the start of the optional header, identical for PE32 and PE32+
+pub const CHECKSUM_OFFSET: usize = 64;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A `WIN_CERTIFICATE` entry in the attribute certificate table
+pub struct AttributeCertificate<'a> {
+    /// The length of this entry, including the header but excluding the padding to the next 8 byte boundary
+    pub length: u32,
+    pub revision: u16,
+    pub certificate_type: u16,
+    /// The certificate bytes, e.g., a DER encoded PKCS#7 `SignedData` blob
+    pub certificate: &'a [u8],
+}
+
+impl<'a> AttributeCertificate<'a> {
+    pub fn parse(bytes: &'a [u8], offset: &mut usize) -> error::Result<Self> {
+        let start = *offset;
+        let length: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let revision = bytes.gread_with(offset, scroll::LE)?;
+        let certificate_type = bytes.gread_with(offset, scroll::LE)?;
+        if (length as usize) < SIZEOF_WIN_CERTIFICATE_HEADER {
+            return Err(error::Error::Malformed(format!("Attribute certificate at {:#x} has bad length {:#x}", start, length)));
+        }
+        let certificate: &'a [u8] = bytes.gread_with(offset, length as usize - SIZEOF_WIN_CERTIFICATE_HEADER)?;
+        // entries are quadword aligned
+        *offset = (*offset + 7) & !7;
+        Ok(AttributeCertificate {
+            length: length,
+            revision: revision,
+            certificate_type: certificate_type,
+            certificate: certificate,
+        })
+    }
+}
+
+/// The list of attribute certificates in a binary
+pub type CertificateDirectoryTable<'a> = Vec<AttributeCertificate<'a>>;
+
+/// Parses every attribute certificate in the table described by `dd`, whose `virtual_address` is a file offset
+pub fn parse<'a>(bytes: &'a [u8], dd: &data_directories::DataDirectory) -> error::Result<CertificateDirectoryTable<'a>> {
+    let start = dd.virtual_address as usize;
+    let end = start.checked_add(dd.size as usize).ok_or(error::Error::Malformed(format!("Certificate table size ({:#x}) overflows", dd.size)))?;
+    if end > bytes.len() {
+        return Err(error::Error::Malformed(format!("Certificate table at {:#x} with size {:#x} is out of bounds for {} bytes", start, dd.size, bytes.len())));
+    }
+    let table = &bytes[..end];
+    let offset = &mut start.clone();
+    let mut certificates = Vec::new();
+    while *offset + SIZEOF_WIN_CERTIFICATE_HEADER <= end {
+        let certificate = AttributeCertificate::parse(table, offset)?;
+        debug!("certificate revision {:#x} type {:#x} length {:#x}", certificate.revision, certificate.certificate_type, certificate.length);
+        certificates.push(certificate);
+    }
+    Ok(certificates)
+}
+
+/// The file offset of the optional header's `CheckSum` field, if the binary has an optional header
+pub fn checksum_offset(header: &header::Header) -> Option<usize> {
+    match header.optional_header {
+        Some(_) => Some(header.dos_header.pe_pointer as usize + header::SIZEOF_COFF_HEADER + CHECKSUM_OFFSET),
+        None => None,
+    }
+}
+
+/// The file offset of the certificate table's data directory entry, if the binary has one
+pub fn certificate_table_entry_offset(header: &header::Header) -> Option<usize> {
+    match header.optional_header {
+        Some(ref optional_header) if optional_header.windows_fields.number_of_rva_and_sizes > 4 => {
+            let fields = if optional_header.standard_fields.magic == optional_header::MAGIC_64 {
+                optional_header::SIZEOF_STANDARD_FIELDS_64 + optional_header::SIZEOF_WINDOWS_FIELDS_64
+            } else {
+                optional_header::SIZEOF_STANDARD_FIELDS_32 + optional_header::SIZEOF_WINDOWS_FIELDS_32
+            };
+            Some(header.dos_header.pe_pointer as usize + header::SIZEOF_COFF_HEADER + fields + 4 * data_directories::SIZEOF_DATA_DIRECTORY)
+        },
+        _ => None,
+    }
+}
+
+/// Computes the value of the optional header's `CheckSum` field for the image in `bytes`, as `imagehlp!CheckSumMappedFile` does.
+///
+/// The 4 bytes at `checksum_offset` (see [`checksum_offset`](fn.checksum_offset.html)) are treated as zero.
+pub fn compute_checksum(bytes: &[u8], checksum_offset: usize) -> u32 {
+    let mut sum: u64 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if i >= checksum_offset && i < checksum_offset + 4 {
+            i += 2;
+            continue;
+        }
+        let lo = bytes[i] as u64;
+        let hi = if i + 1 < bytes.len() { bytes[i + 1] as u64 } else { 0 };
+        sum += lo | (hi << 8);
+        sum = (sum & 0xffff) + (sum >> 16);
+        i += 2;
+    }
+    sum = (sum & 0xffff) + (sum >> 16);
+    (sum as u32).wrapping_add(bytes.len() as u32)
+}
+
+/// Returns the file ranges of `bytes` which are hashed to compute the Authenticode digest of the image, in hash order.
+///
+/// Per the Authenticode specification, this is the headers excluding the `CheckSum` field and the certificate table's
+/// data directory entry, followed by each section's raw data in file order, followed by any remaining data excluding the
+/// certificate table itself.
+pub fn authenticode_ranges(bytes: &[u8], header: &header::Header, sections: &[::pe::section_table::SectionTable]) -> error::Result<Vec<Range<usize>>> {
+    let optional_header = match header.optional_header {
+        Some(ref optional_header) => optional_header,
+        None => return Err(error::Error::Malformed("Cannot compute the Authenticode digest of a binary without an optional header".to_string())),
+    };
+    let len = bytes.len();
+    let size_of_headers = optional_header.windows_fields.size_of_headers as usize;
+    if size_of_headers > len {
+        return Err(error::Error::Malformed(format!("SizeOfHeaders ({:#x}) is larger than the binary ({:#x})", size_of_headers, len)));
+    }
+    let checksum = checksum_offset(header).unwrap();
+    let mut ranges = Vec::new();
+    ranges.push(0..checksum);
+    match certificate_table_entry_offset(header) {
+        Some(entry) => {
+            ranges.push(checksum + 4..entry);
+            ranges.push(entry + data_directories::SIZEOF_DATA_DIRECTORY..size_of_headers);
+        },
+        None => {
+            ranges.push(checksum + 4..size_of_headers);
+        }
+    }
+
+    let mut sections = sections.iter().filter(|section| section.size_of_raw_data > 0).collect::<Vec<_>>();
+    sections.sort_by_key(|section| section.pointer_to_raw_data);
+    let mut sum_of_bytes_hashed = size_of_headers;
+    for section in sections {
+        let start = section.pointer_to_raw_data as usize;
+        let end = start + section.size_of_raw_data as usize;
+        if end > len {
+            return Err(error::Error::Malformed(format!("Section {} with raw data {:#x}..{:#x} is out of bounds for {} bytes", section.name().unwrap_or(""), start, end, len)));
+        }
+        ranges.push(start..end);
+        sum_of_bytes_hashed = ::std::cmp::max(sum_of_bytes_hashed, end);
+    }
+
+    let (cert_start, cert_end) = match *optional_header.data_directories.get_certificate_table() {
+        Some(dd) => (dd.virtual_address as usize, dd.virtual_address as usize + dd.size as usize),
+        None => (len, len),
+    };
+    if sum_of_bytes_hashed < len {
+        if cert_start >= sum_of_bytes_hashed && cert_start < len {
+            ranges.push(sum_of_bytes_hashed..cert_start);
+            ranges.push(::std::cmp::min(cert_end, len)..len);
+        } else {
+            ranges.push(sum_of_bytes_hashed..len);
+        }
+    }
+    Ok(ranges.into_iter().filter(|range| range.start < range.end).collect())
+}
+
+/// Feeds every Authenticode-hashed range of `bytes`, in order, to `update`; a hasher of your choosing (e.g. SHA-256)
+/// fed this way produces the digest stored in the image's PKCS#7 `SpcIndirectDataContent`.
+pub fn authenticode_digest<F: FnMut(&[u8])>(bytes: &[u8], header: &header::Header, sections: &[::pe::section_table::SectionTable], mut update: F) -> error::Result<()> {
+    for range in authenticode_ranges(bytes, header, sections)? {
+        update(&bytes[range]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_certificate_table() {
+        let mut bytes = vec![0u8; 0x40];
+        {
+            use scroll::Pwrite;
+            bytes.pwrite_with(13u32, 0x10, scroll::LE).unwrap();
+            bytes.pwrite_with(WIN_CERT_REVISION_2_0, 0x14, scroll::LE).unwrap();
+            bytes.pwrite_with(WIN_CERT_TYPE_PKCS_SIGNED_DATA, 0x16, scroll::LE).unwrap();
+            bytes[0x18..0x1d].copy_from_slice(b"\x30\x82\x01\x02\x03");
+            bytes.pwrite_with(9u32, 0x20, scroll::LE).unwrap();
+            bytes.pwrite_with(WIN_CERT_REVISION_2_0, 0x24, scroll::LE).unwrap();
+            bytes.pwrite_with(WIN_CERT_TYPE_X509, 0x26, scroll::LE).unwrap();
+        }
+        let dd = data_directories::DataDirectory { virtual_address: 0x10, size: 0x20 };
+        let certificates = parse(&bytes, &dd).unwrap();
+        assert_eq!(certificates.len(), 2);
+        assert_eq!(certificates[0].certificate_type, WIN_CERT_TYPE_PKCS_SIGNED_DATA);
+        assert_eq!(certificates[0].certificate, b"\x30\x82\x01\x02\x03");
+        assert_eq!(certificates[1].certificate_type, WIN_CERT_TYPE_X509);
+        assert_eq!(certificates[1].certificate.len(), 1);
+    }
+
+    #[test]
+    fn checksum_skips_checksum_field() {
+        let bytes = [0x01, 0x00, 0x34, 0x12, 0x00, 0x00, 0x02];
+        // 0x0001 + 0x0002 + len 7
+        assert_eq!(compute_checksum(&bytes, 2), 10);
+        // 0x0001 + 0x1234 + 0x0000 + 0x0002 + len 7
+        assert_eq!(compute_checksum(&bytes, 0x100), 0x123e);
+        // end around carry
+        assert_eq!(compute_checksum(&[0xff, 0xff, 0x02, 0x00], 0x100), 0x0002 + 4);
+    }
+}
@@ -97,3 +97,89 @@ pub fn is_dll(characteristics: u16) -> bool {
 pub fn is_exe(characteristics: u16) -> bool {
   characteristics & IMAGE_FILE_EXECUTABLE_IMAGE == IMAGE_FILE_EXECUTABLE_IMAGE
 }
+
+pub fn is_relocs_stripped(characteristics: u16) -> bool {
+  characteristics & IMAGE_FILE_RELOCS_STRIPPED == IMAGE_FILE_RELOCS_STRIPPED
+}
+
+// DllCharacteristics, in the optional header's Windows specific fields
+
+/// The image can handle a high entropy 64-bit virtual address space
+pub const IMAGE_DLLCHARACTERISTICS_HIGH_ENTROPY_VA: u16 = 0x0020;
+/// The image can be relocated at load time, i.e., ASLR
+pub const IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE: u16 = 0x0040;
+/// Code integrity checks are enforced
+pub const IMAGE_DLLCHARACTERISTICS_FORCE_INTEGRITY: u16 = 0x0080;
+/// The image is compatible with data execution prevention (DEP)
+pub const IMAGE_DLLCHARACTERISTICS_NX_COMPAT: u16 = 0x0100;
+/// The image is isolation aware, but should not be isolated
+pub const IMAGE_DLLCHARACTERISTICS_NO_ISOLATION: u16 = 0x0200;
+/// The image does not use structured exception handling (SEH); no SE handler may be called in this image
+pub const IMAGE_DLLCHARACTERISTICS_NO_SEH: u16 = 0x0400;
+/// Do not bind the image
+pub const IMAGE_DLLCHARACTERISTICS_NO_BIND: u16 = 0x0800;
+/// The image must execute in an AppContainer
+pub const IMAGE_DLLCHARACTERISTICS_APPCONTAINER: u16 = 0x1000;
+/// A WDM driver
+pub const IMAGE_DLLCHARACTERISTICS_WDM_DRIVER: u16 = 0x2000;
+/// The image supports Control Flow Guard
+pub const IMAGE_DLLCHARACTERISTICS_GUARD_CF: u16 = 0x4000;
+/// The image is terminal server aware
+pub const IMAGE_DLLCHARACTERISTICS_TERMINAL_SERVER_AWARE: u16 = 0x8000;
+
+/// Returns the name of the single DllCharacteristics `flag`, e.g. `"DYNAMIC_BASE"`
+pub fn dll_characteristic_to_str(flag: u16) -> &'static str {
+  match flag {
+    IMAGE_DLLCHARACTERISTICS_HIGH_ENTROPY_VA => "HIGH_ENTROPY_VA",
+    IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE => "DYNAMIC_BASE",
+    IMAGE_DLLCHARACTERISTICS_FORCE_INTEGRITY => "FORCE_INTEGRITY",
+    IMAGE_DLLCHARACTERISTICS_NX_COMPAT => "NX_COMPAT",
+    IMAGE_DLLCHARACTERISTICS_NO_ISOLATION => "NO_ISOLATION",
+    IMAGE_DLLCHARACTERISTICS_NO_SEH => "NO_SEH",
+    IMAGE_DLLCHARACTERISTICS_NO_BIND => "NO_BIND",
+    IMAGE_DLLCHARACTERISTICS_APPCONTAINER => "APPCONTAINER",
+    IMAGE_DLLCHARACTERISTICS_WDM_DRIVER => "WDM_DRIVER",
+    IMAGE_DLLCHARACTERISTICS_GUARD_CF => "GUARD_CF",
+    IMAGE_DLLCHARACTERISTICS_TERMINAL_SERVER_AWARE => "TERMINAL_SERVER_AWARE",
+    _ => "UNKNOWN_DLL_CHARACTERISTIC",
+  }
+}
+
+/// Returns the name of every flag set in `dll_characteristics`
+pub fn dll_characteristics_to_strs(dll_characteristics: u16) -> Vec<&'static str> {
+  (0..16).map(|bit| 1u16 << bit).filter(|flag| dll_characteristics & flag != 0).map(dll_characteristic_to_str).collect()
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A summary of the exploit mitigations a PE binary opts into
+pub struct SecurityFeatures {
+  /// ASLR: `DYNAMIC_BASE` is set and relocations have not been stripped
+  pub aslr: bool,
+  /// `HIGH_ENTROPY_VA` is set on a 64-bit binary which has ASLR
+  pub high_entropy_va: bool,
+  /// DEP: `NX_COMPAT` is set
+  pub nx: bool,
+  /// Control Flow Guard: `GUARD_CF` is set
+  pub cfg: bool,
+  /// `NO_SEH` is set, i.e., the binary has no exception handlers to abuse
+  pub no_seh: bool,
+  /// The load config directory has a safe exception handler table (32-bit x86 only)
+  pub safe_seh: bool,
+  /// `FORCE_INTEGRITY` is set, i.e., the binary must be signed to load
+  pub force_integrity: bool,
+  /// `APPCONTAINER` is set
+  pub app_container: bool,
+  /// The extended DLL characteristics debug entry marks the binary as compatible with CET shadow stacks
+  pub cet_compat: bool,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dll_characteristics_names() {
+    let flags = IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE | IMAGE_DLLCHARACTERISTICS_NX_COMPAT | IMAGE_DLLCHARACTERISTICS_GUARD_CF;
+    assert_eq!(dll_characteristics_to_strs(flags), vec!["DYNAMIC_BASE", "NX_COMPAT", "GUARD_CF"]);
+  }
+}
@@ -0,0 +1,538 @@
+//! The .NET CLR runtime header (`IMAGE_COR20_HEADER`) and ECMA-335 metadata
+//!
+//! A managed binary stores a CLI header in the CLR runtime header data directory, which points to the metadata root.
+//! The metadata root lists the streams (heaps and the `#~` tables stream) which together describe every type, method
+//! and assembly reference in the binary.
+
+use scroll::{self, Pread};
+use error;
+
+use pe::section_table;
+use pe::utils;
+use pe::data_directories;
+
+/// The CLI header, `IMAGE_COR20_HEADER`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct Cor20Header {
+    pub cb: u32,
+    pub major_runtime_version: u16,
+    pub minor_runtime_version: u16,
+    pub metadata: data_directories::DataDirectory,
+    pub flags: u32,
+    /// A metadata token for the entry point method, or its RVA if `COMIMAGE_FLAGS_NATIVE_ENTRYPOINT` is set
+    pub entry_point_token: u32,
+    pub resources: data_directories::DataDirectory,
+    pub strong_name_signature: data_directories::DataDirectory,
+    pub code_manager_table: data_directories::DataDirectory,
+    pub vtable_fixups: data_directories::DataDirectory,
+    pub export_address_table_jumps: data_directories::DataDirectory,
+    pub managed_native_header: data_directories::DataDirectory,
+}
+
+pub const SIZEOF_COR20_HEADER: usize = 72;
+
+pub const COMIMAGE_FLAGS_ILONLY: u32 = 0x0000_0001;
+pub const COMIMAGE_FLAGS_32BITREQUIRED: u32 = 0x0000_0002;
+pub const COMIMAGE_FLAGS_IL_LIBRARY: u32 = 0x0000_0004;
+pub const COMIMAGE_FLAGS_STRONGNAMESIGNED: u32 = 0x0000_0008;
+pub const COMIMAGE_FLAGS_NATIVE_ENTRYPOINT: u32 = 0x0000_0010;
+pub const COMIMAGE_FLAGS_TRACKDEBUGDATA: u32 = 0x0001_0000;
+pub const COMIMAGE_FLAGS_32BITPREFERRED: u32 = 0x0002_0000;
+
+impl Cor20Header {
+    /// Whether this image contains only IL code, i.e., no native code
+    pub fn is_il_only(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_ILONLY != 0
+    }
+    /// Whether this image can only be loaded into a 32-bit process
+    pub fn requires_32_bit(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_32BITREQUIRED != 0
+    }
+    /// Whether this image has a strong name signature
+    pub fn is_strong_name_signed(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_STRONGNAMESIGNED != 0
+    }
+    /// Whether `entry_point_token` is a native entry point RVA rather than a metadata token
+    pub fn has_native_entry_point(&self) -> bool {
+        self.flags & COMIMAGE_FLAGS_NATIVE_ENTRYPOINT != 0
+    }
+}
+
+/// "BSJB", little endian
+pub const METADATA_SIGNATURE: u32 = 0x424a_5342;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A stream header in the metadata root
+pub struct StreamHeader<'a> {
+    /// The offset of the stream, relative to the metadata root
+    pub offset: u32,
+    pub size: u32,
+    /// The name of the stream, e.g. `#~`, `#Strings`, `#US`, `#GUID` or `#Blob`
+    pub name: &'a str,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// The metadata root, which describes where each metadata stream lives
+pub struct MetadataRoot<'a> {
+    pub signature: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub reserved: u32,
+    pub length: u32,
+    /// The runtime version this image was built against, e.g., `v4.0.30319`
+    pub version: &'a str,
+    pub flags: u16,
+    pub streams: Vec<StreamHeader<'a>>,
+}
+
+impl<'a> MetadataRoot<'a> {
+    pub fn parse(bytes: &'a [u8], offset: usize) -> error::Result<Self> {
+        let offset = &mut offset.clone();
+        let signature = bytes.gread_with(offset, scroll::LE)?;
+        if signature != METADATA_SIGNATURE {
+            return Err(error::Error::BadMagic(signature as u64));
+        }
+        let major_version = bytes.gread_with(offset, scroll::LE)?;
+        let minor_version = bytes.gread_with(offset, scroll::LE)?;
+        let reserved = bytes.gread_with(offset, scroll::LE)?;
+        let length: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let version_bytes: &'a [u8] = bytes.gread_with(offset, length as usize)?;
+        let version = version_bytes.pread::<&str>(0)?;
+        let flags = bytes.gread_with(offset, scroll::LE)?;
+        let nstreams: u16 = bytes.gread_with(offset, scroll::LE)?;
+        let mut streams = Vec::with_capacity(nstreams as usize);
+        for _ in 0..nstreams {
+            let stream_offset = bytes.gread_with(offset, scroll::LE)?;
+            let size = bytes.gread_with(offset, scroll::LE)?;
+            let name: &'a str = bytes.pread(*offset)?;
+            // the name is null terminated and padded to the next 4 byte boundary
+            *offset += (name.len() + 1 + 3) & !3;
+            streams.push(StreamHeader { offset: stream_offset, size: size, name: name });
+        }
+        Ok(MetadataRoot {
+            signature: signature,
+            major_version: major_version,
+            minor_version: minor_version,
+            reserved: reserved,
+            length: length,
+            version: version,
+            flags: flags,
+            streams: streams,
+        })
+    }
+    /// Finds the stream named `name`
+    pub fn stream(&self, name: &str) -> Option<&StreamHeader<'a>> {
+        self.streams.iter().find(|stream| stream.name == name)
+    }
+}
+
+// Metadata table numbers
+pub const TABLE_MODULE: usize = 0x00;
+pub const TABLE_TYPE_REF: usize = 0x01;
+pub const TABLE_TYPE_DEF: usize = 0x02;
+pub const TABLE_FIELD_PTR: usize = 0x03;
+pub const TABLE_FIELD: usize = 0x04;
+pub const TABLE_METHOD_PTR: usize = 0x05;
+pub const TABLE_METHOD_DEF: usize = 0x06;
+pub const TABLE_PARAM_PTR: usize = 0x07;
+pub const TABLE_PARAM: usize = 0x08;
+pub const TABLE_INTERFACE_IMPL: usize = 0x09;
+pub const TABLE_MEMBER_REF: usize = 0x0a;
+pub const TABLE_CONSTANT: usize = 0x0b;
+pub const TABLE_CUSTOM_ATTRIBUTE: usize = 0x0c;
+pub const TABLE_FIELD_MARSHAL: usize = 0x0d;
+pub const TABLE_DECL_SECURITY: usize = 0x0e;
+pub const TABLE_CLASS_LAYOUT: usize = 0x0f;
+pub const TABLE_FIELD_LAYOUT: usize = 0x10;
+pub const TABLE_STAND_ALONE_SIG: usize = 0x11;
+pub const TABLE_EVENT_MAP: usize = 0x12;
+pub const TABLE_EVENT_PTR: usize = 0x13;
+pub const TABLE_EVENT: usize = 0x14;
+pub const TABLE_PROPERTY_MAP: usize = 0x15;
+pub const TABLE_PROPERTY_PTR: usize = 0x16;
+pub const TABLE_PROPERTY: usize = 0x17;
+pub const TABLE_METHOD_SEMANTICS: usize = 0x18;
+pub const TABLE_METHOD_IMPL: usize = 0x19;
+pub const TABLE_MODULE_REF: usize = 0x1a;
+pub const TABLE_TYPE_SPEC: usize = 0x1b;
+pub const TABLE_IMPL_MAP: usize = 0x1c;
+pub const TABLE_FIELD_RVA: usize = 0x1d;
+pub const TABLE_ENC_LOG: usize = 0x1e;
+pub const TABLE_ENC_MAP: usize = 0x1f;
+pub const TABLE_ASSEMBLY: usize = 0x20;
+pub const TABLE_ASSEMBLY_PROCESSOR: usize = 0x21;
+pub const TABLE_ASSEMBLY_OS: usize = 0x22;
+pub const TABLE_ASSEMBLY_REF: usize = 0x23;
+pub const TABLE_ASSEMBLY_REF_PROCESSOR: usize = 0x24;
+pub const TABLE_ASSEMBLY_REF_OS: usize = 0x25;
+pub const TABLE_FILE: usize = 0x26;
+pub const TABLE_EXPORTED_TYPE: usize = 0x27;
+pub const TABLE_MANIFEST_RESOURCE: usize = 0x28;
+pub const TABLE_NESTED_CLASS: usize = 0x29;
+pub const TABLE_GENERIC_PARAM: usize = 0x2a;
+pub const TABLE_METHOD_SPEC: usize = 0x2b;
+pub const TABLE_GENERIC_PARAM_CONSTRAINT: usize = 0x2c;
+
+/// The number of tables whose schema we know
+const NUM_KNOWN_TABLES: usize = 0x2d;
+/// The maximum number of tables, i.e., the bits in `TablesHeader::valid`
+pub const NUM_TABLES: usize = 64;
+/// Stands in for unused tags in a coded index
+const UNUSED: usize = NUM_TABLES;
+
+/// A coded index: the number of tag bits and the tables each tag refers to
+struct CodedIndex(u32, &'static [usize]);
+
+const TYPE_DEF_OR_REF: CodedIndex = CodedIndex(2, &[TABLE_TYPE_DEF, TABLE_TYPE_REF, TABLE_TYPE_SPEC]);
+const HAS_CONSTANT: CodedIndex = CodedIndex(2, &[TABLE_FIELD, TABLE_PARAM, TABLE_PROPERTY]);
+const HAS_CUSTOM_ATTRIBUTE: CodedIndex = CodedIndex(5, &[
+    TABLE_METHOD_DEF, TABLE_FIELD, TABLE_TYPE_REF, TABLE_TYPE_DEF, TABLE_PARAM, TABLE_INTERFACE_IMPL, TABLE_MEMBER_REF,
+    TABLE_MODULE, TABLE_DECL_SECURITY, TABLE_PROPERTY, TABLE_EVENT, TABLE_STAND_ALONE_SIG, TABLE_MODULE_REF,
+    TABLE_TYPE_SPEC, TABLE_ASSEMBLY, TABLE_ASSEMBLY_REF, TABLE_FILE, TABLE_EXPORTED_TYPE, TABLE_MANIFEST_RESOURCE,
+    TABLE_GENERIC_PARAM, TABLE_GENERIC_PARAM_CONSTRAINT, TABLE_METHOD_SPEC]);
+const HAS_FIELD_MARSHAL: CodedIndex = CodedIndex(1, &[TABLE_FIELD, TABLE_PARAM]);
+const HAS_DECL_SECURITY: CodedIndex = CodedIndex(2, &[TABLE_TYPE_DEF, TABLE_METHOD_DEF, TABLE_ASSEMBLY]);
+const MEMBER_REF_PARENT: CodedIndex = CodedIndex(3, &[TABLE_TYPE_DEF, TABLE_TYPE_REF, TABLE_MODULE_REF, TABLE_METHOD_DEF, TABLE_TYPE_SPEC]);
+const HAS_SEMANTICS: CodedIndex = CodedIndex(1, &[TABLE_EVENT, TABLE_PROPERTY]);
+const METHOD_DEF_OR_REF: CodedIndex = CodedIndex(1, &[TABLE_METHOD_DEF, TABLE_MEMBER_REF]);
+const MEMBER_FORWARDED: CodedIndex = CodedIndex(1, &[TABLE_FIELD, TABLE_METHOD_DEF]);
+const IMPLEMENTATION: CodedIndex = CodedIndex(2, &[TABLE_FILE, TABLE_ASSEMBLY_REF, TABLE_EXPORTED_TYPE]);
+const CUSTOM_ATTRIBUTE_TYPE: CodedIndex = CodedIndex(3, &[UNUSED, UNUSED, TABLE_METHOD_DEF, TABLE_MEMBER_REF, UNUSED]);
+const RESOLUTION_SCOPE: CodedIndex = CodedIndex(2, &[TABLE_MODULE, TABLE_MODULE_REF, TABLE_ASSEMBLY_REF, TABLE_TYPE_REF]);
+const TYPE_OR_METHOD_DEF: CodedIndex = CodedIndex(1, &[TABLE_TYPE_DEF, TABLE_METHOD_DEF]);
+
+/// The type of a column in a metadata table
+enum Column {
+    U16,
+    U32,
+    /// Two single byte columns, only used by `Constant`
+    U8U8,
+    String,
+    Guid,
+    Blob,
+    Table(usize),
+    Coded(&'static CodedIndex),
+}
+
+use self::Column::*;
+
+/// The columns of every known metadata table, per ECMA-335 II.22
+const SCHEMA: [&'static [Column]; NUM_KNOWN_TABLES] = [
+    /* Module */ &[U16, String, Guid, Guid, Guid],
+    /* TypeRef */ &[Coded(&RESOLUTION_SCOPE), String, String],
+    /* TypeDef */ &[U32, String, String, Coded(&TYPE_DEF_OR_REF), Table(TABLE_FIELD), Table(TABLE_METHOD_DEF)],
+    /* FieldPtr */ &[Table(TABLE_FIELD)],
+    /* Field */ &[U16, String, Blob],
+    /* MethodPtr */ &[Table(TABLE_METHOD_DEF)],
+    /* MethodDef */ &[U32, U16, U16, String, Blob, Table(TABLE_PARAM)],
+    /* ParamPtr */ &[Table(TABLE_PARAM)],
+    /* Param */ &[U16, U16, String],
+    /* InterfaceImpl */ &[Table(TABLE_TYPE_DEF), Coded(&TYPE_DEF_OR_REF)],
+    /* MemberRef */ &[Coded(&MEMBER_REF_PARENT), String, Blob],
+    /* Constant */ &[U8U8, Coded(&HAS_CONSTANT), Blob],
+    /* CustomAttribute */ &[Coded(&HAS_CUSTOM_ATTRIBUTE), Coded(&CUSTOM_ATTRIBUTE_TYPE), Blob],
+    /* FieldMarshal */ &[Coded(&HAS_FIELD_MARSHAL), Blob],
+    /* DeclSecurity */ &[U16, Coded(&HAS_DECL_SECURITY), Blob],
+    /* ClassLayout */ &[U16, U32, Table(TABLE_TYPE_DEF)],
+    /* FieldLayout */ &[U32, Table(TABLE_FIELD)],
+    /* StandAloneSig */ &[Blob],
+    /* EventMap */ &[Table(TABLE_TYPE_DEF), Table(TABLE_EVENT)],
+    /* EventPtr */ &[Table(TABLE_EVENT)],
+    /* Event */ &[U16, String, Coded(&TYPE_DEF_OR_REF)],
+    /* PropertyMap */ &[Table(TABLE_TYPE_DEF), Table(TABLE_PROPERTY)],
+    /* PropertyPtr */ &[Table(TABLE_PROPERTY)],
+    /* Property */ &[U16, String, Blob],
+    /* MethodSemantics */ &[U16, Table(TABLE_METHOD_DEF), Coded(&HAS_SEMANTICS)],
+    /* MethodImpl */ &[Table(TABLE_TYPE_DEF), Coded(&METHOD_DEF_OR_REF), Coded(&METHOD_DEF_OR_REF)],
+    /* ModuleRef */ &[String],
+    /* TypeSpec */ &[Blob],
+    /* ImplMap */ &[U16, Coded(&MEMBER_FORWARDED), String, Table(TABLE_MODULE_REF)],
+    /* FieldRVA */ &[U32, Table(TABLE_FIELD)],
+    /* EncLog */ &[U32, U32],
+    /* EncMap */ &[U32],
+    /* Assembly */ &[U32, U16, U16, U16, U16, U32, Blob, String, String],
+    /* AssemblyProcessor */ &[U32],
+    /* AssemblyOS */ &[U32, U32, U32],
+    /* AssemblyRef */ &[U16, U16, U16, U16, U32, Blob, String, String, Blob],
+    /* AssemblyRefProcessor */ &[U32, Table(TABLE_ASSEMBLY_REF)],
+    /* AssemblyRefOS */ &[U32, U32, U32, Table(TABLE_ASSEMBLY_REF)],
+    /* File */ &[U32, String, Blob],
+    /* ExportedType */ &[U32, U32, String, String, Coded(&IMPLEMENTATION)],
+    /* ManifestResource */ &[U32, U32, String, Coded(&IMPLEMENTATION)],
+    /* NestedClass */ &[Table(TABLE_TYPE_DEF), Table(TABLE_TYPE_DEF)],
+    /* GenericParam */ &[U16, U16, Coded(&TYPE_OR_METHOD_DEF), String],
+    /* MethodSpec */ &[Coded(&METHOD_DEF_OR_REF), Blob],
+    /* GenericParamConstraint */ &[Table(TABLE_GENERIC_PARAM), Coded(&TYPE_DEF_OR_REF)],
+];
+
+/// `#Strings` heap indexes are 4 bytes wide
+pub const HEAP_SIZES_STRING: u8 = 0x01;
+/// `#GUID` heap indexes are 4 bytes wide
+pub const HEAP_SIZES_GUID: u8 = 0x02;
+/// `#Blob` heap indexes are 4 bytes wide
+pub const HEAP_SIZES_BLOB: u8 = 0x04;
+/// An extra 4 bytes follow the row counts (uncompressed `#-` streams only)
+pub const HEAP_SIZES_EXTRA_DATA: u8 = 0x40;
+
+/// The header of the `#~` (or `#-`) tables stream
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct TablesHeader {
+    pub reserved: u32,
+    pub major_version: u8,
+    pub minor_version: u8,
+    pub heap_sizes: u8,
+    pub reserved2: u8,
+    /// A bit vector of which tables are present
+    pub valid: u64,
+    /// A bit vector of which tables are sorted
+    pub sorted: u64,
+}
+
+pub const SIZEOF_TABLES_HEADER: usize = 24;
+
+#[derive(Debug, PartialEq, Clone)]
+/// The metadata tables stream, which knows the layout of every table, but decodes rows lazily
+pub struct MetadataTables<'a> {
+    pub header: TablesHeader,
+    /// The number of rows in each table, indexed by table number
+    pub rows: Vec<u32>,
+    /// The offset of each known table into `bytes`
+    offsets: Vec<usize>,
+    bytes: &'a [u8],
+}
+
+impl<'a> MetadataTables<'a> {
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let offset = &mut 0;
+        let header: TablesHeader = bytes.gread_with(offset, scroll::LE)?;
+        let mut rows = vec![0u32; NUM_TABLES];
+        for (i, row) in rows.iter_mut().enumerate() {
+            if header.valid & (1 << i) != 0 {
+                *row = bytes.gread_with(offset, scroll::LE)?;
+            }
+        }
+        if header.heap_sizes & HEAP_SIZES_EXTRA_DATA != 0 {
+            *offset += 4;
+        }
+        let mut tables = MetadataTables { header: header, rows: rows, offsets: vec![0; NUM_KNOWN_TABLES], bytes: bytes };
+        for i in 0..NUM_KNOWN_TABLES {
+            tables.offsets[i] = *offset;
+            let size = (tables.row_size(i) as u64) * (tables.rows[i] as u64);
+            if *offset as u64 + size > bytes.len() as u64 {
+                return Err(error::Error::Malformed(format!("Metadata table {:#x} with {} rows is out of bounds", i, tables.rows[i])));
+            }
+            *offset += size as usize;
+        }
+        Ok(tables)
+    }
+    /// The number of rows in `table`
+    pub fn row_count(&self, table: usize) -> u32 {
+        if table < NUM_TABLES { self.rows[table] } else { 0 }
+    }
+    fn column_size(&self, column: &Column) -> usize {
+        let heap = |flag| if self.header.heap_sizes & flag != 0 { 4 } else { 2 };
+        match *column {
+            U16 | U8U8 => 2,
+            U32 => 4,
+            String => heap(HEAP_SIZES_STRING),
+            Guid => heap(HEAP_SIZES_GUID),
+            Blob => heap(HEAP_SIZES_BLOB),
+            Table(table) => if self.row_count(table) > 0xffff { 4 } else { 2 },
+            Coded(&CodedIndex(bits, tables)) => {
+                let max = tables.iter().map(|&table| self.row_count(table)).max().unwrap_or(0);
+                if max >= (1 << (16 - bits)) { 4 } else { 2 }
+            },
+        }
+    }
+    /// The size in bytes of a row in `table`, which depends on the row counts and heap sizes
+    pub fn row_size(&self, table: usize) -> usize {
+        SCHEMA[table].iter().map(|column| self.column_size(column)).sum()
+    }
+    /// Decodes every column of the zero-based `row` in `table`; heap and table indexes are returned unresolved
+    pub fn row(&self, table: usize, row: usize) -> error::Result<Vec<u32>> {
+        if table >= NUM_KNOWN_TABLES || row >= self.rows[table] as usize {
+            return Err(error::Error::Malformed(format!("Metadata row {} in table {:#x} does not exist", row, table)));
+        }
+        let offset = &mut (self.offsets[table] + row * self.row_size(table));
+        let mut columns = Vec::with_capacity(SCHEMA[table].len());
+        for column in SCHEMA[table] {
+            let value = if self.column_size(column) == 4 {
+                self.bytes.gread_with::<u32>(offset, scroll::LE)?
+            } else {
+                self.bytes.gread_with::<u16>(offset, scroll::LE)? as u32
+            };
+            columns.push(value);
+        }
+        Ok(columns)
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A type defined in this module
+pub struct TypeDef<'a> {
+    pub flags: u32,
+    pub name: &'a str,
+    pub namespace: &'a str,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A method defined in this module
+pub struct MethodDef<'a> {
+    /// The RVA of the method body, or 0
+    pub rva: u32,
+    pub impl_flags: u16,
+    pub flags: u16,
+    pub name: &'a str,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A reference to another assembly
+pub struct AssemblyRef<'a> {
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub build_number: u16,
+    pub revision_number: u16,
+    pub flags: u32,
+    pub name: &'a str,
+    pub culture: &'a str,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// The CLI header and metadata of a managed binary
+pub struct ClrData<'a> {
+    pub cor20_header: Cor20Header,
+    pub metadata_root: MetadataRoot<'a>,
+    /// The `#~` tables stream, if present
+    pub tables: Option<MetadataTables<'a>>,
+    pub type_defs: Vec<TypeDef<'a>>,
+    pub method_defs: Vec<MethodDef<'a>>,
+    pub assembly_refs: Vec<AssemblyRef<'a>>,
+}
+
+impl<'a> ClrData<'a> {
+    pub fn parse(bytes: &'a [u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Self> {
+        let rva = dd.virtual_address as usize;
+        let offset = utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map Cor20Header rva {:#x} into offset", rva)))?;
+        let cor20_header: Cor20Header = bytes.pread_with(offset, scroll::LE)?;
+        debug!("{:#?}", cor20_header);
+        let metadata_rva = cor20_header.metadata.virtual_address as usize;
+        let metadata_offset = utils::find_offset(metadata_rva, sections).ok_or(error::Error::Malformed(format!("Cannot map CLR metadata rva {:#x} into offset", metadata_rva)))?;
+        let metadata_root = MetadataRoot::parse(bytes, metadata_offset)?;
+        debug!("{:#?}", metadata_root);
+
+        let stream_bytes = |name: &str| -> error::Result<Option<&'a [u8]>> {
+            match metadata_root.stream(name) {
+                Some(stream) => {
+                    let offset = metadata_offset + stream.offset as usize;
+                    Ok(Some(bytes.pread_with::<&'a [u8]>(offset, stream.size as usize)?))
+                },
+                None => Ok(None),
+            }
+        };
+        let tables = match stream_bytes("#~")? {
+            Some(tables) => Some(MetadataTables::parse(tables)?),
+            None => match stream_bytes("#-")? {
+                Some(tables) => Some(MetadataTables::parse(tables)?),
+                None => None,
+            }
+        };
+        let strings = stream_bytes("#Strings")?.unwrap_or(&[]);
+        let string = |index: u32| -> error::Result<&'a str> {
+            if index == 0 { return Ok("") }
+            Ok(strings.pread::<&'a str>(index as usize)?)
+        };
+
+        let mut type_defs = Vec::new();
+        let mut method_defs = Vec::new();
+        let mut assembly_refs = Vec::new();
+        if let Some(ref tables) = tables {
+            for i in 0..tables.row_count(TABLE_TYPE_DEF) as usize {
+                let row = tables.row(TABLE_TYPE_DEF, i)?;
+                type_defs.push(TypeDef { flags: row[0], name: string(row[1])?, namespace: string(row[2])? });
+            }
+            for i in 0..tables.row_count(TABLE_METHOD_DEF) as usize {
+                let row = tables.row(TABLE_METHOD_DEF, i)?;
+                method_defs.push(MethodDef { rva: row[0], impl_flags: row[1] as u16, flags: row[2] as u16, name: string(row[3])? });
+            }
+            for i in 0..tables.row_count(TABLE_ASSEMBLY_REF) as usize {
+                let row = tables.row(TABLE_ASSEMBLY_REF, i)?;
+                assembly_refs.push(AssemblyRef {
+                    major_version: row[0] as u16,
+                    minor_version: row[1] as u16,
+                    build_number: row[2] as u16,
+                    revision_number: row[3] as u16,
+                    flags: row[4],
+                    name: string(row[6])?,
+                    culture: string(row[7])?,
+                });
+            }
+        }
+
+        Ok(ClrData {
+            cor20_header: cor20_header,
+            metadata_root: metadata_root,
+            tables: tables,
+            type_defs: type_defs,
+            method_defs: method_defs,
+            assembly_refs: assembly_refs,
+        })
+    }
+    /// The runtime version string from the metadata root, e.g., `v4.0.30319`
+    pub fn runtime_version(&self) -> &'a str {
+        self.metadata_root.version
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizeof_cor20_header() {
+        assert_eq!(::std::mem::size_of::<Cor20Header>(), SIZEOF_COR20_HEADER);
+        assert_eq!(::std::mem::size_of::<TablesHeader>(), SIZEOF_TABLES_HEADER);
+    }
+
+    #[test]
+    fn parse_tables_stream() {
+        use scroll::Pwrite;
+        let mut bytes = vec![0u8; 0x100];
+        let header = TablesHeader {
+            reserved: 0,
+            major_version: 2,
+            minor_version: 0,
+            heap_sizes: 0,
+            reserved2: 1,
+            valid: (1 << TABLE_MODULE) | (1 << TABLE_TYPE_DEF) | (1 << TABLE_METHOD_DEF),
+            sorted: 0,
+        };
+        let offset = &mut 0;
+        bytes.gwrite_with(header, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(2u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1u32, offset, scroll::LE).unwrap();
+        // module row: 5 two byte columns
+        *offset += 10;
+        // two type defs: flags, name, namespace, extends, field list, method list
+        for &(flags, name) in &[(0u32, 1u16), (0x100001, 9)] {
+            bytes.gwrite_with(flags, offset, scroll::LE).unwrap();
+            bytes.gwrite_with(name, offset, scroll::LE).unwrap();
+            *offset += 8;
+        }
+        // a method def: rva, impl flags, flags, name, signature, param list
+        bytes.gwrite_with(0x2050u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0x96u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0x11u16, offset, scroll::LE).unwrap();
+        let tables = MetadataTables::parse(&bytes).unwrap();
+        assert_eq!(tables.row_count(TABLE_TYPE_DEF), 2);
+        assert_eq!(tables.row_size(TABLE_TYPE_DEF), 14);
+        assert_eq!(tables.row(TABLE_TYPE_DEF, 1).unwrap()[..2], [0x100001, 9]);
+        assert_eq!(tables.row(TABLE_METHOD_DEF, 0).unwrap()[..4], [0x2050, 0, 0x96, 0x11]);
+        assert!(tables.row(TABLE_METHOD_DEF, 1).is_err());
+    }
+}
@@ -0,0 +1,559 @@
+//! COFF object files (`.obj`), and the COFF symbol and string tables
+//!
+//! An object file is a bare COFF header, without the DOS header, PE signature or optional header, followed by the
+//! section table. Large objects (`/bigobj`) use an extended header and 32-bit section numbers instead.
+//! The symbol table is shared with images, which may also carry one (e.g., MinGW binaries with debug info).
+
+use std::cmp;
+use scroll::{self, Pread};
+use error;
+use strtab;
+
+use pe::header;
+use pe::section_table;
+
+/// The size of an `IMAGE_SYMBOL`
+pub const SIZEOF_SYMBOL: usize = 18;
+/// The size of an `IMAGE_SYMBOL_EX`, used by bigobj files
+pub const SIZEOF_SYMBOL_EX: usize = 20;
+/// The size of a plain COFF header, i.e., `CoffHeader` without the PE signature
+pub const SIZEOF_COFF_OBJECT_HEADER: usize = 20;
+
+// Special section numbers
+/// The symbol is undefined, or, with a nonzero value, common
+pub const IMAGE_SYM_UNDEFINED: i32 = 0;
+/// The symbol's value is an absolute, non-relocatable value
+pub const IMAGE_SYM_ABSOLUTE: i32 = -1;
+/// The symbol provides debugging information
+pub const IMAGE_SYM_DEBUG: i32 = -2;
+
+// Storage classes
+pub const IMAGE_SYM_CLASS_END_OF_FUNCTION: u8 = 0xff;
+pub const IMAGE_SYM_CLASS_NULL: u8 = 0;
+pub const IMAGE_SYM_CLASS_AUTOMATIC: u8 = 1;
+pub const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+pub const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+pub const IMAGE_SYM_CLASS_REGISTER: u8 = 4;
+pub const IMAGE_SYM_CLASS_EXTERNAL_DEF: u8 = 5;
+pub const IMAGE_SYM_CLASS_LABEL: u8 = 6;
+pub const IMAGE_SYM_CLASS_UNDEFINED_LABEL: u8 = 7;
+pub const IMAGE_SYM_CLASS_MEMBER_OF_STRUCT: u8 = 8;
+pub const IMAGE_SYM_CLASS_ARGUMENT: u8 = 9;
+pub const IMAGE_SYM_CLASS_STRUCT_TAG: u8 = 10;
+pub const IMAGE_SYM_CLASS_MEMBER_OF_UNION: u8 = 11;
+pub const IMAGE_SYM_CLASS_UNION_TAG: u8 = 12;
+pub const IMAGE_SYM_CLASS_TYPE_DEFINITION: u8 = 13;
+pub const IMAGE_SYM_CLASS_UNDEFINED_STATIC: u8 = 14;
+pub const IMAGE_SYM_CLASS_ENUM_TAG: u8 = 15;
+pub const IMAGE_SYM_CLASS_MEMBER_OF_ENUM: u8 = 16;
+pub const IMAGE_SYM_CLASS_REGISTER_PARAM: u8 = 17;
+pub const IMAGE_SYM_CLASS_BIT_FIELD: u8 = 18;
+pub const IMAGE_SYM_CLASS_BLOCK: u8 = 100;
+pub const IMAGE_SYM_CLASS_FUNCTION: u8 = 101;
+pub const IMAGE_SYM_CLASS_END_OF_STRUCT: u8 = 102;
+pub const IMAGE_SYM_CLASS_FILE: u8 = 103;
+pub const IMAGE_SYM_CLASS_SECTION: u8 = 104;
+pub const IMAGE_SYM_CLASS_WEAK_EXTERNAL: u8 = 105;
+pub const IMAGE_SYM_CLASS_CLR_TOKEN: u8 = 107;
+
+/// The complex type of a symbol (bits 4-5 of `typ`) denoting a function
+pub const IMAGE_SYM_DTYPE_FUNCTION: u16 = 2;
+
+// COMDAT selection kinds, in the section definition auxiliary record
+pub const IMAGE_COMDAT_SELECT_NODUPLICATES: u8 = 1;
+pub const IMAGE_COMDAT_SELECT_ANY: u8 = 2;
+pub const IMAGE_COMDAT_SELECT_SAME_SIZE: u8 = 3;
+pub const IMAGE_COMDAT_SELECT_EXACT_MATCH: u8 = 4;
+pub const IMAGE_COMDAT_SELECT_ASSOCIATIVE: u8 = 5;
+pub const IMAGE_COMDAT_SELECT_LARGEST: u8 = 6;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A COFF symbol, `IMAGE_SYMBOL` or `IMAGE_SYMBOL_EX`, along with its auxiliary records
+pub struct Symbol<'a> {
+    /// The index of this symbol in the symbol table; auxiliary records occupy indexes too
+    pub index: usize,
+    /// The short name, or, if the first 4 bytes are zero, an offset into the string table in the last 4
+    pub name: [u8; 8],
+    pub value: u32,
+    /// The one-based section index, or one of the `IMAGE_SYM_*` special values
+    pub section_number: i32,
+    pub typ: u16,
+    pub storage_class: u8,
+    pub number_of_aux_symbols: u8,
+    /// The raw auxiliary records following this symbol, each one symbol record in size
+    pub aux: &'a [u8],
+}
+
+impl<'a> Symbol<'a> {
+    /// Whether the name of this symbol is stored in the string table
+    pub fn has_long_name(&self) -> bool {
+        self.name[0..4] == [0, 0, 0, 0]
+    }
+    /// The offset of this symbol's name in the string table, if it has a long name
+    pub fn name_offset(&self) -> Option<u32> {
+        if self.has_long_name() {
+            Some(self.name.pread_with(4, scroll::LE).unwrap())
+        } else {
+            None
+        }
+    }
+    /// Returns the name of this symbol, looking it up in `strtab` if necessary
+    pub fn name<'b>(&'b self, strtab: &'b strtab::Strtab<'a>) -> error::Result<&'b str> {
+        match self.name_offset() {
+            Some(offset) => {
+                strtab.get(offset as usize).unwrap_or(Err(error::Error::Malformed(format!("Symbol {} name offset {:#x} is out of bounds", self.index, offset))))
+            },
+            None => Ok(self.name.pread::<&str>(0)?),
+        }
+    }
+    /// Whether this symbol is visible to other objects
+    pub fn is_external(&self) -> bool {
+        self.storage_class == IMAGE_SYM_CLASS_EXTERNAL
+    }
+    /// Whether this symbol is referenced, but not defined, in this object
+    pub fn is_undefined(&self) -> bool {
+        self.section_number == IMAGE_SYM_UNDEFINED && self.storage_class == IMAGE_SYM_CLASS_EXTERNAL && self.value == 0
+    }
+    /// Whether this symbol is a function
+    pub fn is_function(&self) -> bool {
+        (self.typ >> 4) & 0x3 == IMAGE_SYM_DTYPE_FUNCTION
+    }
+    /// The file name stored in the auxiliary records of an `IMAGE_SYM_CLASS_FILE` symbol
+    pub fn aux_file_name(&self) -> Option<error::Result<&'a str>> {
+        if self.storage_class != IMAGE_SYM_CLASS_FILE || self.aux.is_empty() {
+            return None
+        }
+        let name = self.aux.split(|byte| *byte == 0).next().unwrap_or(&[]);
+        Some(::std::str::from_utf8(name).map_err(|_| error::Error::Malformed(format!("Symbol {} has a file name which is not utf8", self.index))))
+    }
+    /// The section definition auxiliary record of an `IMAGE_SYM_CLASS_STATIC` section symbol
+    pub fn aux_section_definition(&self) -> Option<AuxSectionDefinition> {
+        if self.storage_class != IMAGE_SYM_CLASS_STATIC || self.number_of_aux_symbols == 0 || self.aux.len() < SIZEOF_SYMBOL {
+            return None
+        }
+        self.aux.pread_with(0, scroll::LE).ok()
+    }
+}
+
+/// The auxiliary record following a section symbol, `IMAGE_AUX_SYMBOL.Section`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct AuxSectionDefinition {
+    pub length: u32,
+    pub number_of_relocations: u16,
+    pub number_of_linenumbers: u16,
+    pub check_sum: u32,
+    /// The low 16 bits of the one-based index of the associated section, for `IMAGE_COMDAT_SELECT_ASSOCIATIVE`
+    pub number: u16,
+    /// The COMDAT selection kind, e.g. `IMAGE_COMDAT_SELECT_ANY`
+    pub selection: u8,
+    pub reserved: u8,
+    /// The high 16 bits of `number`, used by bigobj files
+    pub high_number: u16,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The COFF symbol table
+pub struct SymbolTable<'a> {
+    /// Every symbol, in order; auxiliary records are attached to the symbol they follow
+    pub symbols: Vec<Symbol<'a>>,
+    /// The total number of records, including auxiliary records
+    pub number_of_records: usize,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Parses `count` symbol records at `offset`; `bigobj` files use the extended symbol record format
+    pub fn parse(bytes: &'a [u8], offset: usize, count: usize, bigobj: bool) -> error::Result<Self> {
+        let record = if bigobj { SIZEOF_SYMBOL_EX } else { SIZEOF_SYMBOL };
+        if count > bytes.len() / record {
+            return Err(error::Error::Malformed(format!("Number of symbols ({}) is larger than the binary", count)));
+        }
+        let mut symbols = Vec::with_capacity(count);
+        let mut index = 0;
+        while index < count {
+            let offset = &mut (offset + index * record);
+            let mut name = [0u8; 8];
+            bytes.gread_inout_with(offset, &mut name, scroll::LE)?;
+            let value = bytes.gread_with(offset, scroll::LE)?;
+            let section_number = if bigobj {
+                bytes.gread_with::<i32>(offset, scroll::LE)?
+            } else {
+                bytes.gread_with::<i16>(offset, scroll::LE)? as i32
+            };
+            let typ = bytes.gread_with(offset, scroll::LE)?;
+            let storage_class = bytes.gread_with(offset, scroll::LE)?;
+            let number_of_aux_symbols: u8 = bytes.gread_with(offset, scroll::LE)?;
+            let naux = ::std::cmp::min(number_of_aux_symbols as usize, count - index - 1);
+            let aux: &'a [u8] = bytes.pread_with(*offset, naux * record)?;
+            symbols.push(Symbol {
+                index: index,
+                name: name,
+                value: value,
+                section_number: section_number,
+                typ: typ,
+                storage_class: storage_class,
+                number_of_aux_symbols: number_of_aux_symbols,
+                aux: aux,
+            });
+            index += 1 + naux;
+        }
+        Ok(SymbolTable { symbols: symbols, number_of_records: count })
+    }
+    /// Returns the symbol at record `index`, as referred to by relocations
+    pub fn get(&self, index: usize) -> Option<&Symbol<'a>> {
+        self.symbols.binary_search_by_key(&index, |symbol| symbol.index).ok().map(|i| &self.symbols[i])
+    }
+}
+
+/// Parses the string table which immediately follows the `count` symbol records at `offset`.
+///
+/// Offsets into the returned table are the offsets symbols and long section names refer to, i.e., they include the
+/// leading 4 byte size field.
+pub fn parse_string_table<'a>(bytes: &'a [u8], offset: usize, count: usize, bigobj: bool) -> error::Result<strtab::Strtab<'a>> {
+    let record = if bigobj { SIZEOF_SYMBOL_EX } else { SIZEOF_SYMBOL };
+    let offset = count.checked_mul(record).and_then(|size| size.checked_add(offset)).ok_or(error::Error::Malformed(format!("Symbol table with {} symbols overflows", count)))?;
+    // a missing string table is equivalent to an empty one
+    if offset + 4 > bytes.len() {
+        return Ok(strtab::Strtab::default())
+    }
+    let size: u32 = bytes.pread_with(offset, scroll::LE)?;
+    let size = ::std::cmp::max(size as usize, 4);
+    strtab::Strtab::parse(bytes, offset, size, 0x0)
+}
+
+/// A COFF relocation, `IMAGE_RELOCATION`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct Relocation {
+    /// The offset of the item to relocate, relative to the start of its section
+    pub virtual_address: u32,
+    /// The index of the symbol whose address is applied
+    pub symbol_table_index: u32,
+    /// The machine specific relocation type, e.g. `IMAGE_REL_AMD64_REL32`
+    pub typ: u16,
+}
+
+pub const SIZEOF_RELOCATION: usize = 10;
+
+pub use pe::section_table::IMAGE_SCN_LNK_NRELOC_OVFL;
+
+/// Parses the relocations of `section` in `bytes`
+pub fn parse_relocations(bytes: &[u8], section: &section_table::SectionTable) -> error::Result<Vec<Relocation>> {
+    let offset = &mut (section.pointer_to_relocations as usize);
+    let mut count = section.number_of_relocations as usize;
+    if count == 0xffff && section.characteristics & IMAGE_SCN_LNK_NRELOC_OVFL != 0 {
+        let first: Relocation = bytes.pread_with(*offset, scroll::LE)?;
+        // the count includes the first, placeholder, relocation
+        count = (first.virtual_address as usize).saturating_sub(1);
+        *offset += SIZEOF_RELOCATION;
+    }
+    if count > bytes.len() / SIZEOF_RELOCATION {
+        return Err(error::Error::Malformed(format!("Number of relocations ({}) is larger than the binary", count)));
+    }
+    let mut relocations = Vec::with_capacity(count);
+    for _ in 0..count {
+        relocations.push(bytes.gread_with(offset, scroll::LE)?);
+    }
+    Ok(relocations)
+}
+
+// x64 relocation types
+pub const IMAGE_REL_AMD64_ABSOLUTE: u16 = 0x0000;
+pub const IMAGE_REL_AMD64_ADDR64: u16 = 0x0001;
+pub const IMAGE_REL_AMD64_ADDR32: u16 = 0x0002;
+pub const IMAGE_REL_AMD64_ADDR32NB: u16 = 0x0003;
+pub const IMAGE_REL_AMD64_REL32: u16 = 0x0004;
+pub const IMAGE_REL_AMD64_REL32_1: u16 = 0x0005;
+pub const IMAGE_REL_AMD64_REL32_2: u16 = 0x0006;
+pub const IMAGE_REL_AMD64_REL32_3: u16 = 0x0007;
+pub const IMAGE_REL_AMD64_REL32_4: u16 = 0x0008;
+pub const IMAGE_REL_AMD64_REL32_5: u16 = 0x0009;
+pub const IMAGE_REL_AMD64_SECTION: u16 = 0x000a;
+pub const IMAGE_REL_AMD64_SECREL: u16 = 0x000b;
+pub const IMAGE_REL_AMD64_SECREL7: u16 = 0x000c;
+pub const IMAGE_REL_AMD64_TOKEN: u16 = 0x000d;
+pub const IMAGE_REL_AMD64_SREL32: u16 = 0x000e;
+pub const IMAGE_REL_AMD64_PAIR: u16 = 0x000f;
+pub const IMAGE_REL_AMD64_SSPAN32: u16 = 0x0010;
+
+// x86 relocation types
+pub const IMAGE_REL_I386_ABSOLUTE: u16 = 0x0000;
+pub const IMAGE_REL_I386_DIR16: u16 = 0x0001;
+pub const IMAGE_REL_I386_REL16: u16 = 0x0002;
+pub const IMAGE_REL_I386_DIR32: u16 = 0x0006;
+pub const IMAGE_REL_I386_DIR32NB: u16 = 0x0007;
+pub const IMAGE_REL_I386_SEG12: u16 = 0x0009;
+pub const IMAGE_REL_I386_SECTION: u16 = 0x000a;
+pub const IMAGE_REL_I386_SECREL: u16 = 0x000b;
+pub const IMAGE_REL_I386_TOKEN: u16 = 0x000c;
+pub const IMAGE_REL_I386_SECREL7: u16 = 0x000d;
+pub const IMAGE_REL_I386_REL32: u16 = 0x0014;
+
+// ARM64 relocation types
+pub const IMAGE_REL_ARM64_ABSOLUTE: u16 = 0x0000;
+pub const IMAGE_REL_ARM64_ADDR32: u16 = 0x0001;
+pub const IMAGE_REL_ARM64_ADDR32NB: u16 = 0x0002;
+pub const IMAGE_REL_ARM64_BRANCH26: u16 = 0x0003;
+pub const IMAGE_REL_ARM64_PAGEBASE_REL21: u16 = 0x0004;
+pub const IMAGE_REL_ARM64_REL21: u16 = 0x0005;
+pub const IMAGE_REL_ARM64_PAGEOFFSET_12A: u16 = 0x0006;
+pub const IMAGE_REL_ARM64_PAGEOFFSET_12L: u16 = 0x0007;
+pub const IMAGE_REL_ARM64_SECREL: u16 = 0x0008;
+pub const IMAGE_REL_ARM64_SECREL_LOW12A: u16 = 0x0009;
+pub const IMAGE_REL_ARM64_SECREL_HIGH12A: u16 = 0x000a;
+pub const IMAGE_REL_ARM64_SECREL_LOW12L: u16 = 0x000b;
+pub const IMAGE_REL_ARM64_TOKEN: u16 = 0x000c;
+pub const IMAGE_REL_ARM64_SECTION: u16 = 0x000d;
+pub const IMAGE_REL_ARM64_ADDR64: u16 = 0x000e;
+pub const IMAGE_REL_ARM64_BRANCH19: u16 = 0x000f;
+pub const IMAGE_REL_ARM64_BRANCH14: u16 = 0x0010;
+pub const IMAGE_REL_ARM64_REL32: u16 = 0x0011;
+
+/// The `ANON_OBJECT_HEADER_BIGOBJ` class id, {D1BAA1C7-BAEE-4ba9-AF20-FAF66AA4DCB8}
+pub const BIGOBJ_CLASS_ID: [u8; 16] = [0xc7, 0xa1, 0xba, 0xd1, 0xee, 0xba, 0xa9, 0x4b, 0xaf, 0x20, 0xfa, 0xf6, 0x6a, 0xa4, 0xdc, 0xb8];
+
+/// The header of a bigobj object file, `ANON_OBJECT_HEADER_BIGOBJ`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct BigObjHeader {
+    /// Always `IMAGE_FILE_MACHINE_UNKNOWN`, i.e., 0
+    pub sig1: u16,
+    /// Always 0xffff
+    pub sig2: u16,
+    /// At least 2
+    pub version: u16,
+    pub machine: u16,
+    pub time_date_stamp: u32,
+    pub class_id: [u8; 16],
+    pub size_of_data: u32,
+    pub flags: u32,
+    pub meta_data_size: u32,
+    pub meta_data_offset: u32,
+    pub number_of_sections: u32,
+    pub pointer_to_symbol_table: u32,
+    pub number_of_symbols: u32,
+}
+
+pub const SIZEOF_BIGOBJ_HEADER: usize = 56;
+
+impl BigObjHeader {
+    /// Whether `bytes` starts with a bigobj header
+    pub fn is_bigobj(bytes: &[u8]) -> bool {
+        match bytes.pread_with::<BigObjHeader>(0, scroll::LE) {
+            Ok(header) => header.sig1 == 0 && header.sig2 == 0xffff && header.version >= 2 && header.class_id == BIGOBJ_CLASS_ID,
+            Err(_) => false,
+        }
+    }
+}
+
+/// The largest section number a regular (non bigobj) object can refer to, `IMAGE_SYM_SECTION_MAX`
+pub const IMAGE_SYM_SECTION_MAX: u16 = 0xfeff;
+
+/// Whether the first 16 bytes of `bytes` look like a COFF object file.
+///
+/// There is no magic number, so besides a known machine, the section count and symbol table fields must be plausible;
+/// bigobj files are recognized by their signature, version and the start of their class id.
+pub fn peek(bytes: &[u8]) -> bool {
+    use pe::header::{COFF_MACHINE_X86, COFF_MACHINE_X86_64, COFF_MACHINE_ARM, COFF_MACHINE_ARMNT, COFF_MACHINE_ARM64, COFF_MACHINE_ARM64EC};
+    if bytes.len() < 16 {
+        return false;
+    }
+    let machine: u16 = bytes.pread_with(0, scroll::LE).unwrap();
+    let nsections: u16 = bytes.pread_with(2, scroll::LE).unwrap();
+    if machine == header::COFF_MACHINE_UNKNOWN && nsections == 0xffff {
+        let version: u16 = bytes.pread_with(4, scroll::LE).unwrap();
+        return version >= 2 && bytes[12..16] == BIGOBJ_CLASS_ID[0..4];
+    }
+    match machine {
+        COFF_MACHINE_X86 | COFF_MACHINE_X86_64 | COFF_MACHINE_ARM | COFF_MACHINE_ARMNT | COFF_MACHINE_ARM64 | COFF_MACHINE_ARM64EC => (),
+        _ => return false,
+    }
+    let symtab: u32 = bytes.pread_with(8, scroll::LE).unwrap();
+    let nsymbols: u32 = bytes.pread_with(12, scroll::LE).unwrap();
+    let end_of_sections = SIZEOF_COFF_OBJECT_HEADER + nsections as usize * section_table::SIZEOF_SECTION_TABLE;
+    nsections <= IMAGE_SYM_SECTION_MAX
+        && (nsections != 0 || nsymbols != 0)
+        && if symtab == 0 { nsymbols == 0 } else { symtab as usize >= end_of_sections }
+}
+
+#[derive(Debug)]
+/// A parsed COFF object file
+pub struct Coff<'a> {
+    /// The COFF header; for bigobj files, this is synthesized from `bigobj_header`, with `number_of_sections` saturated
+    /// to `u16::MAX`. `signature` is always 0
+    pub header: header::CoffHeader,
+    /// The extended header, if this is a bigobj file
+    pub bigobj_header: Option<BigObjHeader>,
+    pub sections: Vec<section_table::SectionTable>,
+    pub symbols: SymbolTable<'a>,
+    /// The string table holding long symbol and section names
+    pub strings: strtab::Strtab<'a>,
+    /// The relocations of each section, in section order
+    pub relocations: Vec<Vec<Relocation>>,
+}
+
+impl<'a> Coff<'a> {
+    /// Reads a COFF object file, either regular or bigobj, from the underlying `bytes`
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let offset = &mut 0;
+        let (header, bigobj_header, nsections, nsymbols) = if BigObjHeader::is_bigobj(bytes) {
+            let bigobj: BigObjHeader = bytes.gread_with(offset, scroll::LE)?;
+            let header = header::CoffHeader {
+                signature: 0,
+                machine: bigobj.machine,
+                // saturated, as the count does not fit; use `bigobj_header` or `sections` for the real one
+                number_of_sections: cmp::min(bigobj.number_of_sections, u16::max_value() as u32) as u16,
+                time_date_stamp: bigobj.time_date_stamp,
+                pointer_to_symbol_table: bigobj.pointer_to_symbol_table,
+                number_of_symbol_table: bigobj.number_of_symbols,
+                size_of_optional_header: 0,
+                characteristics: 0,
+            };
+            (header, Some(bigobj), bigobj.number_of_sections as usize, bigobj.number_of_symbols as usize)
+        } else {
+            let header = header::CoffHeader::parse_without_signature(bytes, offset)?;
+            *offset += header.size_of_optional_header as usize;
+            (header, None, header.number_of_sections as usize, header.number_of_symbol_table as usize)
+        };
+        debug!("{:#?}", header);
+        let bigobj = bigobj_header.is_some();
+        if nsections > bytes.len() / section_table::SIZEOF_SECTION_TABLE {
+            return Err(error::Error::Malformed(format!("Number of sections ({}) is larger than the binary", nsections)));
+        }
+        let mut sections = Vec::with_capacity(nsections);
+        for _ in 0..nsections {
+            sections.push(section_table::SectionTable::parse(bytes, offset)?);
+        }
+        let (symbols, strings) = if header.pointer_to_symbol_table != 0 {
+            let symtab = header.pointer_to_symbol_table as usize;
+            (SymbolTable::parse(bytes, symtab, nsymbols, bigobj)?, parse_string_table(bytes, symtab, nsymbols, bigobj)?)
+        } else {
+            (SymbolTable::default(), strtab::Strtab::default())
+        };
+        let mut relocations = Vec::with_capacity(nsections);
+        for section in &sections {
+            relocations.push(parse_relocations(bytes, section)?);
+        }
+        Ok(Coff {
+            header: header,
+            bigobj_header: bigobj_header,
+            sections: sections,
+            symbols: symbols,
+            strings: strings,
+            relocations: relocations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::Pwrite;
+
+    #[test]
+    fn sizeof_bigobj_header() {
+        assert_eq!(::std::mem::size_of::<BigObjHeader>(), SIZEOF_BIGOBJ_HEADER);
+    }
+
+    #[test]
+    fn parse_object() {
+        let mut bytes = vec![0u8; 0x200];
+        let offset = &mut 0;
+        // header
+        bytes.gwrite_with(header::COFF_MACHINE_X86_64, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0x100u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(3u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u16, offset, scroll::LE).unwrap();
+        // .text with one relocation
+        bytes.gwrite_with(&b".text\0\0\0"[..], offset, ()).unwrap();
+        *offset += 16;
+        bytes.gwrite_with(0xe0u32, offset, scroll::LE).unwrap();
+        *offset += 4;
+        bytes.gwrite_with(1u16, offset, scroll::LE).unwrap();
+        *offset += 6;
+        let relocation = Relocation { virtual_address: 4, symbol_table_index: 2, typ: IMAGE_REL_AMD64_REL32 };
+        bytes.pwrite_with(relocation, 0xe0, scroll::LE).unwrap();
+        // symbols: .text with one aux record, then a long named external
+        let offset = &mut 0x100;
+        bytes.gwrite_with(&b".text\0\0\0"[..], offset, ()).unwrap();
+        bytes.gwrite_with(0u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1i16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(IMAGE_SYM_CLASS_STATIC, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1u8, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0x20u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(1u16, offset, scroll::LE).unwrap();
+        *offset += 12;
+        bytes.gwrite_with(0u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(4u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0i16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0x20u16, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(IMAGE_SYM_CLASS_EXTERNAL, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(0u8, offset, scroll::LE).unwrap();
+        // string table
+        let name = b"a_rather_long_function_name\0";
+        bytes.gwrite_with(4 + name.len() as u32, offset, scroll::LE).unwrap();
+        bytes.gwrite_with(&name[..], offset, ()).unwrap();
+
+        let coff = Coff::parse(&bytes).unwrap();
+        assert_eq!(coff.header.machine, header::COFF_MACHINE_X86_64);
+        assert_eq!(coff.sections.len(), 1);
+        assert_eq!(coff.relocations[0], vec![relocation]);
+        assert_eq!(coff.symbols.symbols.len(), 2);
+        let text = &coff.symbols.symbols[0];
+        assert_eq!(text.name(&coff.strings).unwrap(), ".text");
+        assert_eq!(text.aux_section_definition().unwrap().length, 0x20);
+        let function = coff.symbols.get(coff.relocations[0][0].symbol_table_index as usize).unwrap();
+        assert_eq!(function.name(&coff.strings).unwrap(), "a_rather_long_function_name");
+        assert!(function.is_undefined());
+        assert!(function.is_function());
+        assert!(peek(&bytes));
+    }
+
+    #[test]
+    fn peek_object() {
+        let mut bytes = [0u8; 16];
+        bytes.pwrite_with(header::COFF_MACHINE_ARM64EC, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(2u16, 2, scroll::LE).unwrap();
+        bytes.pwrite_with(0x200u32, 8, scroll::LE).unwrap();
+        bytes.pwrite_with(10u32, 12, scroll::LE).unwrap();
+        assert!(peek(&bytes));
+        // the symbol table would overlap the section table
+        bytes.pwrite_with(0x40u32, 8, scroll::LE).unwrap();
+        assert!(!peek(&bytes));
+        // symbols without a symbol table
+        bytes.pwrite_with(0u32, 8, scroll::LE).unwrap();
+        assert!(!peek(&bytes));
+        // text which happens to start with an x86 machine, "L\x01"
+        assert!(!peek(b"L\x01ttle endian?"));
+
+        let bigobj = BigObjHeader { sig1: 0, sig2: 0xffff, version: 2, machine: header::COFF_MACHINE_X86_64, class_id: BIGOBJ_CLASS_ID, ..Default::default() };
+        let mut bytes = [0u8; SIZEOF_BIGOBJ_HEADER];
+        bytes.pwrite_with(bigobj, 0, scroll::LE).unwrap();
+        assert!(peek(&bytes));
+        // an import object shares the signature, but has version 0
+        bytes.pwrite_with(0u16, 4, scroll::LE).unwrap();
+        assert!(!peek(&bytes));
+    }
+
+    #[test]
+    #[cfg(all(feature = "elf64", feature = "elf32", feature = "mach64", feature = "mach32", feature = "archive"))]
+    fn truncated_object_is_unknown() {
+        // this passes the header checks, but is too short for the header
+        let mut bytes = [0u8; 16];
+        bytes.pwrite_with(header::COFF_MACHINE_X86_64, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(1u16, 2, scroll::LE).unwrap();
+        assert!(peek(&bytes));
+        match ::Object::parse(&bytes).unwrap() {
+            ::Object::Unknown(magic) => assert_eq!(magic, header::COFF_MACHINE_X86_64 as u64 | 1 << 16),
+            object => panic!("unexpected object {:?}", object),
+        }
+    }
+}
@@ -4,29 +4,81 @@ use error;
 use pe::section_table;
 use pe::utils;
 use pe::data_directories;
+use pe::options;
 
-#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct DebugData<'a> {
+    /// The first debug directory entry
     pub image_debug_directory: ImageDebugDirectory,
+    /// The first `RSDS` CodeView record, if any
     pub codeview_pdb70_debug_info: Option<CodeviewPDB70DebugInfo<'a>>,
+    /// The first `NB10` CodeView record, if any
+    pub codeview_pdb20_debug_info: Option<CodeviewPDB20DebugInfo<'a>>,
+    /// Every debug directory entry, along with its decoded data
+    pub entries: Vec<DebugEntry<'a>>,
 }
 
 impl<'a> DebugData<'a> {
     pub fn parse(bytes: &'a [u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Self> {
-        let image_debug_directory = ImageDebugDirectory::parse(bytes, dd, sections)?;
-        let codeview_pdb70_debug_info = CodeviewPDB70DebugInfo::parse(bytes, &image_debug_directory)?;
+        Self::parse_with_opts(bytes, dd, sections, &options::ParseOptions::default())
+    }
+
+    pub fn parse_with_opts(bytes: &'a [u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable], opts: &options::ParseOptions) -> error::Result<Self> {
+        let rva = dd.virtual_address as usize;
+        let offset = &mut utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map ImageDebugDirectory rva {:#x} into offset", rva)))?;
+        // a size smaller than one entry is common in the wild, and the loader still reads the first entry
+        let count = ::std::cmp::max(dd.size as usize / SIZEOF_IMAGE_DEBUG_DIRECTORY, 1);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let idd: ImageDebugDirectory = match bytes.gread_with(offset, scroll::LE) {
+                Ok(idd) => idd,
+                // a directory running past the end of the file is cut short, as long as it has an entry
+                Err(_) if !entries.is_empty() => break,
+                Err(err) => return Err(err.into()),
+            };
+            debug!("{:#?}", idd);
+            // in a mapped image the debug data is found at its RVA instead
+            let data_offset = if opts.resolve_rva { idd.pointer_to_raw_data } else { idd.address_of_raw_data };
+            entries.push(DebugEntry::parse_at(bytes, idd, data_offset)?);
+        }
+        let codeview_pdb70_debug_info = entries.iter().filter_map(|entry| match entry.info {
+            DebugInfo::CodeviewPDB70(info) => Some(info),
+            _ => None,
+        }).next();
+        let codeview_pdb20_debug_info = entries.iter().filter_map(|entry| match entry.info {
+            DebugInfo::CodeviewPDB20(info) => Some(info),
+            _ => None,
+        }).next();
 
         Ok(DebugData{
-            image_debug_directory: image_debug_directory,
-            codeview_pdb70_debug_info: codeview_pdb70_debug_info
+            image_debug_directory: entries[0].directory,
+            codeview_pdb70_debug_info: codeview_pdb70_debug_info,
+            codeview_pdb20_debug_info: codeview_pdb20_debug_info,
+            entries: entries,
         })
     }
-    
+
     /// Return this executable's debugging GUID, suitable for matching against a PDB file.
     pub fn guid(&self) -> Option<[u8; 16]> {
         self.codeview_pdb70_debug_info
             .map(|pdb70| pdb70.signature)
     }
+
+    /// The `IMAGE_DLLCHARACTERISTICS_EX_*` flags, if the binary has an extended DLL characteristics entry
+    pub fn ex_dll_characteristics(&self) -> Option<u32> {
+        self.entries.iter().filter_map(|entry| match entry.info {
+            DebugInfo::ExDllCharacteristics(flags) => Some(flags),
+            _ => None,
+        }).next()
+    }
+
+    /// The hash of a deterministic (`/Brepro`) build, if the binary has a repro entry with one
+    pub fn repro_hash(&self) -> Option<&'a [u8]> {
+        self.entries.iter().filter_map(|entry| match entry.info {
+            DebugInfo::Repro(hash) => hash,
+            _ => None,
+        }).next()
+    }
 }
 
 // https://msdn.microsoft.com/en-us/library/windows/desktop/ms680307(v=vs.85).aspx
@@ -44,6 +96,8 @@ pub struct ImageDebugDirectory {
     pub pointer_to_raw_data: u32,
 }
 
+pub const SIZEOF_IMAGE_DEBUG_DIRECTORY: usize = 28;
+
 pub const IMAGE_DEBUG_TYPE_UNKNOWN: u32 = 0;
 pub const IMAGE_DEBUG_TYPE_COFF: u32 = 1;
 pub const IMAGE_DEBUG_TYPE_CODEVIEW: u32 = 2;
@@ -51,17 +105,129 @@ pub const IMAGE_DEBUG_TYPE_FPO: u32 = 3;
 pub const IMAGE_DEBUG_TYPE_MISC: u32 = 4;
 pub const IMAGE_DEBUG_TYPE_EXCEPTION: u32 = 5;
 pub const IMAGE_DEBUG_TYPE_FIXUP: u32 = 6;
+pub const IMAGE_DEBUG_TYPE_OMAP_TO_SRC: u32 = 7;
+pub const IMAGE_DEBUG_TYPE_OMAP_FROM_SRC: u32 = 8;
 pub const IMAGE_DEBUG_TYPE_BORLAND: u32 = 9;
+pub const IMAGE_DEBUG_TYPE_RESERVED10: u32 = 10;
+pub const IMAGE_DEBUG_TYPE_CLSID: u32 = 11;
+/// Counts of the compiler and linker security features (/GS, /sdl, ...) objects were built with
+pub const IMAGE_DEBUG_TYPE_VC_FEATURE: u32 = 12;
+/// Profile guided optimization (POGO) section contributions
+pub const IMAGE_DEBUG_TYPE_POGO: u32 = 13;
+pub const IMAGE_DEBUG_TYPE_ILTCG: u32 = 14;
+pub const IMAGE_DEBUG_TYPE_MPX: u32 = 15;
+/// The binary is a deterministic (`/Brepro`) build; the timestamps in it are hashes rather than times
+pub const IMAGE_DEBUG_TYPE_REPRO: u32 = 16;
+/// A deflate compressed portable PDB embedded in the binary
+pub const IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB: u32 = 17;
+pub const IMAGE_DEBUG_TYPE_SPGO: u32 = 18;
+/// A cryptographic hash of the PDB file matching this binary
+pub const IMAGE_DEBUG_TYPE_PDBCHECKSUM: u32 = 19;
+/// Extended DLL characteristics, e.g. CET shadow stack compatibility
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
+
+/// The image is compatible with CET shadow stacks
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT: u32 = 0x01;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE: u32 = 0x02;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE: u32 = 0x04;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC: u32 = 0x08;
+pub const IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT: u32 = 0x40;
+pub const IMAGE_DLLCHARACTERISTICS_EX_HOTPATCH_COMPATIBLE: u32 = 0x80;
 
 impl ImageDebugDirectory {
-    fn parse(bytes: &[u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Self> {
-        let rva = dd.virtual_address as usize;
-        let offset = utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map ImageDebugDirectory rva {:#x} into offset", rva)))?;;
-        let idd: Self = bytes.pread_with(offset, scroll::LE)?;
-        Ok (idd)
+    /// Returns the data this entry describes, if it has any in the file
+    pub fn data<'a>(&self, bytes: &'a [u8]) -> error::Result<Option<&'a [u8]>> {
+        if self.pointer_to_raw_data == 0 || self.size_of_data == 0 {
+            return Ok(None)
+        }
+        Ok(Some(bytes.pread_with::<&[u8]>(self.pointer_to_raw_data as usize, self.size_of_data as usize)?))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A debug directory entry and its decoded data
+pub struct DebugEntry<'a> {
+    pub directory: ImageDebugDirectory,
+    pub info: DebugInfo<'a>,
+}
+
+impl<'a> DebugEntry<'a> {
+    /// Decodes the data of `idd`; data which is out of bounds or cannot be decoded is returned as `DebugInfo::Other`
+    pub fn parse(bytes: &'a [u8], idd: ImageDebugDirectory) -> error::Result<Self> {
+        Self::parse_at(bytes, idd, idd.pointer_to_raw_data)
+    }
+
+    /// Decodes the data of `idd` found at `data_offset` in `bytes` rather than at its `pointer_to_raw_data`; the directory is kept as is
+    pub fn parse_at(bytes: &'a [u8], idd: ImageDebugDirectory, data_offset: u32) -> error::Result<Self> {
+        let located = ImageDebugDirectory { pointer_to_raw_data: data_offset, ..idd };
+        let data = located.data(bytes).unwrap_or(None);
+        let info = match Self::parse_info(bytes, &located, data) {
+            Ok(info) => info,
+            Err(_) => DebugInfo::Other(data.unwrap_or(&[])),
+        };
+        Ok(DebugEntry { directory: idd, info: info })
+    }
+
+    fn parse_info(bytes: &'a [u8], idd: &ImageDebugDirectory, data: Option<&'a [u8]>) -> error::Result<DebugInfo<'a>> {
+        let info = match (idd.data_type, data) {
+            (IMAGE_DEBUG_TYPE_CODEVIEW, Some(data)) => {
+                match data.pread_with::<u32>(0, scroll::LE)? {
+                    CODEVIEW_PDB70_MAGIC => {
+                        match CodeviewPDB70DebugInfo::parse(bytes, idd)? {
+                            Some(info) => DebugInfo::CodeviewPDB70(info),
+                            None => DebugInfo::Other(data),
+                        }
+                    },
+                    CODEVIEW_PDB20_MAGIC => DebugInfo::CodeviewPDB20(CodeviewPDB20DebugInfo::parse(data)?),
+                    _ => DebugInfo::Other(data),
+                }
+            },
+            (IMAGE_DEBUG_TYPE_POGO, Some(data)) => DebugInfo::Pogo(PogoDebugInfo::parse(data)?),
+            (IMAGE_DEBUG_TYPE_VC_FEATURE, Some(data)) => DebugInfo::VcFeature(data.pread_with(0, scroll::LE)?),
+            (IMAGE_DEBUG_TYPE_REPRO, data) => {
+                // the hash is a length prefixed byte string; older linkers emit an empty entry
+                let hash = match data {
+                    Some(data) => {
+                        let size: u32 = data.pread_with(0, scroll::LE)?;
+                        Some(data.pread_with::<&[u8]>(4, size as usize)?)
+                    },
+                    None => None,
+                };
+                DebugInfo::Repro(hash)
+            },
+            (IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, Some(data)) => DebugInfo::ExDllCharacteristics(data.pread_with(0, scroll::LE)?),
+            (IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB, Some(data)) => DebugInfo::EmbeddedPortablePdb(EmbeddedPortablePdb::parse(data)?),
+            (IMAGE_DEBUG_TYPE_PDBCHECKSUM, Some(data)) => {
+                let algorithm = data.pread::<&str>(0)?;
+                // the checksum follows the NUL terminated name; there is none if the name is unterminated
+                let checksum = data.get(algorithm.len() + 1..).unwrap_or(&[]);
+                DebugInfo::PdbChecksum(PdbChecksum { algorithm: algorithm, checksum: checksum })
+            },
+            (_, data) => DebugInfo::Other(data.unwrap_or(&[])),
+        };
+        Ok(info)
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+/// The decoded data of a debug directory entry
+pub enum DebugInfo<'a> {
+    /// A CodeView PDB 7.0 (`RSDS`) record
+    CodeviewPDB70(CodeviewPDB70DebugInfo<'a>),
+    /// A CodeView PDB 2.0 (`NB10`) record
+    CodeviewPDB20(CodeviewPDB20DebugInfo<'a>),
+    Pogo(PogoDebugInfo<'a>),
+    VcFeature(VcFeatureInfo),
+    /// The hash of a deterministic build, if the linker recorded one
+    Repro(Option<&'a [u8]>),
+    /// The `IMAGE_DLLCHARACTERISTICS_EX_*` flags
+    ExDllCharacteristics(u32),
+    EmbeddedPortablePdb(EmbeddedPortablePdb<'a>),
+    PdbChecksum(PdbChecksum<'a>),
+    /// Any other entry, with its raw data (empty if it has none in the file)
+    Other(&'a [u8]),
+}
+
 pub const CODEVIEW_PDB70_MAGIC: u32 = 0x53445352;
 pub const CODEVIEW_PDB20_MAGIC: u32 = 0x3031424e;
 pub const CODEVIEW_CV50_MAGIC: u32 = 0x3131424e;
@@ -88,6 +254,12 @@ impl<'a> CodeviewPDB70DebugInfo<'a> {
         // ImageDebugDirectory.pointer_to_raw_data stores a raw offset -- not a virtual offset -- which we can use directly
         let mut offset: usize = idd.pointer_to_raw_data as usize;
 
+        // check the codeview signature
+        let codeview_signature: u32 = bytes.gread_with(&mut offset, scroll::LE)?;
+        if codeview_signature != CODEVIEW_PDB70_MAGIC {
+            return Ok(None);
+        }
+
         // calculate how long the eventual filename will be, which doubles as a check of the record size
         let filename_length = idd.size_of_data as isize - 24;
         if filename_length < 0 || filename_length > 1024 {
@@ -96,19 +268,13 @@ impl<'a> CodeviewPDB70DebugInfo<'a> {
         }
         let filename_length = filename_length as usize;
 
-        // check the codeview signature
-        let codeview_signature: u32 = bytes.gread_with(&mut offset, scroll::LE)?;
-        if codeview_signature != CODEVIEW_PDB70_MAGIC {
-            return Ok(None);
-        }
-
         // read the rest
         let mut signature: [u8; 16] = [0; 16];
         for i in 0..16 {
             signature[i] = bytes.gread_with(&mut offset, scroll::LE)?;
         }
         let age: u32 = bytes.gread_with(&mut offset, scroll::LE)?;
-        let filename = &bytes[offset..offset + filename_length];
+        let filename = bytes.pread_with::<&[u8]>(offset, filename_length)?;
 
         Ok(Some(CodeviewPDB70DebugInfo{
             codeview_signature: codeview_signature,
@@ -118,3 +284,227 @@ impl<'a> CodeviewPDB70DebugInfo<'a> {
         }))
     }
 }
+
+/// A CodeView PDB 2.0 record, which identifies its PDB by a timestamp rather than a GUID
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct CodeviewPDB20DebugInfo<'a> {
+    pub codeview_signature: u32,
+    /// Always 0, as the debug information is in a separate PDB
+    pub codeview_offset: u32,
+    /// The PDB's signature, a timestamp
+    pub signature: u32,
+    pub age: u32,
+    /// The path of the PDB, without the NUL terminator
+    pub filename: &'a [u8],
+}
+
+impl<'a> CodeviewPDB20DebugInfo<'a> {
+    pub fn parse(data: &'a [u8]) -> error::Result<Self> {
+        let offset = &mut 0;
+        let codeview_signature = data.gread_with(offset, scroll::LE)?;
+        let codeview_offset = data.gread_with(offset, scroll::LE)?;
+        let signature = data.gread_with(offset, scroll::LE)?;
+        let age = data.gread_with(offset, scroll::LE)?;
+        let filename = &data[*offset..];
+        let filename = match filename.iter().position(|&c| c == 0) {
+            Some(end) => &filename[..end],
+            None => filename,
+        };
+        Ok(CodeviewPDB20DebugInfo {
+            codeview_signature: codeview_signature,
+            codeview_offset: codeview_offset,
+            signature: signature,
+            age: age,
+            filename: filename,
+        })
+    }
+}
+
+/// `PGU\0`, little endian: the binary was built with profile guided optimization
+pub const POGO_SIGNATURE_PGU: u32 = 0x5047_5500;
+/// `PGI\0`, little endian: the binary was instrumented for profile guided optimization
+pub const POGO_SIGNATURE_PGI: u32 = 0x5047_4900;
+/// `LTCG`, little endian: the binary was built with link time code generation
+pub const POGO_SIGNATURE_LTCG: u32 = 0x4c54_4347;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A section contribution recorded in the POGO entry
+pub struct PogoEntry<'a> {
+    pub rva: u32,
+    pub size: u32,
+    /// The name of the (grouped) section, e.g. `.text$mn`
+    pub name: &'a str,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The POGO debug entry, which lists the grouped sections making up the image
+pub struct PogoDebugInfo<'a> {
+    pub signature: u32,
+    pub entries: Vec<PogoEntry<'a>>,
+}
+
+impl<'a> PogoDebugInfo<'a> {
+    pub fn parse(data: &'a [u8]) -> error::Result<Self> {
+        let offset = &mut 0;
+        let signature = data.gread_with(offset, scroll::LE)?;
+        let mut entries = Vec::new();
+        while *offset + 8 < data.len() {
+            let rva = data.gread_with(offset, scroll::LE)?;
+            let size = data.gread_with(offset, scroll::LE)?;
+            let name = data.pread::<&str>(*offset)?;
+            // names are NUL terminated and padded to 4 bytes
+            *offset = (*offset + name.len() + 1 + 3) & !3;
+            entries.push(PogoEntry { rva: rva, size: size, name: name });
+        }
+        Ok(PogoDebugInfo { signature: signature, entries: entries })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+/// The number of objects built with each of the compiler's security features
+pub struct VcFeatureInfo {
+    /// Objects built by compilers older than Visual C++ 11
+    pub pre_vc_plus_plus_11_count: u32,
+    pub c_and_cpp_count: u32,
+    /// Objects built with `/GS`
+    pub gs_count: u32,
+    /// Objects built with `/sdl`
+    pub sdl_count: u32,
+    /// Functions with `/guardN`
+    pub guard_n_count: u32,
+}
+
+/// `MPDB`, little endian
+pub const EMBEDDED_PORTABLE_PDB_SIGNATURE: u32 = 0x4244_504d;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A portable PDB embedded in a (managed) binary
+pub struct EmbeddedPortablePdb<'a> {
+    pub uncompressed_size: u32,
+    /// The PDB, compressed with deflate (RFC 1951)
+    pub compressed_data: &'a [u8],
+}
+
+impl<'a> EmbeddedPortablePdb<'a> {
+    pub fn parse(data: &'a [u8]) -> error::Result<Self> {
+        let signature: u32 = data.pread_with(0, scroll::LE)?;
+        if signature != EMBEDDED_PORTABLE_PDB_SIGNATURE {
+            return Err(error::Error::Malformed(format!("Embedded portable PDB has bad signature {:#x}", signature)));
+        }
+        Ok(EmbeddedPortablePdb {
+            uncompressed_size: data.pread_with(4, scroll::LE)?,
+            compressed_data: &data[8..],
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A hash of the PDB matching this binary
+pub struct PdbChecksum<'a> {
+    /// The hash algorithm, e.g. `SHA256`
+    pub algorithm: &'a str,
+    pub checksum: &'a [u8],
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_debug_entries() {
+        let mut bytes = vec![0u8; 0x100];
+        let mut entries = Vec::new();
+        {
+            use scroll::Pwrite;
+            bytes.pwrite_with(CODEVIEW_PDB20_MAGIC, 0x80, scroll::LE).unwrap();
+            bytes.pwrite_with(0x3a2b1c0du32, 0x88, scroll::LE).unwrap();
+            bytes.pwrite_with(2u32, 0x8c, scroll::LE).unwrap();
+            bytes[0x90..0x97].copy_from_slice(b"old.pdb");
+            bytes.pwrite_with(POGO_SIGNATURE_LTCG, 0xa0, scroll::LE).unwrap();
+            bytes.pwrite_with(0x1000u32, 0xa4, scroll::LE).unwrap();
+            bytes.pwrite_with(0x20u32, 0xa8, scroll::LE).unwrap();
+            bytes[0xac..0xb5].copy_from_slice(b".text$mn\0");
+            bytes.pwrite_with(0x2000u32, 0xb8, scroll::LE).unwrap();
+            bytes.pwrite_with(0x10u32, 0xbc, scroll::LE).unwrap();
+            bytes[0xc0..0xc6].copy_from_slice(b".data\0");
+            bytes.pwrite_with(4u32, 0xc8, scroll::LE).unwrap();
+            bytes[0xcc..0xd0].copy_from_slice(b"\xde\xad\xbe\xef");
+            bytes.pwrite_with(IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT, 0xd0, scroll::LE).unwrap();
+            for &(data_type, size, offset) in &[(IMAGE_DEBUG_TYPE_CODEVIEW, 0x18, 0x80), (IMAGE_DEBUG_TYPE_POGO, 0x28, 0xa0), (IMAGE_DEBUG_TYPE_REPRO, 8, 0xc8), (IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, 4, 0xd0), (IMAGE_DEBUG_TYPE_REPRO, 0, 0)] {
+                let mut idd = ImageDebugDirectory::default();
+                idd.data_type = data_type;
+                idd.size_of_data = size;
+                idd.pointer_to_raw_data = offset;
+                entries.push(idd);
+            }
+        }
+        let entries = entries.into_iter().map(|idd| DebugEntry::parse(&bytes, idd).unwrap()).collect::<Vec<_>>();
+        match entries[0].info {
+            DebugInfo::CodeviewPDB20(ref info) => {
+                assert_eq!(info.signature, 0x3a2b1c0d);
+                assert_eq!(info.age, 2);
+                assert_eq!(info.filename, b"old.pdb");
+            },
+            ref info => panic!("{:?}", info),
+        }
+        match entries[1].info {
+            DebugInfo::Pogo(ref pogo) => {
+                assert_eq!(pogo.signature, POGO_SIGNATURE_LTCG);
+                assert_eq!(pogo.entries.iter().map(|entry| entry.name).collect::<Vec<_>>(), vec![".text$mn", ".data"]);
+                assert_eq!(pogo.entries[1].rva, 0x2000);
+            },
+            ref info => panic!("{:?}", info),
+        }
+        assert_eq!(entries[2].info, DebugInfo::Repro(Some(b"\xde\xad\xbe\xef")));
+        assert_eq!(entries[3].info, DebugInfo::ExDllCharacteristics(IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT));
+        assert_eq!(entries[4].info, DebugInfo::Repro(None));
+    }
+
+    #[test]
+    fn parse_malformed_debug_entries() {
+        let bytes = b"\0SHA256\0\x01\x02MD5\x0c";
+        let mut idd = ImageDebugDirectory::default();
+        idd.data_type = IMAGE_DEBUG_TYPE_PDBCHECKSUM;
+        idd.size_of_data = 9;
+        idd.pointer_to_raw_data = 1;
+        assert_eq!(DebugEntry::parse(bytes, idd).unwrap().info, DebugInfo::PdbChecksum(PdbChecksum { algorithm: "SHA256", checksum: b"\x01\x02" }));
+        // an unterminated algorithm name has no checksum
+        idd.size_of_data = 3;
+        idd.pointer_to_raw_data = 10;
+        assert_eq!(DebugEntry::parse(bytes, idd).unwrap().info, DebugInfo::PdbChecksum(PdbChecksum { algorithm: "MD5", checksum: b"" }));
+        // too short for its flags
+        idd.data_type = IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS;
+        idd.size_of_data = 1;
+        idd.pointer_to_raw_data = 13;
+        let entry = DebugEntry::parse(bytes, idd).unwrap();
+        assert_eq!(entry.directory, idd);
+        assert_eq!(entry.info, DebugInfo::Other(b"\x0c"));
+        // past the end of the file
+        idd.size_of_data = 4;
+        assert_eq!(DebugEntry::parse(bytes, idd).unwrap().info, DebugInfo::Other(b""));
+    }
+
+    #[test]
+    fn parse_mapped_debug_directory() {
+        use scroll::Pwrite;
+        let mut bytes = vec![0u8; 0x100];
+        let mut idd = ImageDebugDirectory::default();
+        idd.data_type = IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS;
+        idd.size_of_data = 4;
+        idd.address_of_raw_data = 0x80;
+        idd.pointer_to_raw_data = 0x40;
+        bytes.pwrite_with(idd, 0x20, scroll::LE).unwrap();
+        bytes.pwrite_with(IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT, 0x80, scroll::LE).unwrap();
+        // a mapped image is laid out by RVA
+        let mut section = section_table::SectionTable::default();
+        section.virtual_size = 0x100;
+        section.size_of_raw_data = 0x100;
+        let dd = data_directories::DataDirectory { virtual_address: 0x20, size: SIZEOF_IMAGE_DEBUG_DIRECTORY as u32 };
+        let debug_data = DebugData::parse_with_opts(&bytes, &dd, &[section], &options::ParseOptions::mapped()).unwrap();
+        assert_eq!(debug_data.image_debug_directory, idd);
+        assert_eq!(debug_data.entries[0].info, DebugInfo::ExDllCharacteristics(IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT));
+    }
+}
@@ -0,0 +1,43 @@
+use scroll::{self, Pread};
+use error;
+
+use pe::section_table;
+use pe::utils;
+use pe::data_directories;
+
+/// An x64 function table entry in the exception table (`.pdata`), `RUNTIME_FUNCTION`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct RuntimeFunction {
+    pub begin_address: u32,
+    pub end_address: u32,
+    pub unwind_info_address: u32,
+}
+
+pub const SIZEOF_RUNTIME_FUNCTION: usize = 12;
+
+/// Parses the x64 function table described by the exception table data directory; entries are sorted by `begin_address`
+pub fn parse(bytes: &[u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Vec<RuntimeFunction>> {
+    let rva = dd.virtual_address as usize;
+    let offset = &mut utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map exception table rva {:#x} into offset", rva)))?;
+    let count = dd.size as usize / SIZEOF_RUNTIME_FUNCTION;
+    if count > bytes.len() / SIZEOF_RUNTIME_FUNCTION {
+        return Err(error::Error::Malformed(format!("Exception table size ({:#x}) is larger than the binary", dd.size)));
+    }
+    let mut functions = Vec::with_capacity(count);
+    for _ in 0..count {
+        functions.push(bytes.gread_with(offset, scroll::LE)?);
+    }
+    Ok(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizeof_runtime_function() {
+        assert_eq!(::std::mem::size_of::<RuntimeFunction>(), SIZEOF_RUNTIME_FUNCTION);
+    }
+}
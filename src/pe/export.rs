@@ -5,6 +5,7 @@ use error;
 use pe::utils;
 use pe::section_table;
 use pe::data_directories;
+use pe::exception;
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone, Default)]
@@ -172,80 +173,168 @@ impl<'a> Reexport<'a> {
 #[derive(Debug, Default)]
 /// An exported symbol in this binary, contains synthetic data (name offset, etc., are computed)
 pub struct Export<'a> {
-    pub name: &'a str,
+    /// The name of this export, or `None` if it is only exported by ordinal
+    pub name: Option<&'a str>,
+    /// The biased ordinal, i.e., the index into the export address table plus the ordinal base, which `ORDINAL n` imports refer to
+    pub ordinal: u32,
     pub offset: usize,
     pub rva: usize,
+    /// The size of the exported code or data; computed from the function table if present, otherwise from the next export
     pub size: usize,
     pub reexport: Option<Reexport<'a>>,
 }
 
 #[derive(Debug, Copy, Clone)]
-struct ExportCtx<'a> {
-    pub ptr: u32,
+struct ExportCtx<'a, 'b> {
+    pub name: Option<&'a str>,
     pub idx: usize,
-    pub sections: &'a [section_table::SectionTable],
-    pub addresses: &'a ExportAddressTable,
-    pub ordinals: &'a ExportOrdinalTable,
+    pub ordinal_base: u32,
+    pub sections: &'b [section_table::SectionTable],
+    pub addresses: &'b ExportAddressTable,
 }
 
-impl<'a, 'b> scroll::ctx::TryFromCtx<'a, ExportCtx<'b>> for Export<'a> {
+impl<'a, 'b> scroll::ctx::TryFromCtx<'a, ExportCtx<'a, 'b>> for Export<'a> {
     type Error = error::Error;
     type Size = usize;
     #[inline]
-    fn try_from_ctx(bytes: &'a [u8], ExportCtx { ptr, idx, sections, addresses, ordinals }: ExportCtx<'b>) -> Result<(Self, Self::Size), Self::Error> {
+    fn try_from_ctx(bytes: &'a [u8], ExportCtx { name, idx, ordinal_base, sections, addresses }: ExportCtx<'a, 'b>) -> Result<(Self, Self::Size), Self::Error> {
         use self::ExportAddressTableEntry::*;
-        let i = idx;
-        let name_offset = utils::find_offset_or(ptr as usize, sections, &format!("Cannot map export name pointer rva ({:#x}) into offset", ptr))?;
-        let name = bytes.pread::<&str>(name_offset)?;
-        let ordinal = ordinals[i];
-        let address_index = ordinal as usize;
-        debug!("name: {} name_offset: {:#x} ordinal: {} address_index: {}", name, name_offset, ordinal, address_index);
-        if address_index >= addresses.len() {
-            debug!("Export.pread bad address index ({}) for {}: idx: {} ordinal: {} addresses.len(): {}", name, address_index, i, ordinal, addresses.len());
-            Ok((Export::default(), 0))
-        } else {
-            match addresses[address_index] {
-                ExportRVA(rva) => {
-                    let rva = rva as usize;
-                    let offset = utils::find_offset_or(rva, sections, &format!("Cannot map export rva ({:#x}) into offset for {}", rva, name))?;
-                    debug!("{}: {:#x}", name, offset);
-                    Ok((Export { name: name, offset: offset, rva: rva, reexport: None, size: 0 }, 0))
-                },
-                ForwarderRVA(rva) => {
-                    let rva = rva as usize;
-                    let offset = utils::find_offset_or(rva, sections, &format!("Cannot map forwarder rva ({:#x}) into offset for {}", rva, name))?;
-                    debug!("{}: stroffset {:#x}", name, offset);
-                    let reexport = Reexport::parse(bytes, offset)?;
-                    debug!("{}: reexport {:?}", name, reexport);
-                    Ok((Export { name: name, offset: rva, rva: rva, reexport: Some(reexport), size: 0 }, 0))
-                },
-            }
+        let ordinal = ordinal_base.wrapping_add(idx as u32);
+        let display = name.unwrap_or("<ordinal only>");
+        debug!("name: {} ordinal: {} address_index: {}", display, ordinal, idx);
+        match addresses[idx] {
+            ExportRVA(rva) => {
+                let rva = rva as usize;
+                let offset = utils::find_offset_or(rva, sections, &format!("Cannot map export rva ({:#x}) into offset for {} (ordinal {})", rva, display, ordinal))?;
+                debug!("{}: {:#x}", display, offset);
+                Ok((Export { name: name, ordinal: ordinal, offset: offset, rva: rva, reexport: None, size: 0 }, 0))
+            },
+            ForwarderRVA(rva) => {
+                let rva = rva as usize;
+                let offset = utils::find_offset_or(rva, sections, &format!("Cannot map forwarder rva ({:#x}) into offset for {} (ordinal {})", rva, display, ordinal))?;
+                debug!("{}: stroffset {:#x}", display, offset);
+                let reexport = Reexport::parse(bytes, offset)?;
+                debug!("{}: reexport {:?}", display, reexport);
+                Ok((Export { name: name, ordinal: ordinal, offset: rva, rva: rva, reexport: Some(reexport), size: 0 }, 0))
+            },
         }
     }
 }
 
 impl<'a> Export<'a> {
-    pub fn parse(bytes: &'a [u8], export_data: &ExportData, sections: &[section_table::SectionTable]) -> error::Result<Vec<Export<'a>>> {
+    pub fn parse(bytes: &'a [u8], export_data: &ExportData<'a>, sections: &[section_table::SectionTable]) -> error::Result<Vec<Export<'a>>> {
+        Export::parse_with_functions(bytes, export_data, sections, &[])
+    }
+    /// Parses every export in the export address table, using the `functions` of the exception table (if any) to compute sizes
+    ///
+    /// Exports are returned in ordinal order; an address exported under several names yields one export per name.
+    pub fn parse_with_functions(bytes: &'a [u8], export_data: &ExportData<'a>, sections: &[section_table::SectionTable], functions: &[exception::RuntimeFunction]) -> error::Result<Vec<Export<'a>>> {
         let pointers = &export_data.export_name_pointer_table;
         let addresses = &export_data.export_address_table;
         let ordinals = &export_data.export_ordinal_table;
-        //let ordinal_base = export_data.export_directory_table.ordinal_base as usize;
-        let mut exports = Vec::with_capacity(pointers.len());
-        for (idx, ptr) in pointers.iter().enumerate() {
-            let export = bytes.pread_with(0, ExportCtx { ptr: *ptr, idx: idx, sections: sections, addresses: addresses, ordinals: ordinals })?;
-            exports.push(export);
+        let ordinal_base = export_data.export_directory_table.ordinal_base;
+
+        // an address can be exported under several names, or none at all
+        let mut names: Vec<Vec<&'a str>> = vec![Vec::new(); addresses.len()];
+        for (ptr, ordinal) in pointers.iter().zip(ordinals.iter()) {
+            let address_index = *ordinal as usize;
+            let name_offset = utils::find_offset_or(*ptr as usize, sections, &format!("Cannot map export name pointer rva ({:#x}) into offset", ptr))?;
+            let name = bytes.pread::<&'a str>(name_offset)?;
+            if address_index >= addresses.len() {
+                debug!("Export.parse bad address index ({}) for {}: addresses.len(): {}", address_index, name, addresses.len());
+                continue;
+            }
+            names[address_index].push(name);
+        }
+
+        let mut exports = Vec::with_capacity(addresses.len());
+        for (idx, address) in addresses.iter().enumerate() {
+            // unused slots in the address table are zero
+            match *address {
+                ExportAddressTableEntry::ExportRVA(0) => continue,
+                _ => (),
+            }
+            let ctx = |name| ExportCtx { name: name, idx: idx, ordinal_base: ordinal_base, sections: sections, addresses: addresses };
+            if names[idx].is_empty() {
+                exports.push(bytes.pread_with(0, ctx(None))?);
+            } else {
+                for name in &names[idx] {
+                    exports.push(bytes.pread_with(0, ctx(Some(*name)))?);
+                }
+            }
         }
-        // TODO: sort + compute size
+        compute_sizes(&mut exports, sections, functions);
         Ok (exports)
     }
 }
 
+/// Sizes each non-forwarded export by its function table entry, or else by the distance to the next export or the end of its section
+fn compute_sizes(exports: &mut [Export], sections: &[section_table::SectionTable], functions: &[exception::RuntimeFunction]) {
+    let mut rvas = exports.iter().filter(|export| export.reexport.is_none()).map(|export| export.rva).collect::<Vec<_>>();
+    rvas.sort();
+    rvas.dedup();
+    let end = |section: &section_table::SectionTable| {
+        // a section without a virtual size is as large as its raw data
+        let size = if section.virtual_size == 0 { section.size_of_raw_data } else { section.virtual_size };
+        section.virtual_address as usize + size as usize
+    };
+    for export in exports.iter_mut().filter(|export| export.reexport.is_none()) {
+        if let Ok(i) = functions.binary_search_by_key(&(export.rva as u32), |function| function.begin_address) {
+            let function = &functions[i];
+            export.size = function.end_address.saturating_sub(function.begin_address) as usize;
+            continue;
+        }
+        let section_end = sections.iter()
+            .find(|section| utils::is_in_range(export.rva, section.virtual_address as usize, end(section)))
+            .map(end)
+            .unwrap_or(export.rva);
+        let next = match rvas.binary_search(&export.rva) {
+            Ok(i) if i + 1 < rvas.len() => rvas[i + 1],
+            _ => section_end,
+        };
+        export.size = ::std::cmp::min(next, section_end).saturating_sub(export.rva);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pe::tests::directory_section;
 
     #[test]
     fn size_export_directory_table() {
         assert_eq!(::std::mem::size_of::<ExportDirectoryTable>(), SIZEOF_EXPORT_DIRECTORY_TABLE);
     }
+
+    #[test]
+    fn ordinal_only_exports_and_sizes() {
+        let mut sections = [directory_section(0x100)];
+        let bytes = b"\0foo\0";
+        let mut export_data = ExportData::default();
+        export_data.export_directory_table.ordinal_base = 10;
+        export_data.export_address_table = vec![
+            ExportAddressTableEntry::ExportRVA(0x1040),
+            ExportAddressTableEntry::ExportRVA(0),
+            ExportAddressTableEntry::ExportRVA(0x1010),
+        ];
+        export_data.export_name_pointer_table = vec![0x1001];
+        export_data.export_ordinal_table = vec![2];
+        let exports = Export::parse(bytes, &export_data, &sections).unwrap();
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports[0].name, None);
+        assert_eq!(exports[0].ordinal, 10);
+        assert_eq!(exports[0].size, 0xc0);
+        assert_eq!(exports[1].name, Some("foo"));
+        assert_eq!(exports[1].ordinal, 12);
+        assert_eq!(exports[1].size, 0x30);
+        let functions = [exception::RuntimeFunction { begin_address: 0x1010, end_address: 0x1018, unwind_info_address: 0x2000 }];
+        let exports = Export::parse_with_functions(bytes, &export_data, &sections, &functions).unwrap();
+        assert_eq!(exports[1].size, 8);
+        // a section without a virtual size ends with its raw data
+        let mut exports = Export::parse(bytes, &export_data, &sections).unwrap();
+        sections[0].virtual_size = 0;
+        compute_sizes(&mut exports, &sections, &[]);
+        assert_eq!(exports[0].size, 0xc0);
+        assert_eq!(exports[1].size, 0x30);
+    }
 }
@@ -3,30 +3,155 @@ use error;
 use pe::optional_header;
 use scroll::{self, Pread};
 
-/// DOS header present in all PE binaries
+/// DOS header present in all PE binaries, `IMAGE_DOS_HEADER`
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
 pub struct DosHeader {
     /// Magic number: 5a4d
     pub signature: u16,
+    pub bytes_on_last_page: u16,
+    pub pages_in_file: u16,
+    pub relocations: u16,
+    pub size_of_header_in_paragraphs: u16,
+    pub minimum_extra_paragraphs_needed: u16,
+    pub maximum_extra_paragraphs_needed: u16,
+    pub initial_relative_ss: u16,
+    pub initial_sp: u16,
+    pub checksum: u16,
+    pub initial_ip: u16,
+    pub initial_relative_cs: u16,
+    pub file_address_of_relocation_table: u16,
+    pub overlay_number: u16,
+    pub reserved: [u16; 4],
+    pub oem_id: u16,
+    pub oem_info: u16,
+    pub reserved2: [u16; 10],
     /// Pointer to PE header, always at offset 0x3c
     pub pe_pointer: u32,
 }
 
+pub const SIZEOF_DOS_HEADER: usize = 0x40;
 pub const DOS_MAGIC: u16 = 0x5a4d;
 pub const PE_POINTER_OFFSET: u32 = 0x3c;
 
 impl DosHeader {
     pub fn parse(bytes: &[u8]) -> error::Result<Self> {
-        let signature = bytes.pread_with(0, scroll::LE)?;
-        let pe_pointer = bytes.pread_with(PE_POINTER_OFFSET as usize, scroll::LE)?;
-        Ok (DosHeader { signature: signature, pe_pointer: pe_pointer })
+        let dos_header = bytes.pread_with(0, scroll::LE)?;
+        Ok (dos_header)
+    }
+    /// Returns the DOS stub following this header in `bytes`, i.e., everything up to the PE header, including the Rich header if any
+    pub fn stub<'a>(&self, bytes: &'a [u8]) -> error::Result<&'a [u8]> {
+        let end = self.pe_pointer as usize;
+        if end < SIZEOF_DOS_HEADER || end > bytes.len() {
+            return Err(error::Error::Malformed(format!("PE pointer ({:#x}) is not after the DOS header or is out of bounds", end)));
+        }
+        Ok(&bytes[SIZEOF_DOS_HEADER..end])
+    }
+}
+
+/// "Rich", little endian
+pub const RICH_MAGIC: u32 = 0x6863_6952;
+/// "DanS", little endian
+pub const DANS_MAGIC: u32 = 0x536e_6144;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A decoded Rich header entry: how many objects a given Microsoft toolchain component contributed to the link
+pub struct RichEntry {
+    /// The product (tool and version), e.g. the C compiler of a specific Visual Studio release
+    pub product_id: u16,
+    /// The build number of the product
+    pub build: u16,
+    /// The number of objects linked which were produced by this product
+    pub count: u32,
+}
+
+impl RichEntry {
+    /// The raw `@comp.id` of this entry, i.e., `product_id << 16 | build`
+    pub fn comp_id(&self) -> u32 {
+        (self.product_id as u32) << 16 | self.build as u32
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The undocumented "Rich" header the Microsoft linker emits between the DOS stub and the PE header
+pub struct RichHeader {
+    /// The file offset of the (encoded) "DanS" marker which starts the Rich header
+    pub offset: usize,
+    /// The XOR key the header is encoded with, which is also its checksum
+    pub key: u32,
+    pub entries: Vec<RichEntry>,
+    /// The checksum computed over the DOS header, DOS stub and entries; equal to `key` unless the header was tampered with
+    pub checksum: u32,
+}
+
+impl RichHeader {
+    /// Finds and decodes the Rich header in the DOS stub of `bytes`, returning `None` if there is none
+    pub fn parse(bytes: &[u8], dos_header: &DosHeader) -> error::Result<Option<Self>> {
+        // e.g., hand crafted binaries overlap the PE header with the DOS header
+        let stub = match dos_header.stub(bytes) {
+            Ok(stub) => stub,
+            Err(_) => return Ok(None),
+        };
+        // "Rich" is dword aligned and followed by the key
+        let mut rich = None;
+        let mut i = (stub.len() / 4) * 4;
+        while i >= 8 {
+            i -= 4;
+            if stub.pread_with::<u32>(i - 4, scroll::LE)? == RICH_MAGIC {
+                rich = Some(i - 4);
+                break;
+            }
+        }
+        let rich = match rich {
+            Some(rich) => rich,
+            None => return Ok(None),
+        };
+        let key: u32 = stub.pread_with(rich + 4, scroll::LE)?;
+        let mut dans = None;
+        let mut i = rich;
+        while i >= 4 {
+            i -= 4;
+            if stub.pread_with::<u32>(i, scroll::LE)? ^ key == DANS_MAGIC {
+                dans = Some(i);
+                break;
+            }
+        }
+        let dans = match dans {
+            Some(dans) => dans,
+            // junk or partially overwritten DOS stubs are common, so a "Rich" without a marker is no Rich header
+            None => return Ok(None),
+        };
+        // the marker is followed by three zero (once decoded) padding dwords
+        let offset = &mut (dans + 16);
+        let mut entries = Vec::new();
+        while *offset + 8 <= rich {
+            let comp_id = stub.gread_with::<u32>(offset, scroll::LE)? ^ key;
+            let count = stub.gread_with::<u32>(offset, scroll::LE)? ^ key;
+            entries.push(RichEntry { product_id: (comp_id >> 16) as u16, build: comp_id as u16, count: count });
+        }
+        let offset = SIZEOF_DOS_HEADER + dans;
+        let mut checksum = offset as u32;
+        for (i, byte) in bytes[..offset].iter().enumerate() {
+            // the PE pointer is excluded, since it is only known once the headers are laid out
+            if i >= PE_POINTER_OFFSET as usize && i < SIZEOF_DOS_HEADER { continue }
+            checksum = checksum.wrapping_add((*byte as u32).rotate_left(i as u32));
+        }
+        for entry in &entries {
+            checksum = checksum.wrapping_add(entry.comp_id().rotate_left(entry.count));
+        }
+        Ok(Some(RichHeader { offset: offset, key: key, entries: entries, checksum: checksum }))
+    }
+    /// Whether the stored key matches the computed checksum
+    pub fn is_checksum_valid(&self) -> bool {
+        self.key == self.checksum
     }
 }
 
 /// COFF Header
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
 pub struct CoffHeader {
     /// COFF Magic: PE\0\0, little endian
     pub signature: u32,
@@ -43,13 +168,126 @@ pub struct CoffHeader {
 pub const SIZEOF_COFF_HEADER: usize = 24;
 /// PE\0\0, little endian
 pub const COFF_MAGIC: u32 = 0x00004550;
+/// The content of this field is assumed to be applicable to any machine type
+pub const COFF_MACHINE_UNKNOWN: u16 = 0x0;
+/// Intel 386 or later processors and compatible processors
 pub const COFF_MACHINE_X86: u16 = 0x14c;
+/// x64
 pub const COFF_MACHINE_X86_64: u16 = 0x8664;
+/// ARM little endian
+pub const COFF_MACHINE_ARM: u16 = 0x1c0;
+/// ARM Thumb-2 little endian
+pub const COFF_MACHINE_ARMNT: u16 = 0x1c4;
+/// ARM64 little endian
+pub const COFF_MACHINE_ARM64: u16 = 0xaa64;
+/// ARM64 code interoperable with x64 (ARM64EC)
+pub const COFF_MACHINE_ARM64EC: u16 = 0xa641;
+/// A hybrid image containing both ARM64 and ARM64EC code
+pub const COFF_MACHINE_ARM64X: u16 = 0xa64e;
+/// x86 code compiled for ARM64 hybrid portable executables (CHPE)
+pub const COFF_MACHINE_CHPE_X86: u16 = 0x3a64;
+/// Thumb
+pub const COFF_MACHINE_THUMB: u16 = 0x1c2;
+/// Intel Itanium
+pub const COFF_MACHINE_IA64: u16 = 0x200;
+/// EFI byte code
+pub const COFF_MACHINE_EBC: u16 = 0xebc;
+/// Matsushita AM33
+pub const COFF_MACHINE_AM33: u16 = 0x1d3;
+/// DEC Alpha
+pub const COFF_MACHINE_ALPHA: u16 = 0x184;
+/// DEC Alpha 64-bit
+pub const COFF_MACHINE_ALPHA64: u16 = 0x284;
+/// Mitsubishi M32R little endian
+pub const COFF_MACHINE_M32R: u16 = 0x9041;
+/// MIPS16
+pub const COFF_MACHINE_MIPS16: u16 = 0x266;
+/// MIPS with FPU
+pub const COFF_MACHINE_MIPSFPU: u16 = 0x366;
+/// MIPS16 with FPU
+pub const COFF_MACHINE_MIPSFPU16: u16 = 0x466;
+/// MIPS R3000 little endian
+pub const COFF_MACHINE_R3000: u16 = 0x162;
+/// MIPS R4000 little endian
+pub const COFF_MACHINE_R4000: u16 = 0x166;
+/// MIPS R10000 little endian
+pub const COFF_MACHINE_R10000: u16 = 0x168;
+/// MIPS little endian WCE v2
+pub const COFF_MACHINE_WCEMIPSV2: u16 = 0x169;
+/// Power PC little endian
+pub const COFF_MACHINE_POWERPC: u16 = 0x1f0;
+/// Power PC with floating point support
+pub const COFF_MACHINE_POWERPCFP: u16 = 0x1f1;
+/// Hitachi SH3
+pub const COFF_MACHINE_SH3: u16 = 0x1a2;
+/// Hitachi SH3 DSP
+pub const COFF_MACHINE_SH3DSP: u16 = 0x1a3;
+/// Hitachi SH4
+pub const COFF_MACHINE_SH4: u16 = 0x1a6;
+/// Hitachi SH5
+pub const COFF_MACHINE_SH5: u16 = 0x1a8;
+/// RISC-V 32-bit address space
+pub const COFF_MACHINE_RISCV32: u16 = 0x5032;
+/// RISC-V 64-bit address space
+pub const COFF_MACHINE_RISCV64: u16 = 0x5064;
+/// RISC-V 128-bit address space
+pub const COFF_MACHINE_RISCV128: u16 = 0x5128;
+/// LoongArch 32-bit
+pub const COFF_MACHINE_LOONGARCH32: u16 = 0x6232;
+/// LoongArch 64-bit
+pub const COFF_MACHINE_LOONGARCH64: u16 = 0x6264;
+
+/// Returns the name of the COFF `machine`, e.g. `"ARM64"`
+pub fn machine_to_str(machine: u16) -> &'static str {
+    match machine {
+        COFF_MACHINE_UNKNOWN => "UNKNOWN",
+        COFF_MACHINE_X86 => "X86",
+        COFF_MACHINE_X86_64 => "X86_64",
+        COFF_MACHINE_ARM => "ARM",
+        COFF_MACHINE_ARMNT => "ARMNT",
+        COFF_MACHINE_ARM64 => "ARM64",
+        COFF_MACHINE_ARM64EC => "ARM64EC",
+        COFF_MACHINE_ARM64X => "ARM64X",
+        COFF_MACHINE_CHPE_X86 => "CHPE_X86",
+        COFF_MACHINE_THUMB => "THUMB",
+        COFF_MACHINE_IA64 => "IA64",
+        COFF_MACHINE_EBC => "EBC",
+        COFF_MACHINE_AM33 => "AM33",
+        COFF_MACHINE_ALPHA => "ALPHA",
+        COFF_MACHINE_ALPHA64 => "ALPHA64",
+        COFF_MACHINE_M32R => "M32R",
+        COFF_MACHINE_MIPS16 => "MIPS16",
+        COFF_MACHINE_MIPSFPU => "MIPSFPU",
+        COFF_MACHINE_MIPSFPU16 => "MIPSFPU16",
+        COFF_MACHINE_R3000 => "R3000",
+        COFF_MACHINE_R4000 => "R4000",
+        COFF_MACHINE_R10000 => "R10000",
+        COFF_MACHINE_WCEMIPSV2 => "WCEMIPSV2",
+        COFF_MACHINE_POWERPC => "POWERPC",
+        COFF_MACHINE_POWERPCFP => "POWERPCFP",
+        COFF_MACHINE_SH3 => "SH3",
+        COFF_MACHINE_SH3DSP => "SH3DSP",
+        COFF_MACHINE_SH4 => "SH4",
+        COFF_MACHINE_SH5 => "SH5",
+        COFF_MACHINE_RISCV32 => "RISCV32",
+        COFF_MACHINE_RISCV64 => "RISCV64",
+        COFF_MACHINE_RISCV128 => "RISCV128",
+        COFF_MACHINE_LOONGARCH32 => "LOONGARCH32",
+        COFF_MACHINE_LOONGARCH64 => "LOONGARCH64",
+        _ => "UNKNOWN_MACHINE",
+    }
+}
 
 impl CoffHeader {
     pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
+        let signature = bytes.gread_with(offset, scroll::LE)?;
+        let mut coff = CoffHeader::parse_without_signature(bytes, offset)?;
+        coff.signature = signature;
+        Ok(coff)
+    }
+    /// Parses a COFF header which is not preceded by the `PE\0\0` signature, as in object files; `signature` is left as 0
+    pub fn parse_without_signature(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
         let mut coff = CoffHeader::default();
-        coff.signature = bytes.gread_with(offset, scroll::LE)?;
         coff.machine = bytes.gread_with(offset, scroll::LE)?;
         coff.number_of_sections = bytes.gread_with(offset, scroll::LE)?;
         coff.time_date_stamp = bytes.gread_with(offset, scroll::LE)?;
@@ -75,7 +313,13 @@ impl Header {
         let coff_header = CoffHeader::parse(&bytes, &mut offset)?;
         let optional_header =
             if coff_header.size_of_optional_header > 0 {
-                Some (bytes.pread::<optional_header::OptionalHeader>(offset)?)
+                let optional_header = bytes.gread::<optional_header::OptionalHeader>(&mut offset)?;
+                // the loader tolerates data directories past size_of_optional_header, but not truncated fields
+                let fields_size = optional_header.standard_fields.size() + optional_header.windows_fields.size();
+                if fields_size > coff_header.size_of_optional_header as usize {
+                    return Err(error::Error::Malformed(format!("Optional header fields ({:#x} bytes) are larger than size_of_optional_header ({:#x})", fields_size, coff_header.size_of_optional_header)));
+                }
+                Some (optional_header)
             }
         else { None };
         Ok( Header { dos_header: dos_header, coff_header: coff_header, optional_header: optional_header })
@@ -84,7 +328,7 @@ impl Header {
 
 #[cfg(test)]
 mod tests {
-    use super::{DOS_MAGIC, COFF_MAGIC, COFF_MACHINE_X86, Header};
+    use super::{DOS_MAGIC, COFF_MAGIC, COFF_MACHINE_X86, Header, RichHeader, RichEntry};
 
     const CRSS_HEADER: [u8; 688] =
         [0x4d, 0x5a, 0x90, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0xff, 0xff, 0x00, 0x00,
@@ -139,4 +383,44 @@ mod tests {
         assert!(header.coff_header.machine == COFF_MACHINE_X86);
         println!("header: {:?}", &header);
     }
+
+    #[test]
+    fn crss_dos_header_and_rich_header() {
+        let header = Header::parse(&&CRSS_HEADER[..]).unwrap();
+        let dos_header = header.dos_header;
+        assert_eq!(dos_header.pe_pointer, 0xd0);
+        assert_eq!(dos_header.pages_in_file, 3);
+        assert_eq!(dos_header.file_address_of_relocation_table, 0x40);
+        assert_eq!(dos_header.stub(&CRSS_HEADER).unwrap().len(), 0x90);
+        let rich = RichHeader::parse(&CRSS_HEADER, &dos_header).unwrap().unwrap();
+        assert_eq!(rich.offset, 0x80);
+        assert_eq!(rich.key, 0xb8ad2bee);
+        assert_eq!(rich.entries.len(), 6);
+        assert_eq!(rich.entries[0], RichEntry { product_id: 1, build: 0, count: 16 });
+        assert_eq!(rich.entries[1], RichEntry { product_id: 203, build: 65501, count: 5 });
+        assert!(rich.is_checksum_valid());
+    }
+
+    #[test]
+    fn rich_header_without_dans_marker() {
+        let mut bytes = CRSS_HEADER.to_vec();
+        // the DanS marker is at the start of the Rich header
+        bytes[0x80] ^= 0xff;
+        let header = Header::parse(&bytes).unwrap();
+        assert_eq!(RichHeader::parse(&bytes, &header.dos_header).unwrap(), None);
+    }
+
+    #[test]
+    fn small_size_of_optional_header() {
+        use scroll::{self, Pread, Pwrite};
+        let mut bytes = CRSS_HEADER.to_vec();
+        let pe_pointer: u32 = bytes.pread_with(0x3c, scroll::LE).unwrap();
+        let size_of_optional_header = pe_pointer as usize + 20;
+        // room for the fields, but not the data directories
+        bytes.pwrite_with(0x60u16, size_of_optional_header, scroll::LE).unwrap();
+        let header = Header::parse(&bytes).unwrap();
+        assert_eq!(header.optional_header.unwrap().windows_fields.number_of_rva_and_sizes, 16);
+        bytes.pwrite_with(0x5fu16, size_of_optional_header, scroll::LE).unwrap();
+        assert!(Header::parse(&bytes).is_err());
+    }
 }
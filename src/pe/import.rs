@@ -30,7 +30,8 @@ pub enum SyntheticImportLookupTableEntry<'a> {
 
 #[derive(Debug)]
 pub struct ImportLookupTableEntry<'a> {
-    pub bitfield: u32,
+    /// The raw thunk; 32 bits wide in PE32 binaries and 64 bits wide in PE32+ binaries
+    pub bitfield: u64,
     pub synthetic: SyntheticImportLookupTableEntry<'a>,
 }
 
@@ -38,15 +39,17 @@ pub type ImportLookupTable<'a> = Vec<ImportLookupTableEntry<'a>>;
 
 pub const IMPORT_BY_ORDINAL_32: u32 = 0x8000_0000;
 pub const IMPORT_RVA_MASK_32: u32 = 0x8fff_ffff;
+pub const IMPORT_BY_ORDINAL_64: u64 = 0x8000_0000_0000_0000;
 
 impl<'a> ImportLookupTableEntry<'a> {
-    pub fn parse(bytes: &'a [u8], mut offset: usize, sections: &[section_table::SectionTable])
+    pub fn parse(bytes: &'a [u8], mut offset: usize, is_64: bool, sections: &[section_table::SectionTable])
                                                                       -> error::Result<ImportLookupTable<'a>> {
         let le = scroll::LE;
         let offset = &mut offset;
         let mut table = Vec::new();
+        let ordinal_flag = if is_64 { IMPORT_BY_ORDINAL_64 } else { IMPORT_BY_ORDINAL_32 as u64 };
         loop {
-            let bitfield: u32 = bytes.gread_with(offset, le)?;
+            let bitfield: u64 = if is_64 { bytes.gread_with(offset, le)? } else { bytes.gread_with::<u32>(offset, le)? as u64 };
             if bitfield == 0 {
                 debug!("imports done");
                 break;
@@ -54,12 +57,12 @@ impl<'a> ImportLookupTableEntry<'a> {
                 let synthetic = {
                     debug!("bitfield {:#x}", bitfield);
                     use self::SyntheticImportLookupTableEntry::*;
-                    if bitfield & IMPORT_BY_ORDINAL_32 == IMPORT_BY_ORDINAL_32 {
+                    if bitfield & ordinal_flag == ordinal_flag {
                         let ordinal = (0xffff & bitfield) as u16;
                         debug!("importing by ordinal {:#x}", ordinal);
                         OrdinalNumber(ordinal)
                     } else {
-                        let rva = bitfield & IMPORT_RVA_MASK_32;
+                        let rva = bitfield as u32 & IMPORT_RVA_MASK_32;
                         let hentry = {
                             debug!("searching for RVA {:#x}", rva);
                             if let Some(offset) = utils::find_offset(rva as usize, sections) {
@@ -82,9 +85,10 @@ impl<'a> ImportLookupTableEntry<'a> {
 }
 
 // get until entry is 0
-pub type ImportAddressTable = Vec<u32>;
+pub type ImportAddressTable = Vec<u64>;
 
 pub const SIZEOF_IMPORT_ADDRESS_TABLE_ENTRY: usize = 4;
+pub const SIZEOF_IMPORT_ADDRESS_TABLE_ENTRY_64: usize = 8;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -121,7 +125,7 @@ pub struct SyntheticImportDirectoryEntry<'a> {
 }
 
 impl<'a> SyntheticImportDirectoryEntry<'a> {
-    pub fn parse(bytes: &'a [u8], import_directory_entry: ImportDirectoryEntry, sections: &[section_table::SectionTable]) -> error::Result<SyntheticImportDirectoryEntry<'a>> {
+    pub fn parse(bytes: &'a [u8], import_directory_entry: ImportDirectoryEntry, is_64: bool, sections: &[section_table::SectionTable]) -> error::Result<SyntheticImportDirectoryEntry<'a>> {
         const LE: scroll::Endian = scroll::LE;
         let name_rva = import_directory_entry.name_rva;
         let name = utils::try_name(bytes, name_rva as usize, sections)?;
@@ -129,7 +133,7 @@ impl<'a> SyntheticImportDirectoryEntry<'a> {
             let import_lookup_table_rva = import_directory_entry.import_lookup_table_rva;
             debug!("Synthesizing lookup table imports for {} lib, with import lookup table rva: {:#x}", name, import_lookup_table_rva);
             if let Some(import_lookup_table_offset) = utils::find_offset(import_lookup_table_rva as usize, sections) {
-                let import_lookup_table = ImportLookupTableEntry::parse(bytes, import_lookup_table_offset, sections)?;
+                let import_lookup_table = ImportLookupTableEntry::parse(bytes, import_lookup_table_offset, is_64, sections)?;
                 debug!("Successfully synthesized import lookup table entry: {:#?}", import_lookup_table);
                 Some(import_lookup_table)
             } else {
@@ -139,7 +143,7 @@ impl<'a> SyntheticImportDirectoryEntry<'a> {
         let import_address_table_offset = &mut utils::find_offset(import_directory_entry.import_address_table_rva as usize, sections).ok_or(error::Error::Malformed(format!("Cannot map import_address_table_rva {:#x} into offset for {}", import_directory_entry.import_address_table_rva, name)))?;
         let mut import_address_table = Vec::new();
         loop {
-            let import_address = bytes.gread_with(import_address_table_offset, LE)?;
+            let import_address: u64 = if is_64 { bytes.gread_with(import_address_table_offset, LE)? } else { bytes.gread_with::<u32>(import_address_table_offset, LE)? as u64 };
             if import_address == 0 { break } else { import_address_table.push(import_address); }
         }
         Ok(SyntheticImportDirectoryEntry {
@@ -158,7 +162,7 @@ pub struct ImportData<'a> {
 }
 
 impl<'a> ImportData<'a> {
-    pub fn parse(bytes: &'a[u8], dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<ImportData<'a>> {
+    pub fn parse(bytes: &'a[u8], dd: &data_directories::DataDirectory, is_64: bool, sections: &[section_table::SectionTable]) -> error::Result<ImportData<'a>> {
         let import_directory_table_rva = dd.virtual_address as usize;
         debug!("import_directory_table_rva {:#x}", import_directory_table_rva);
         let offset = &mut utils::find_offset(import_directory_table_rva, sections).ok_or(error::Error::Malformed(format!("Cannot create ImportData; cannot map import_directory_table_rva {:#x} into offset", import_directory_table_rva)))?;;
@@ -170,7 +174,7 @@ impl<'a> ImportData<'a> {
             if import_directory_entry.is_null() {
                 break;
             } else {
-                let entry = SyntheticImportDirectoryEntry::parse(bytes, import_directory_entry, sections)?;
+                let entry = SyntheticImportDirectoryEntry::parse(bytes, import_directory_entry, is_64, sections)?;
                 debug!("entry {:#?}", entry);
                 import_data.push(entry);
             }
@@ -192,15 +196,16 @@ pub struct Import<'a> {
 }
 
 impl<'a> Import<'a> {
-    pub fn parse(_bytes: &'a [u8], import_data: &ImportData<'a>, _sections: &[section_table::SectionTable]) -> error::Result<Vec<Import<'a>>> {
+    pub fn parse(_bytes: &'a [u8], import_data: &ImportData<'a>, is_64: bool, _sections: &[section_table::SectionTable]) -> error::Result<Vec<Import<'a>>> {
         let mut imports = Vec::new();
+        let entry_size = if is_64 { SIZEOF_IMPORT_ADDRESS_TABLE_ENTRY_64 } else { SIZEOF_IMPORT_ADDRESS_TABLE_ENTRY };
         for data in &import_data.import_data {
             if let Some(ref import_lookup_table) = data.import_lookup_table {
                 let dll = data.name;
                 let import_base = data.import_directory_entry.import_address_table_rva as usize;
                 debug!("Getting imports from {}", &dll);
                 for (i, entry) in import_lookup_table.iter().enumerate() {
-                    let offset = import_base + (i * SIZEOF_IMPORT_ADDRESS_TABLE_ENTRY);
+                    let offset = import_base + (i * entry_size);
                     use self::SyntheticImportLookupTableEntry::*;
                     let (rva, name, ordinal) =
                         match &entry.synthetic {
@@ -219,7 +224,7 @@ impl<'a> Import<'a> {
                         Import {
                             name: name,
                             ordinal: ordinal, dll: dll,
-                            size: 4, offset: offset, rva: rva as usize
+                            size: entry_size, offset: offset, rva: rva as usize
                         };
                     imports.push(import);
                 }
@@ -228,3 +233,24 @@ impl<'a> Import<'a> {
         Ok (imports)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pe::tests::directory_section;
+
+    #[test]
+    fn parse_64_bit_ordinal_thunks() {
+        use scroll::Pwrite;
+        let sections = [directory_section(0x100)];
+        let mut bytes = vec![0u8; 0x100];
+        bytes.pwrite_with(IMPORT_BY_ORDINAL_64 | 5, 0, scroll::LE).unwrap();
+        let table = ImportLookupTableEntry::parse(&bytes, 0, true, &sections).unwrap();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table[0].bitfield, IMPORT_BY_ORDINAL_64 | 5);
+        match table[0].synthetic {
+            SyntheticImportLookupTableEntry::OrdinalNumber(ordinal) => assert_eq!(ordinal, 5),
+            ref synthetic => panic!("unexpected thunk {:?}", synthetic),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! An accounting of which parts of a PE binary's file layout hold what: the headers, each section, data the loader never
+//! maps (the certificate table, debug data outside of sections, the COFF symbol table), the gaps in between, and any
+//! overlay appended past the end of the image data
+
+use core::ops::Range;
+use scroll::Pread;
+
+use pe::header;
+use pe::section_table;
+use pe::debug;
+use pe::coff;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RegionKind {
+    /// The DOS header and stub, PE headers and section table, up to `SizeOfHeaders`
+    Headers,
+    /// The raw data of the section with the given index
+    Section(usize),
+    /// The raw data of the debug directory entry with the given index, when it is not inside a section
+    DebugData(usize),
+    /// The COFF symbol table and the string table following it
+    SymbolTable,
+    /// The attribute certificate table
+    CertificateTable,
+    /// Bytes between the other regions which nothing refers to
+    Gap,
+    /// Bytes past the end of the image data, e.g. the payload of an installer or self-extracting archive
+    Overlay,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A named range of file offsets
+pub struct Region {
+    pub range: Range<usize>,
+    pub kind: RegionKind,
+    pub name: String,
+}
+
+impl Region {
+    fn new(range: Range<usize>, kind: RegionKind, name: String) -> Self {
+        Region { range: range, kind: kind, name: name }
+    }
+}
+
+/// Returns the regions of `bytes` which hold image data, sorted by offset and clamped to `bytes`
+pub fn data_regions(bytes: &[u8], header: &header::Header, sections: &[section_table::SectionTable], strings: &::strtab::Strtab, debug_data: Option<&debug::DebugData>) -> Vec<Region> {
+    let len = bytes.len();
+    let clamp = |start: usize, size: usize| -> Range<usize> {
+        let start = ::std::cmp::min(start, len);
+        start..::std::cmp::min(start.saturating_add(size), len)
+    };
+    let mut regions = Vec::new();
+    let size_of_headers = match header.optional_header {
+        Some(ref optional_header) => optional_header.windows_fields.size_of_headers as usize,
+        None => header.dos_header.pe_pointer as usize + header::SIZEOF_COFF_HEADER + header.coff_header.size_of_optional_header as usize + sections.len() * section_table::SIZEOF_SECTION_TABLE,
+    };
+    regions.push(Region::new(clamp(0, size_of_headers), RegionKind::Headers, "headers".to_string()));
+    for (i, section) in sections.iter().enumerate() {
+        if section.pointer_to_raw_data == 0 || section.size_of_raw_data == 0 { continue }
+        let name = section.full_name(strings).unwrap_or("").to_string();
+        regions.push(Region::new(clamp(section.pointer_to_raw_data as usize, section.size_of_raw_data as usize), RegionKind::Section(i), name));
+    }
+    if let Some(debug_data) = debug_data {
+        for (i, entry) in debug_data.entries.iter().enumerate() {
+            let range = clamp(entry.directory.pointer_to_raw_data as usize, entry.directory.size_of_data as usize);
+            if range.start == 0 || range.start == range.end { continue }
+            if regions.iter().any(|region| region.range.start <= range.start && range.end <= region.range.end) { continue }
+            regions.push(Region::new(range, RegionKind::DebugData(i), format!("debug data ({})", entry.directory.data_type)));
+        }
+    }
+    let symbol_table = header.coff_header.pointer_to_symbol_table as usize;
+    if symbol_table != 0 {
+        let symbols = (header.coff_header.number_of_symbol_table as usize).saturating_mul(coff::SIZEOF_SYMBOL);
+        let strings = bytes.pread_with::<u32>(symbol_table.saturating_add(symbols), ::scroll::LE).unwrap_or(0) as usize;
+        regions.push(Region::new(clamp(symbol_table, symbols.saturating_add(strings)), RegionKind::SymbolTable, "symbol table".to_string()));
+    }
+    if let Some(ref optional_header) = header.optional_header {
+        if let Some(dd) = *optional_header.data_directories.get_certificate_table() {
+            regions.push(Region::new(clamp(dd.virtual_address as usize, dd.size as usize), RegionKind::CertificateTable, "certificate table".to_string()));
+        }
+    }
+    regions.retain(|region| region.range.start < region.range.end);
+    regions.sort_by_key(|region| (region.range.start, region.range.end));
+    regions
+}
+
+/// The file offset just past the last byte of image data in `regions`, i.e., where the overlay, if any, begins
+pub fn end_of_image(regions: &[Region]) -> usize {
+    regions.iter().map(|region| region.range.end).max().unwrap_or(0)
+}
+
+/// Names every byte of `bytes`: the data regions, with `Gap`s between them and an `Overlay` after them
+pub fn layout(bytes: &[u8], header: &header::Header, sections: &[section_table::SectionTable], strings: &::strtab::Strtab, debug_data: Option<&debug::DebugData>) -> Vec<Region> {
+    let mut layout = Vec::new();
+    let mut cursor = 0;
+    for region in data_regions(bytes, header, sections, strings, debug_data) {
+        if region.range.start > cursor {
+            layout.push(Region::new(cursor..region.range.start, RegionKind::Gap, "gap".to_string()));
+        }
+        cursor = ::std::cmp::max(cursor, region.range.end);
+        layout.push(region);
+    }
+    if cursor < bytes.len() {
+        layout.push(Region::new(cursor..bytes.len(), RegionKind::Overlay, "overlay".to_string()));
+    }
+    layout
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scroll::{self, Pwrite};
+    use pe::PE;
+    use pe::data_directories;
+    use pe::tests::{tiny_pe, data_directory_offset};
+
+    #[test]
+    fn gaps_and_overlay() {
+        let mut bytes = tiny_pe();
+        let text = PE::parse(&bytes).unwrap().sections[0];
+        bytes.resize(0x800, 0);
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x500, size: 0x100 }, data_directory_offset(4), scroll::LE).unwrap();
+        let header = header::Header::parse(&bytes).unwrap();
+        let strings = ::strtab::Strtab::default();
+        let regions = layout(&bytes, &header, &[text], &strings, None);
+        let kinds = regions.iter().map(|region| (region.range.clone(), region.kind)).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            (0..0x200, RegionKind::Headers),
+            (0x200..0x400, RegionKind::Section(0)),
+            (0x400..0x500, RegionKind::Gap),
+            (0x500..0x600, RegionKind::CertificateTable),
+            (0x600..0x800, RegionKind::Overlay),
+        ]);
+        assert_eq!(regions[1].name, ".text");
+        assert_eq!(end_of_image(&data_regions(&bytes, &header, &[text], &strings, None)), 0x600);
+    }
+}
@@ -0,0 +1,668 @@
+use scroll::{self, Pread};
+use error;
+
+use pe::section_table;
+use pe::utils;
+use pe::data_directories;
+
+/// `IMAGE_LOAD_CONFIG_CODE_INTEGRITY`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct LoadConfigCodeIntegrity {
+    pub flags: u16,
+    pub catalog: u16,
+    pub catalog_offset: u32,
+    pub reserved: u32,
+}
+
+pub const SIZEOF_LOAD_CONFIG_CODE_INTEGRITY: usize = 12;
+
+/// The 32-bit load configuration directory, `IMAGE_LOAD_CONFIG_DIRECTORY32`.
+///
+/// The directory is versioned by its `size` field; fields past `size` are zeroed when parsed.
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageLoadConfigDirectory32 {
+    pub size: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub global_flags_clear: u32,
+    pub global_flags_set: u32,
+    pub critical_section_default_timeout: u32,
+    pub de_commit_free_block_threshold: u32,
+    pub de_commit_total_free_threshold: u32,
+    pub lock_prefix_table: u32,
+    pub maximum_allocation_size: u32,
+    pub virtual_memory_threshold: u32,
+    pub process_heap_flags: u32,
+    pub process_affinity_mask: u32,
+    pub csd_version: u16,
+    pub dependent_load_flags: u16,
+    pub edit_list: u32,
+    pub security_cookie: u32,
+    pub se_handler_table: u32,
+    pub se_handler_count: u32,
+    pub guard_cf_check_function_pointer: u32,
+    pub guard_cf_dispatch_function_pointer: u32,
+    pub guard_cf_function_table: u32,
+    pub guard_cf_function_count: u32,
+    pub guard_flags: u32,
+    pub code_integrity: LoadConfigCodeIntegrity,
+    pub guard_address_taken_iat_entry_table: u32,
+    pub guard_address_taken_iat_entry_count: u32,
+    pub guard_long_jump_target_table: u32,
+    pub guard_long_jump_target_count: u32,
+    pub dynamic_value_reloc_table: u32,
+    pub chpe_metadata_pointer: u32,
+    pub guard_rf_failure_routine: u32,
+    pub guard_rf_failure_routine_function_pointer: u32,
+    pub dynamic_value_reloc_table_offset: u32,
+    pub dynamic_value_reloc_table_section: u16,
+    pub reserved2: u16,
+    pub guard_rf_verify_stack_pointer_function_pointer: u32,
+    pub hot_patch_table_offset: u32,
+    pub reserved3: u32,
+    pub enclave_configuration_pointer: u32,
+    pub volatile_metadata_pointer: u32,
+    pub guard_eh_continuation_table: u32,
+    pub guard_eh_continuation_count: u32,
+    pub guard_xfg_check_function_pointer: u32,
+    pub guard_xfg_dispatch_function_pointer: u32,
+    pub guard_xfg_table_dispatch_function_pointer: u32,
+    pub cast_guard_os_determined_failure_mode: u32,
+    pub guard_memcpy_function_pointer: u32,
+}
+
+pub const SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_32: usize = 192;
+
+/// The 64-bit load configuration directory, `IMAGE_LOAD_CONFIG_DIRECTORY64`.
+///
+/// The directory is versioned by its `size` field; fields past `size` are zeroed when parsed.
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageLoadConfigDirectory64 {
+    pub size: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub global_flags_clear: u32,
+    pub global_flags_set: u32,
+    pub critical_section_default_timeout: u32,
+    pub de_commit_free_block_threshold: u64,
+    pub de_commit_total_free_threshold: u64,
+    pub lock_prefix_table: u64,
+    pub maximum_allocation_size: u64,
+    pub virtual_memory_threshold: u64,
+    pub process_affinity_mask: u64,
+    pub process_heap_flags: u32,
+    pub csd_version: u16,
+    pub dependent_load_flags: u16,
+    pub edit_list: u64,
+    pub security_cookie: u64,
+    pub se_handler_table: u64,
+    pub se_handler_count: u64,
+    pub guard_cf_check_function_pointer: u64,
+    pub guard_cf_dispatch_function_pointer: u64,
+    pub guard_cf_function_table: u64,
+    pub guard_cf_function_count: u64,
+    pub guard_flags: u32,
+    pub code_integrity: LoadConfigCodeIntegrity,
+    pub guard_address_taken_iat_entry_table: u64,
+    pub guard_address_taken_iat_entry_count: u64,
+    pub guard_long_jump_target_table: u64,
+    pub guard_long_jump_target_count: u64,
+    pub dynamic_value_reloc_table: u64,
+    pub chpe_metadata_pointer: u64,
+    pub guard_rf_failure_routine: u64,
+    pub guard_rf_failure_routine_function_pointer: u64,
+    pub dynamic_value_reloc_table_offset: u32,
+    pub dynamic_value_reloc_table_section: u16,
+    pub reserved2: u16,
+    pub guard_rf_verify_stack_pointer_function_pointer: u64,
+    pub hot_patch_table_offset: u32,
+    pub reserved3: u32,
+    pub enclave_configuration_pointer: u64,
+    pub volatile_metadata_pointer: u64,
+    pub guard_eh_continuation_table: u64,
+    pub guard_eh_continuation_count: u64,
+    pub guard_xfg_check_function_pointer: u64,
+    pub guard_xfg_dispatch_function_pointer: u64,
+    pub guard_xfg_table_dispatch_function_pointer: u64,
+    pub cast_guard_os_determined_failure_mode: u64,
+    pub guard_memcpy_function_pointer: u64,
+}
+
+pub const SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_64: usize = 320;
+
+/// Unified 32/64-bit load configuration directory; pointers are virtual addresses, i.e., they include the image base
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct ImageLoadConfigDirectory {
+    pub size: u32,
+    pub time_date_stamp: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub global_flags_clear: u32,
+    pub global_flags_set: u32,
+    pub critical_section_default_timeout: u32,
+    pub de_commit_free_block_threshold: u64,
+    pub de_commit_total_free_threshold: u64,
+    pub lock_prefix_table: u64,
+    pub maximum_allocation_size: u64,
+    pub virtual_memory_threshold: u64,
+    pub process_affinity_mask: u64,
+    pub process_heap_flags: u32,
+    pub csd_version: u16,
+    pub dependent_load_flags: u16,
+    pub edit_list: u64,
+    pub security_cookie: u64,
+    pub se_handler_table: u64,
+    pub se_handler_count: u64,
+    pub guard_cf_check_function_pointer: u64,
+    pub guard_cf_dispatch_function_pointer: u64,
+    pub guard_cf_function_table: u64,
+    pub guard_cf_function_count: u64,
+    pub guard_flags: u32,
+    pub code_integrity: LoadConfigCodeIntegrity,
+    pub guard_address_taken_iat_entry_table: u64,
+    pub guard_address_taken_iat_entry_count: u64,
+    pub guard_long_jump_target_table: u64,
+    pub guard_long_jump_target_count: u64,
+    pub dynamic_value_reloc_table: u64,
+    pub chpe_metadata_pointer: u64,
+    pub guard_rf_failure_routine: u64,
+    pub guard_rf_failure_routine_function_pointer: u64,
+    pub dynamic_value_reloc_table_offset: u32,
+    pub dynamic_value_reloc_table_section: u16,
+    pub guard_rf_verify_stack_pointer_function_pointer: u64,
+    pub hot_patch_table_offset: u32,
+    pub enclave_configuration_pointer: u64,
+    pub volatile_metadata_pointer: u64,
+    pub guard_eh_continuation_table: u64,
+    pub guard_eh_continuation_count: u64,
+    pub guard_xfg_check_function_pointer: u64,
+    pub guard_xfg_dispatch_function_pointer: u64,
+    pub guard_xfg_table_dispatch_function_pointer: u64,
+    pub cast_guard_os_determined_failure_mode: u64,
+    pub guard_memcpy_function_pointer: u64,
+}
+
+impl From<ImageLoadConfigDirectory32> for ImageLoadConfigDirectory {
+    fn from(lc: ImageLoadConfigDirectory32) -> Self {
+        ImageLoadConfigDirectory {
+            size: lc.size,
+            time_date_stamp: lc.time_date_stamp,
+            major_version: lc.major_version,
+            minor_version: lc.minor_version,
+            global_flags_clear: lc.global_flags_clear,
+            global_flags_set: lc.global_flags_set,
+            critical_section_default_timeout: lc.critical_section_default_timeout,
+            de_commit_free_block_threshold: lc.de_commit_free_block_threshold as u64,
+            de_commit_total_free_threshold: lc.de_commit_total_free_threshold as u64,
+            lock_prefix_table: lc.lock_prefix_table as u64,
+            maximum_allocation_size: lc.maximum_allocation_size as u64,
+            virtual_memory_threshold: lc.virtual_memory_threshold as u64,
+            process_affinity_mask: lc.process_affinity_mask as u64,
+            process_heap_flags: lc.process_heap_flags,
+            csd_version: lc.csd_version,
+            dependent_load_flags: lc.dependent_load_flags,
+            edit_list: lc.edit_list as u64,
+            security_cookie: lc.security_cookie as u64,
+            se_handler_table: lc.se_handler_table as u64,
+            se_handler_count: lc.se_handler_count as u64,
+            guard_cf_check_function_pointer: lc.guard_cf_check_function_pointer as u64,
+            guard_cf_dispatch_function_pointer: lc.guard_cf_dispatch_function_pointer as u64,
+            guard_cf_function_table: lc.guard_cf_function_table as u64,
+            guard_cf_function_count: lc.guard_cf_function_count as u64,
+            guard_flags: lc.guard_flags,
+            code_integrity: lc.code_integrity,
+            guard_address_taken_iat_entry_table: lc.guard_address_taken_iat_entry_table as u64,
+            guard_address_taken_iat_entry_count: lc.guard_address_taken_iat_entry_count as u64,
+            guard_long_jump_target_table: lc.guard_long_jump_target_table as u64,
+            guard_long_jump_target_count: lc.guard_long_jump_target_count as u64,
+            dynamic_value_reloc_table: lc.dynamic_value_reloc_table as u64,
+            chpe_metadata_pointer: lc.chpe_metadata_pointer as u64,
+            guard_rf_failure_routine: lc.guard_rf_failure_routine as u64,
+            guard_rf_failure_routine_function_pointer: lc.guard_rf_failure_routine_function_pointer as u64,
+            dynamic_value_reloc_table_offset: lc.dynamic_value_reloc_table_offset,
+            dynamic_value_reloc_table_section: lc.dynamic_value_reloc_table_section,
+            guard_rf_verify_stack_pointer_function_pointer: lc.guard_rf_verify_stack_pointer_function_pointer as u64,
+            hot_patch_table_offset: lc.hot_patch_table_offset,
+            enclave_configuration_pointer: lc.enclave_configuration_pointer as u64,
+            volatile_metadata_pointer: lc.volatile_metadata_pointer as u64,
+            guard_eh_continuation_table: lc.guard_eh_continuation_table as u64,
+            guard_eh_continuation_count: lc.guard_eh_continuation_count as u64,
+            guard_xfg_check_function_pointer: lc.guard_xfg_check_function_pointer as u64,
+            guard_xfg_dispatch_function_pointer: lc.guard_xfg_dispatch_function_pointer as u64,
+            guard_xfg_table_dispatch_function_pointer: lc.guard_xfg_table_dispatch_function_pointer as u64,
+            cast_guard_os_determined_failure_mode: lc.cast_guard_os_determined_failure_mode as u64,
+            guard_memcpy_function_pointer: lc.guard_memcpy_function_pointer as u64,
+        }
+    }
+}
+
+impl From<ImageLoadConfigDirectory64> for ImageLoadConfigDirectory {
+    fn from(lc: ImageLoadConfigDirectory64) -> Self {
+        ImageLoadConfigDirectory {
+            size: lc.size,
+            time_date_stamp: lc.time_date_stamp,
+            major_version: lc.major_version,
+            minor_version: lc.minor_version,
+            global_flags_clear: lc.global_flags_clear,
+            global_flags_set: lc.global_flags_set,
+            critical_section_default_timeout: lc.critical_section_default_timeout,
+            de_commit_free_block_threshold: lc.de_commit_free_block_threshold,
+            de_commit_total_free_threshold: lc.de_commit_total_free_threshold,
+            lock_prefix_table: lc.lock_prefix_table,
+            maximum_allocation_size: lc.maximum_allocation_size,
+            virtual_memory_threshold: lc.virtual_memory_threshold,
+            process_affinity_mask: lc.process_affinity_mask,
+            process_heap_flags: lc.process_heap_flags,
+            csd_version: lc.csd_version,
+            dependent_load_flags: lc.dependent_load_flags,
+            edit_list: lc.edit_list,
+            security_cookie: lc.security_cookie,
+            se_handler_table: lc.se_handler_table,
+            se_handler_count: lc.se_handler_count,
+            guard_cf_check_function_pointer: lc.guard_cf_check_function_pointer,
+            guard_cf_dispatch_function_pointer: lc.guard_cf_dispatch_function_pointer,
+            guard_cf_function_table: lc.guard_cf_function_table,
+            guard_cf_function_count: lc.guard_cf_function_count,
+            guard_flags: lc.guard_flags,
+            code_integrity: lc.code_integrity,
+            guard_address_taken_iat_entry_table: lc.guard_address_taken_iat_entry_table,
+            guard_address_taken_iat_entry_count: lc.guard_address_taken_iat_entry_count,
+            guard_long_jump_target_table: lc.guard_long_jump_target_table,
+            guard_long_jump_target_count: lc.guard_long_jump_target_count,
+            dynamic_value_reloc_table: lc.dynamic_value_reloc_table,
+            chpe_metadata_pointer: lc.chpe_metadata_pointer,
+            guard_rf_failure_routine: lc.guard_rf_failure_routine,
+            guard_rf_failure_routine_function_pointer: lc.guard_rf_failure_routine_function_pointer,
+            dynamic_value_reloc_table_offset: lc.dynamic_value_reloc_table_offset,
+            dynamic_value_reloc_table_section: lc.dynamic_value_reloc_table_section,
+            guard_rf_verify_stack_pointer_function_pointer: lc.guard_rf_verify_stack_pointer_function_pointer,
+            hot_patch_table_offset: lc.hot_patch_table_offset,
+            enclave_configuration_pointer: lc.enclave_configuration_pointer,
+            volatile_metadata_pointer: lc.volatile_metadata_pointer,
+            guard_eh_continuation_table: lc.guard_eh_continuation_table,
+            guard_eh_continuation_count: lc.guard_eh_continuation_count,
+            guard_xfg_check_function_pointer: lc.guard_xfg_check_function_pointer,
+            guard_xfg_dispatch_function_pointer: lc.guard_xfg_dispatch_function_pointer,
+            guard_xfg_table_dispatch_function_pointer: lc.guard_xfg_table_dispatch_function_pointer,
+            cast_guard_os_determined_failure_mode: lc.cast_guard_os_determined_failure_mode,
+            guard_memcpy_function_pointer: lc.guard_memcpy_function_pointer,
+        }
+    }
+}
+
+// Control Flow Guard flags, stored in `guard_flags`
+pub const IMAGE_GUARD_CF_INSTRUMENTED: u32 = 0x0000_0100;
+pub const IMAGE_GUARD_CFW_INSTRUMENTED: u32 = 0x0000_0200;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT: u32 = 0x0000_0400;
+pub const IMAGE_GUARD_SECURITY_COOKIE_UNUSED: u32 = 0x0000_0800;
+pub const IMAGE_GUARD_PROTECT_DELAYLOAD_IAT: u32 = 0x0000_1000;
+pub const IMAGE_GUARD_DELAYLOAD_IAT_IN_ITS_OWN_SECTION: u32 = 0x0000_2000;
+pub const IMAGE_GUARD_CF_EXPORT_SUPPRESSION_INFO_PRESENT: u32 = 0x0000_4000;
+pub const IMAGE_GUARD_CF_ENABLE_EXPORT_SUPPRESSION: u32 = 0x0000_8000;
+pub const IMAGE_GUARD_CF_LONGJUMP_TABLE_PRESENT: u32 = 0x0001_0000;
+pub const IMAGE_GUARD_RF_INSTRUMENTED: u32 = 0x0002_0000;
+pub const IMAGE_GUARD_RF_ENABLE: u32 = 0x0004_0000;
+pub const IMAGE_GUARD_RF_STRICT: u32 = 0x0008_0000;
+pub const IMAGE_GUARD_RETPOLINE_PRESENT: u32 = 0x0010_0000;
+pub const IMAGE_GUARD_EH_CONTINUATION_TABLE_PRESENT: u32 = 0x0040_0000;
+pub const IMAGE_GUARD_XFG_ENABLED: u32 = 0x0080_0000;
+/// The number of extra metadata bytes following each RVA in the guard function table is stored in these bits
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK: u32 = 0xf000_0000;
+pub const IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT: u32 = 28;
+
+// Flags in the first metadata byte of a guard function table entry
+pub const IMAGE_GUARD_FLAG_FID_SUPPRESSED: u8 = 0x01;
+pub const IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED: u8 = 0x02;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// An entry in the Control Flow Guard function table, i.e., a valid indirect call target
+pub struct GuardFunction {
+    pub rva: u32,
+    /// `IMAGE_GUARD_FLAG_*` metadata, or 0 if the table has no metadata bytes
+    pub flags: u8,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A code range described by the CHPE (hybrid x86 on ARM64, or ARM64EC) metadata
+pub struct ChpeRangeEntry {
+    /// The range's RVA, with the range type stored in the low bits
+    pub start_offset: u32,
+    pub length: u32,
+}
+
+impl ChpeRangeEntry {
+    /// The RVA this range starts at
+    pub fn rva(&self) -> u32 {
+        self.start_offset & !0x3
+    }
+    /// The architecture of the code in this range; 0 is native ARM64, 1 is ARM64EC (or CHPE for x86) and 2 is x64
+    pub fn range_type(&self) -> u32 {
+        self.start_offset & 0x3
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The common prefix of the x86 CHPE and ARM64EC metadata, `IMAGE_CHPE_METADATA_X86` and `IMAGE_ARM64EC_METADATA`
+pub struct ChpeMetadata {
+    pub version: u32,
+    pub code_ranges: Vec<ChpeRangeEntry>,
+}
+
+impl ChpeMetadata {
+    pub fn parse(bytes: &[u8], offset: usize, sections: &[section_table::SectionTable]) -> error::Result<Self> {
+        let offset = &mut offset.clone();
+        let version = bytes.gread_with(offset, scroll::LE)?;
+        let code_map_rva: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let code_map_count: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let mut code_ranges = Vec::new();
+        if code_map_rva != 0 && code_map_count != 0 {
+            let offset = &mut utils::find_offset_or(code_map_rva as usize, sections, &format!("Cannot map CHPE code map rva {:#x} into offset", code_map_rva))?;
+            if code_map_count as usize > bytes.len() / 8 {
+                return Err(error::Error::Malformed(format!("CHPE code map count ({}) is larger than the binary", code_map_count)));
+            }
+            for _ in 0..code_map_count {
+                let start_offset = bytes.gread_with(offset, scroll::LE)?;
+                let length = bytes.gread_with(offset, scroll::LE)?;
+                code_ranges.push(ChpeRangeEntry { start_offset: start_offset, length: length });
+            }
+        }
+        Ok(ChpeMetadata { version: version, code_ranges: code_ranges })
+    }
+}
+
+// Well-known `DynamicRelocation::symbol` values
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_RF_PROLOGUE: u64 = 1;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_RF_EPILOGUE: u64 = 2;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_IMPORT_CONTROL_TRANSFER: u64 = 3;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_INDIR_CONTROL_TRANSFER: u64 = 4;
+pub const IMAGE_DYNAMIC_RELOCATION_GUARD_SWITCHTABLE_BRANCH: u64 = 5;
+pub const IMAGE_DYNAMIC_RELOCATION_ARM64X: u64 = 6;
+pub const IMAGE_DYNAMIC_RELOCATION_FUNCTION_OVERRIDE: u64 = 7;
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// A base relocation style block of fixups for a single page
+pub struct DynamicRelocationBlock<'a> {
+    pub virtual_address: u32,
+    pub size_of_block: u32,
+    /// The raw fixup records; their encoding depends on the owning `DynamicRelocation::symbol`
+    pub entries: &'a [u8],
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+/// An `IMAGE_DYNAMIC_RELOCATION` (version 1) or `IMAGE_DYNAMIC_RELOCATION_V2` (version 2) entry
+pub struct DynamicRelocation<'a> {
+    pub symbol: u64,
+    /// Only present in version 2 tables
+    pub symbol_group: u32,
+    /// Only present in version 2 tables
+    pub flags: u32,
+    /// For version 1 tables, a list of page blocks; see `blocks`. For version 2, the raw fixup info
+    pub fixup_info: &'a [u8],
+}
+
+impl<'a> DynamicRelocation<'a> {
+    /// Splits a version 1 `fixup_info` into its page blocks
+    pub fn blocks(&self) -> error::Result<Vec<DynamicRelocationBlock<'a>>> {
+        let bytes = self.fixup_info;
+        let offset = &mut 0;
+        let mut blocks = Vec::new();
+        while *offset + 8 <= bytes.len() {
+            let start = *offset;
+            let virtual_address: u32 = bytes.gread_with(offset, scroll::LE)?;
+            let size_of_block: u32 = bytes.gread_with(offset, scroll::LE)?;
+            if (size_of_block as usize) < 8 || start + size_of_block as usize > bytes.len() {
+                return Err(error::Error::Malformed(format!("Dynamic relocation block at {:#x} has bad size {:#x}", start, size_of_block)));
+            }
+            let entries = &bytes[*offset..start + size_of_block as usize];
+            *offset = start + size_of_block as usize;
+            blocks.push(DynamicRelocationBlock { virtual_address: virtual_address, size_of_block: size_of_block, entries: entries });
+        }
+        Ok(blocks)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The dynamic value relocation table, `IMAGE_DYNAMIC_RELOCATION_TABLE`, and its relocations
+pub struct DynamicRelocationTable<'a> {
+    pub version: u32,
+    pub size: u32,
+    /// The relocations of the table; empty if its `version` is unknown
+    pub relocations: Vec<DynamicRelocation<'a>>,
+}
+
+impl<'a> DynamicRelocationTable<'a> {
+    pub fn parse(bytes: &'a [u8], offset: usize, is_64: bool) -> error::Result<Self> {
+        let offset = &mut offset.clone();
+        let version: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let size: u32 = bytes.gread_with(offset, scroll::LE)?;
+        let start = *offset;
+        let end = start.checked_add(size as usize).ok_or(error::Error::Malformed(format!("Dynamic relocation table size ({:#x}) overflows", size)))?;
+        if end > bytes.len() {
+            return Err(error::Error::Malformed(format!("Dynamic relocation table at {:#x} with size {:#x} is out of bounds", start, size)));
+        }
+        let mut relocations = Vec::new();
+        while *offset < end {
+            let relocation = match version {
+                1 => {
+                    let symbol = if is_64 { bytes.gread_with::<u64>(offset, scroll::LE)? } else { bytes.gread_with::<u32>(offset, scroll::LE)? as u64 };
+                    let base_reloc_size: u32 = bytes.gread_with(offset, scroll::LE)?;
+                    let fixup_info: &'a [u8] = bytes[..end].gread_with(offset, base_reloc_size as usize)?;
+                    DynamicRelocation { symbol: symbol, symbol_group: 0, flags: 0, fixup_info: fixup_info }
+                },
+                2 => {
+                    let header_start = *offset;
+                    let header_size: u32 = bytes.gread_with(offset, scroll::LE)?;
+                    let fixup_info_size: u32 = bytes.gread_with(offset, scroll::LE)?;
+                    let symbol = if is_64 { bytes.gread_with::<u64>(offset, scroll::LE)? } else { bytes.gread_with::<u32>(offset, scroll::LE)? as u64 };
+                    let symbol_group = bytes.gread_with(offset, scroll::LE)?;
+                    let flags = bytes.gread_with(offset, scroll::LE)?;
+                    *offset = header_start + header_size as usize;
+                    let fixup_info: &'a [u8] = bytes[..end].gread_with(offset, fixup_info_size as usize)?;
+                    DynamicRelocation { symbol: symbol, symbol_group: symbol_group, flags: flags, fixup_info: fixup_info }
+                },
+                // a version we do not know the layout of, whose relocations are left undecoded
+                _ => break,
+            };
+            debug!("{:?}", relocation);
+            relocations.push(relocation);
+        }
+        Ok(DynamicRelocationTable { version: version, size: size, relocations: relocations })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The load configuration directory of this binary, and the security related tables it points to
+pub struct LoadConfigData<'a> {
+    pub image_load_config_directory: ImageLoadConfigDirectory,
+    /// The virtual address of the `/GS` security cookie
+    pub security_cookie: Option<u64>,
+    /// The RVAs of the registered SafeSEH handlers; only present in 32-bit binaries
+    pub se_handler_table: Option<Vec<u32>>,
+    /// The Control Flow Guard function table, i.e., every valid indirect call target
+    pub guard_cf_function_table: Vec<GuardFunction>,
+    pub chpe_metadata: Option<ChpeMetadata>,
+    pub dynamic_relocation_table: Option<DynamicRelocationTable<'a>>,
+}
+
+impl<'a> LoadConfigData<'a> {
+    pub fn parse(bytes: &'a [u8], image_base: u64, is_64: bool, dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Self> {
+        let rva = dd.virtual_address as usize;
+        let offset = utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map ImageLoadConfigDirectory rva {:#x} into offset", rva)))?;
+        // the directory may be shorter (older toolchains) or longer (newer toolchains) than what we know about,
+        // so copy whatever is present into a zeroed buffer of the size we understand
+        let size = bytes.pread_with::<u32>(offset, scroll::LE)? as usize;
+        let max = if is_64 { SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_64 } else { SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_32 };
+        let len = ::std::cmp::min(size, max);
+        if offset + len > bytes.len() {
+            return Err(error::Error::Malformed(format!("ImageLoadConfigDirectory at {:#x} with size {:#x} is out of bounds", offset, size)));
+        }
+        let mut raw = [0u8; SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_64];
+        raw[..len].copy_from_slice(&bytes[offset..offset + len]);
+        let image_load_config_directory: ImageLoadConfigDirectory = if is_64 {
+            raw.pread_with::<ImageLoadConfigDirectory64>(0, scroll::LE)?.into()
+        } else {
+            raw.pread_with::<ImageLoadConfigDirectory32>(0, scroll::LE)?.into()
+        };
+        debug!("{:#?}", image_load_config_directory);
+        let lc = &image_load_config_directory;
+
+        let security_cookie = if lc.security_cookie != 0 { Some(lc.security_cookie) } else { None };
+
+        // the tables the directory points to are often garbage in packed or hand-edited binaries, so a table which
+        // cannot be read is left out instead of failing the whole directory
+        let se_handler_table = if !is_64 && lc.se_handler_table != 0 {
+            parse_se_handler_table(bytes, lc, image_base, sections).ok()
+        } else {
+            None
+        };
+
+        let guard_cf_function_table = if lc.guard_cf_function_table != 0 && lc.guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT != 0 {
+            parse_guard_cf_function_table(bytes, lc, image_base, sections).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let chpe_metadata = if lc.chpe_metadata_pointer != 0 {
+            utils::find_offset_va(lc.chpe_metadata_pointer, image_base, sections)
+                .and_then(|offset| ChpeMetadata::parse(bytes, offset, sections).ok())
+        } else {
+            None
+        };
+
+        let dynamic_relocation_table = {
+            let section = lc.dynamic_value_reloc_table_section as usize;
+            let offset = if section != 0 && section <= sections.len() {
+                let rva = sections[section - 1].virtual_address as usize + lc.dynamic_value_reloc_table_offset as usize;
+                utils::find_offset(rva, sections)
+            } else if lc.dynamic_value_reloc_table != 0 {
+                utils::find_offset_va(lc.dynamic_value_reloc_table, image_base, sections)
+            } else {
+                None
+            };
+            offset.and_then(|offset| DynamicRelocationTable::parse(bytes, offset, is_64).ok())
+        };
+
+        Ok(LoadConfigData {
+            image_load_config_directory: image_load_config_directory,
+            security_cookie: security_cookie,
+            se_handler_table: se_handler_table,
+            guard_cf_function_table: guard_cf_function_table,
+            chpe_metadata: chpe_metadata,
+            dynamic_relocation_table: dynamic_relocation_table,
+        })
+    }
+}
+
+/// Reads the RVAs of the SafeSEH handlers `lc` points to
+fn parse_se_handler_table(bytes: &[u8], lc: &ImageLoadConfigDirectory, image_base: u64, sections: &[section_table::SectionTable]) -> error::Result<Vec<u32>> {
+    let count = lc.se_handler_count as usize;
+    let offset = &mut utils::find_offset_va(lc.se_handler_table, image_base, sections).ok_or(error::Error::Malformed(format!("Cannot map SEHandlerTable {:#x} into offset", lc.se_handler_table)))?;
+    if count > bytes.len() / 4 {
+        return Err(error::Error::Malformed(format!("SEHandlerCount ({}) is larger than the binary", count)));
+    }
+    let mut handlers = Vec::with_capacity(count);
+    for _ in 0..count {
+        handlers.push(bytes.gread_with(offset, scroll::LE)?);
+    }
+    Ok(handlers)
+}
+
+/// Reads the Control Flow Guard function table `lc` points to
+fn parse_guard_cf_function_table(bytes: &[u8], lc: &ImageLoadConfigDirectory, image_base: u64, sections: &[section_table::SectionTable]) -> error::Result<Vec<GuardFunction>> {
+    let count = lc.guard_cf_function_count as usize;
+    let stride = ((lc.guard_flags & IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_MASK) >> IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT) as usize;
+    let offset = &mut utils::find_offset_va(lc.guard_cf_function_table, image_base, sections).ok_or(error::Error::Malformed(format!("Cannot map GuardCFFunctionTable {:#x} into offset", lc.guard_cf_function_table)))?;
+    if count > bytes.len() / (4 + stride) {
+        return Err(error::Error::Malformed(format!("GuardCFFunctionCount ({}) is larger than the binary", count)));
+    }
+    let mut functions = Vec::with_capacity(count);
+    for _ in 0..count {
+        let rva = bytes.gread_with(offset, scroll::LE)?;
+        let flags = if stride > 0 { bytes.pread_with(*offset, scroll::LE)? } else { 0 };
+        *offset += stride;
+        functions.push(GuardFunction { rva: rva, flags: flags });
+    }
+    Ok(functions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pe::tests::directory_section;
+
+    #[test]
+    fn sizeof_load_config_directories() {
+        assert_eq!(::std::mem::size_of::<LoadConfigCodeIntegrity>(), SIZEOF_LOAD_CONFIG_CODE_INTEGRITY);
+        assert_eq!(::std::mem::size_of::<ImageLoadConfigDirectory32>(), SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_32);
+        assert_eq!(::std::mem::size_of::<ImageLoadConfigDirectory64>(), SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_64);
+    }
+
+    #[test]
+    fn parse_short_load_config_with_guard_table() {
+        use scroll::Pwrite;
+        let sections = [directory_section(0x200)];
+        let image_base = 0x1_4000_0000u64;
+        let mut bytes = vec![0u8; 0x200];
+        // a version which ends right after `guard_flags`
+        let size = 148u32;
+        bytes.pwrite_with(size, 0, scroll::LE).unwrap();
+        // security cookie
+        bytes.pwrite_with(image_base + 0x1180, 88, scroll::LE).unwrap();
+        // guard cf function table + count
+        bytes.pwrite_with(image_base + 0x1100, 128, scroll::LE).unwrap();
+        bytes.pwrite_with(2u64, 136, scroll::LE).unwrap();
+        bytes.pwrite_with(IMAGE_GUARD_CF_INSTRUMENTED | IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT | (1 << IMAGE_GUARD_CF_FUNCTION_TABLE_SIZE_SHIFT), 144, scroll::LE).unwrap();
+        // this is past `size` and must be ignored
+        bytes.pwrite_with(image_base + 0x1190, 192, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1010u32, 0x100, scroll::LE).unwrap();
+        bytes.pwrite_with(0u8, 0x104, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1020u32, 0x105, scroll::LE).unwrap();
+        bytes.pwrite_with(IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED, 0x109, scroll::LE).unwrap();
+        let dd = data_directories::DataDirectory { virtual_address: 0x1000, size: size };
+        let lc = LoadConfigData::parse(&bytes, image_base, true, &dd, &sections).unwrap();
+        assert_eq!(lc.security_cookie, Some(image_base + 0x1180));
+        assert_eq!(lc.image_load_config_directory.dynamic_value_reloc_table, 0);
+        assert_eq!(lc.se_handler_table, None);
+        assert_eq!(lc.guard_cf_function_table, vec![GuardFunction { rva: 0x1010, flags: 0 }, GuardFunction { rva: 0x1020, flags: IMAGE_GUARD_FLAG_EXPORT_SUPPRESSED }]);
+        assert!(lc.dynamic_relocation_table.is_none());
+    }
+
+    #[test]
+    fn parse_load_config_with_bad_pointers() {
+        use scroll::Pwrite;
+        let sections = [directory_section(0x200)];
+        let image_base = 0x400000u64;
+        let mut bytes = vec![0u8; 0x200];
+        let size = SIZEOF_IMAGE_LOAD_CONFIG_DIRECTORY_32 as u32;
+        bytes.pwrite_with(size, 0, scroll::LE).unwrap();
+        // se handler table and count, pointing outside the image
+        bytes.pwrite_with(0xdead_0000u32, 64, scroll::LE).unwrap();
+        bytes.pwrite_with(4u32, 68, scroll::LE).unwrap();
+        // guard cf function table, count and flags, with a count larger than the binary
+        bytes.pwrite_with(image_base as u32 + 0x1100, 80, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1000_0000u32, 84, scroll::LE).unwrap();
+        bytes.pwrite_with(IMAGE_GUARD_CF_INSTRUMENTED | IMAGE_GUARD_CF_FUNCTION_TABLE_PRESENT, 88, scroll::LE).unwrap();
+        // dynamic value reloc table, with an unknown version
+        bytes.pwrite_with(image_base as u32 + 0x1180, 120, scroll::LE).unwrap();
+        bytes.pwrite_with(7u32, 0x180, scroll::LE).unwrap();
+        bytes.pwrite_with(8u32, 0x184, scroll::LE).unwrap();
+        // chpe metadata pointer, outside the image
+        bytes.pwrite_with(0xdead_1000u32, 124, scroll::LE).unwrap();
+        let dd = data_directories::DataDirectory { virtual_address: 0x1000, size: size };
+        let lc = LoadConfigData::parse(&bytes, image_base, false, &dd, &sections).unwrap();
+        assert_eq!(lc.se_handler_table, None);
+        assert!(lc.guard_cf_function_table.is_empty());
+        assert_eq!(lc.chpe_metadata, None);
+        let table = lc.dynamic_relocation_table.unwrap();
+        assert_eq!(table.version, 7);
+        assert!(table.relocations.is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! Converting a PE image between its file layout and the layout the loader maps it into memory with
+//!
+//! In the mapped layout every RVA is an offset into the image: the headers are at 0 and each section's raw data is at its
+//! `virtual_address`, zero filled up to its (section aligned) `virtual_size`.
+
+use error;
+
+use pe::header;
+use pe::section_table;
+use pe::writer::align_up;
+
+/// How much larger than its input a mapped or unmapped image may be, i.e., the most zero filling, e.g. for `.bss`, that
+/// `map` and `unmap` allocate before rejecting the headers as bogus
+pub const MAX_ZERO_FILL: usize = 0x1000_0000;
+
+/// Rejects a `size` byte layout of `len` input bytes which would be mostly zero filling
+fn check_size(size: usize, len: usize) -> error::Result<()> {
+    if size > len.saturating_add(MAX_ZERO_FILL) {
+        return Err(error::Error::Malformed(format!("Image size ({:#x}) is implausibly large for {:#x} bytes of input", size, len)));
+    }
+    Ok(())
+}
+
+fn alignments(header: &header::Header) -> error::Result<(usize, usize, usize, usize)> {
+    match header.optional_header {
+        Some(ref optional_header) => {
+            let windows_fields = &optional_header.windows_fields;
+            Ok((windows_fields.section_alignment as usize, windows_fields.file_alignment as usize, windows_fields.size_of_headers as usize, windows_fields.size_of_image as usize))
+        },
+        None => Err(error::Error::Malformed("Cannot map a PE binary without an optional header".to_string())),
+    }
+}
+
+/// The number of raw data bytes the loader copies for `section`
+fn mapped_raw_size(section: &section_table::SectionTable, section_alignment: usize) -> usize {
+    let raw = section.size_of_raw_data as usize;
+    if section.virtual_size == 0 { raw } else { ::std::cmp::min(raw, align_up(section.virtual_size as usize, section_alignment)) }
+}
+
+/// Lays out the file `bytes` as the loader maps them: headers at 0, each section at its RVA, zero filling up to
+/// `SizeOfImage` (or the end of the last section, if it is larger), which may exceed the length of `bytes` by at most
+/// `MAX_ZERO_FILL`
+pub fn map(bytes: &[u8], header: &header::Header, sections: &[section_table::SectionTable]) -> error::Result<Vec<u8>> {
+    let (section_alignment, _, size_of_headers, size_of_image) = alignments(header)?;
+    let end = sections.iter().map(|section| {
+        section.virtual_address as usize + align_up(::std::cmp::max(section.virtual_size, section.size_of_raw_data) as usize, section_alignment)
+    }).max().unwrap_or(0);
+    let size = ::std::cmp::max(size_of_image, end);
+    check_size(size, bytes.len())?;
+    let mut image = vec![0u8; size];
+    let headers = ::std::cmp::min(::std::cmp::min(size_of_headers, bytes.len()), image.len());
+    image[..headers].copy_from_slice(&bytes[..headers]);
+    for section in sections {
+        let start = section.pointer_to_raw_data as usize;
+        if start >= bytes.len() { continue }
+        let size = ::std::cmp::min(mapped_raw_size(section, section_alignment), bytes.len() - start);
+        let rva = section.virtual_address as usize;
+        debug!("mapping {} {:#x}..{:#x} to {:#x}", section.name().unwrap_or(""), start, start + size, rva);
+        image[rva..rva + size].copy_from_slice(&bytes[start..start + size]);
+    }
+    Ok(image)
+}
+
+/// Restores the file layout of `image`, a mapped image such as a memory dump, by copying each section's raw data back to
+/// its `pointer_to_raw_data`. Data which is never mapped, e.g. the certificate table, cannot be recovered and is left out.
+pub fn unmap(image: &[u8], header: &header::Header, sections: &[section_table::SectionTable]) -> error::Result<Vec<u8>> {
+    let (section_alignment, file_alignment, size_of_headers, _) = alignments(header)?;
+    let end = sections.iter().filter(|section| section.pointer_to_raw_data != 0).map(|section| {
+        section.pointer_to_raw_data as usize + section.size_of_raw_data as usize
+    }).max().unwrap_or(0);
+    let size = align_up(::std::cmp::max(size_of_headers, end), file_alignment);
+    check_size(size, image.len())?;
+    let mut bytes = vec![0u8; size];
+    let headers = ::std::cmp::min(::std::cmp::min(size_of_headers, image.len()), bytes.len());
+    bytes[..headers].copy_from_slice(&image[..headers]);
+    for section in sections {
+        let rva = section.virtual_address as usize;
+        if section.pointer_to_raw_data == 0 || rva >= image.len() { continue }
+        let size = ::std::cmp::min(mapped_raw_size(section, section_alignment), image.len() - rva);
+        let start = section.pointer_to_raw_data as usize;
+        debug!("unmapping {} {:#x}..{:#x} to {:#x}", section.name().unwrap_or(""), rva, rva + size, start);
+        bytes[start..start + size].copy_from_slice(&image[rva..rva + size]);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pe::PE;
+    use pe::tests::tiny_pe;
+
+    #[test]
+    fn map_and_unmap() {
+        let mut bytes = tiny_pe();
+        bytes[0x3ff] = 0xcc;
+        let mut header = header::Header::parse(&bytes).unwrap();
+        header.optional_header.as_mut().unwrap().windows_fields.size_of_image = 0x3000;
+        let text = PE::parse(&bytes).unwrap().sections[0];
+        let mut bss = section_table::SectionTable::default();
+        bss.virtual_address = 0x2000;
+        bss.virtual_size = 0x800;
+        let sections = [text, bss];
+        let image = map(&bytes, &header, &sections).unwrap();
+        assert_eq!(image.len(), 0x3000);
+        assert_eq!(image[0], b'M');
+        assert_eq!(image[0x1000], 0xc3);
+        assert_eq!(image[0x11ff], 0xcc);
+        assert_eq!(unmap(&image, &header, &sections).unwrap(), bytes);
+    }
+
+    #[test]
+    fn implausible_size_of_image() {
+        let bytes = tiny_pe();
+        let mut header = header::Header::parse(&bytes).unwrap();
+        header.optional_header.as_mut().unwrap().windows_fields.size_of_image = 0xffff_f000;
+        assert!(map(&bytes, &header, &[]).is_err());
+    }
+}
@@ -11,19 +11,37 @@ pub mod data_directories;
 pub mod export;
 pub mod import;
 pub mod debug;
+pub mod tls;
+pub mod load_config;
+pub mod certificate;
+pub mod clr;
+pub mod coff;
+pub mod exception;
+pub mod writer;
+pub mod mapping;
+pub mod options;
+pub mod bound_import;
+pub mod hash;
+pub mod layout;
 mod utils;
 
 use error;
 use container;
+use strtab;
 
 #[derive(Debug)]
 /// An analyzed PE32/PE32+ binary
 pub struct PE<'a> {
     /// The PE header
     pub header: header::Header,
+    /// The Rich header in the DOS stub, if the binary was linked by a Microsoft linker
+    pub rich_header: Option<header::RichHeader>,
     /// A list of the sections in this PE binary
     pub sections: Vec<section_table::SectionTable>,
-    /// The size of the binary
+    /// The COFF string table, holding the long section names of e.g. MinGW binaries, if the binary has a symbol table
+    pub strings: strtab::Strtab<'a>,
+    /// The size of the image data in the binary: the end of its headers, sections, certificate table, debug data or
+    /// symbol table, whichever is last; anything past it is the overlay
     pub size: usize,
     /// The name of this `dll`, if it has one
     pub name: Option<&'a str>,
@@ -45,23 +63,42 @@ pub struct PE<'a> {
     pub imports: Vec<import::Import<'a>>,
     /// The list of libraries which this binary imports symbols from
     pub libraries: Vec<&'a str>,
+    /// The modules this binary's imports were bound against, if it is bound and the bound import directory can be read
+    pub bound_imports: bound_import::BoundImportDirectory<'a>,
     /// Debug information, if any, contained in the PE header
-    pub debug_data: Option<debug::DebugData<'a>>
+    pub debug_data: Option<debug::DebugData<'a>>,
+    /// The TLS directory and its callbacks, if any and it can be read
+    pub tls_data: Option<tls::TlsData<'a>>,
+    /// The load configuration directory, if any and it can be read
+    pub load_config_data: Option<load_config::LoadConfigData<'a>>,
+    /// The attribute certificates, e.g., Authenticode signatures, appended to this binary, if it has a certificate
+    /// table which can be read; truncated or re-stripped signed binaries often point past the end of the file
+    pub certificates: Option<certificate::CertificateDirectoryTable<'a>>,
+    /// The CLI header and metadata, if this is a managed (.NET) binary and they can be parsed
+    pub clr_data: Option<clr::ClrData<'a>>,
 }
 
 impl<'a> PE<'a> {
     /// Reads a PE binary from the underlying `bytes`
     pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        Self::parse_with_opts(bytes, &options::ParseOptions::default())
+    }
+    /// Reads a PE binary from the underlying `bytes` with the given parsing options, e.g.
+    /// [`ParseOptions::mapped`](options/struct.ParseOptions.html#method.mapped) for an image captured from memory
+    pub fn parse_with_opts(bytes: &'a [u8], opts: &options::ParseOptions) -> error::Result<Self> {
         let header = header::Header::parse(bytes)?;
         debug!("{:#?}", header);
-        let offset = &mut (header.dos_header.pe_pointer as usize + header::SIZEOF_COFF_HEADER + header.coff_header.size_of_optional_header as usize);
-        let nsections = header.coff_header.number_of_sections as usize;
-        let mut sections = Vec::with_capacity(nsections);
-        for i in 0..nsections {
-            let section = section_table::SectionTable::parse(bytes, offset)?;
-            debug!("({}) {:#?}", i, section);
-            sections.push(section);
-        }
+        let rich_header = header::RichHeader::parse(bytes, &header.dos_header)?;
+        debug!("{:#?}", rich_header);
+        let sections = parse_sections(bytes, &header)?;
+        // in a mapped image every section's data is at its RVA, so resolving through sections which say so is the identity
+        let rva_sections = if opts.resolve_rva { sections.clone() } else { mapped_sections(&sections) };
+        // the symbol table is never mapped, and images often carry a stale pointer to it, so a bad one is ignored
+        let strings = if header.coff_header.pointer_to_symbol_table != 0 && opts.resolve_rva {
+            coff::parse_string_table(bytes, header.coff_header.pointer_to_symbol_table as usize, header.coff_header.number_of_symbol_table as usize, false).unwrap_or_default()
+        } else {
+            strtab::Strtab::default()
+        };
         let is_lib = characteristic::is_dll(header.coff_header.characteristics);
         let mut entry = 0;
         let mut image_base = 0;
@@ -71,7 +108,12 @@ impl<'a> PE<'a> {
         let mut imports = vec![];
         let mut import_data = None;
         let mut libraries = vec![];
+        let mut bound_imports = vec![];
         let mut debug_data = None;
+        let mut tls_data = None;
+        let mut load_config_data = None;
+        let mut certificates = None;
+        let mut clr_data = None;
         let mut is_64 = false;
         if let Some(optional_header) = header.optional_header {
             entry = optional_header.standard_fields.address_of_entry_point as usize;
@@ -79,32 +121,71 @@ impl<'a> PE<'a> {
             is_64 = optional_header.container()? == container::Container::Big;
             debug!("entry {:#x} image_base {:#x} is_64: {}", entry, image_base, is_64);
             if let &Some(export_table) = optional_header.data_directories.get_export_table() {
-                let ed = export::ExportData::parse(bytes, &export_table, &sections)?;
+                let ed = export::ExportData::parse(bytes, &export_table, &rva_sections)?;
                 debug!("export data {:#?}", ed);
-                exports = export::Export::parse(bytes, &ed, &sections)?;
+                let mut functions = vec![];
+                if header.coff_header.machine == header::COFF_MACHINE_X86_64 {
+                    if let &Some(exception_table) = optional_header.data_directories.get_exception_table() {
+                        // the function table only refines export sizes, so a malformed one is ignored
+                        functions = exception::parse(bytes, &exception_table, &rva_sections).unwrap_or_default();
+                        functions.sort_by_key(|function| function.begin_address);
+                    }
+                }
+                exports = export::Export::parse_with_functions(bytes, &ed, &rva_sections, &functions)?;
                 name = Some(ed.name);
                 debug!("name: {}", ed.name);
                 export_data = Some(ed);
             }
             debug!("exports: {:#?}", exports);
             if let &Some(import_table) = optional_header.data_directories.get_import_table() {
-                let id = import::ImportData::parse(bytes, &import_table, &sections)?;
+                let id = import::ImportData::parse(bytes, &import_table, is_64, &rva_sections)?;
                 debug!("import data {:#?}", id);
-                imports = import::Import::parse(bytes, &id, &sections)?;
+                imports = import::Import::parse(bytes, &id, is_64, &rva_sections)?;
                 libraries = id.import_data.iter().map( | data | { data.name }).collect::<Vec<&'a str>>();
                 libraries.sort();
                 libraries.dedup();
                 import_data = Some(id);
             }
             debug!("imports: {:#?}", imports);
+            if let &Some(bound_import_table) = optional_header.data_directories.get_bound_import_table() {
+                // binding is only an optimization, and stale or patched binaries commonly carry a corrupt directory
+                bound_imports = bound_import::parse(bytes, &bound_import_table, &rva_sections).unwrap_or_default();
+            }
             if let &Some(debug_table) = optional_header.data_directories.get_debug_table() {
-                debug_data = Some(debug::DebugData::parse(bytes, &debug_table, &sections)?);
+                debug_data = Some(debug::DebugData::parse_with_opts(bytes, &debug_table, &rva_sections, opts)?);
+            }
+            let image_base = optional_header.windows_fields.image_base;
+            if let &Some(tls_table) = optional_header.data_directories.get_tls_table() {
+                // packers commonly leave TLS and load config directories which cannot be read
+                tls_data = tls::TlsData::parse(bytes, image_base, is_64, &tls_table, &rva_sections).ok();
+                debug!("tls data: {:#?}", tls_data);
+            }
+            if let &Some(load_config_table) = optional_header.data_directories.get_load_config_table() {
+                load_config_data = load_config::LoadConfigData::parse(bytes, image_base, is_64, &load_config_table, &rva_sections).ok();
+                debug!("load config data: {:#?}", load_config_data);
+            }
+            // the certificate table is never mapped
+            if let (&Some(certificate_table), true) = (optional_header.data_directories.get_certificate_table(), opts.resolve_rva) {
+                certificates = certificate::parse(bytes, &certificate_table).ok();
+            }
+            if let &Some(clr_runtime_header) = optional_header.data_directories.get_clr_runtime_header() {
+                // obfuscated or damaged metadata must not make the rest of the image unreadable
+                clr_data = clr::ClrData::parse(bytes, &clr_runtime_header, &rva_sections).ok();
+                debug!("clr data: {:#?}", clr_data);
             }
         }
+        let size = if opts.resolve_rva {
+            layout::end_of_image(&layout::data_regions(bytes, &header, &sections, &strings, debug_data.as_ref()))
+        } else {
+            let size_of_image = header.optional_header.map_or(bytes.len(), |optional_header| optional_header.windows_fields.size_of_image as usize);
+            ::std::cmp::min(size_of_image, bytes.len())
+        };
         Ok( PE {
             header: header,
+            rich_header: rich_header,
             sections: sections,
-            size: 0,
+            strings: strings,
+            size: size,
             name: name,
             is_lib: is_lib,
             is_64: is_64,
@@ -115,7 +196,248 @@ impl<'a> PE<'a> {
             exports: exports,
             imports: imports,
             libraries: libraries,
+            bound_imports: bound_imports,
             debug_data: debug_data,
+            tls_data: tls_data,
+            load_config_data: load_config_data,
+            certificates: certificates,
+            clr_data: clr_data,
         })
     }
+    /// Summarizes the exploit mitigations this binary opts into from its DllCharacteristics and load config directory
+    pub fn security_features(&self) -> characteristic::SecurityFeatures {
+        let dll_characteristics = match self.header.optional_header {
+            Some(ref optional_header) => optional_header.windows_fields.dll_characteristics,
+            None => 0,
+        };
+        let has = |flag: u16| dll_characteristics & flag == flag;
+        let aslr = has(characteristic::IMAGE_DLLCHARACTERISTICS_DYNAMIC_BASE) && !characteristic::is_relocs_stripped(self.header.coff_header.characteristics);
+        characteristic::SecurityFeatures {
+            aslr: aslr,
+            high_entropy_va: aslr && self.is_64 && has(characteristic::IMAGE_DLLCHARACTERISTICS_HIGH_ENTROPY_VA),
+            nx: has(characteristic::IMAGE_DLLCHARACTERISTICS_NX_COMPAT),
+            cfg: has(characteristic::IMAGE_DLLCHARACTERISTICS_GUARD_CF),
+            no_seh: has(characteristic::IMAGE_DLLCHARACTERISTICS_NO_SEH),
+            safe_seh: self.load_config_data.as_ref().map_or(false, |load_config| load_config.se_handler_table.is_some()),
+            force_integrity: has(characteristic::IMAGE_DLLCHARACTERISTICS_FORCE_INTEGRITY),
+            app_container: has(characteristic::IMAGE_DLLCHARACTERISTICS_APPCONTAINER),
+            cet_compat: self.debug_data.as_ref().and_then(|debug_data| debug_data.ex_dll_characteristics()).map_or(false, |flags| flags & debug::IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT != 0),
+        }
+    }
+    /// Returns the section whose virtual range contains `rva`
+    pub fn section_for_rva(&self, rva: u32) -> Option<&section_table::SectionTable> {
+        self.sections.iter().find(|section| section.contains_rva(rva))
+    }
+    /// Returns the first section named `name`, resolving long section names through the COFF string table
+    pub fn section_by_name(&self, name: &str) -> Option<&section_table::SectionTable> {
+        self.sections.iter().find(|section| section.full_name(&self.strings).ok() == Some(name))
+    }
+    /// Computes this binary's imphash, the MD5 of its normalized imports; see the [`hash`](hash/index.html) module
+    pub fn imphash(&self) -> String {
+        hash::imphash(&self.imports)
+    }
+    /// Computes this binary's export hash, the SHA-256 of its named exports; see the [`hash`](hash/index.html) module
+    pub fn exphash(&self) -> String {
+        hash::exphash(&self.exports)
+    }
+    /// Finds the export with the biased `ordinal`, e.g., to resolve an `ORDINAL n` import from another binary
+    pub fn export_by_ordinal(&self, ordinal: u32) -> Option<&export::Export<'a>> {
+        self.exports.iter().find(|export| export.ordinal == ordinal)
+    }
+    /// Returns the file ranges of `bytes`, the binary this `PE` was parsed from, which make up its Authenticode digest
+    pub fn authenticode_ranges(&self, bytes: &[u8]) -> error::Result<Vec<::core::ops::Range<usize>>> {
+        certificate::authenticode_ranges(bytes, &self.header, &self.sections)
+    }
+    /// Computes the Authenticode digest of `bytes`, the binary this `PE` was parsed from, by feeding every hashed range to `update`
+    pub fn authenticode_digest<F: FnMut(&[u8])>(&self, bytes: &[u8], update: F) -> error::Result<()> {
+        certificate::authenticode_digest(bytes, &self.header, &self.sections, update)
+    }
+    /// Returns the data appended to `bytes`, the binary this `PE` was parsed from, past the end of its image data, if any
+    pub fn overlay<'b>(&self, bytes: &'b [u8]) -> Option<&'b [u8]> {
+        if self.size < bytes.len() { Some(&bytes[self.size..]) } else { None }
+    }
+    /// Names every byte range of `bytes`, the binary this `PE` was parsed from; see the [`layout`](layout/index.html) module
+    pub fn layout(&self, bytes: &[u8]) -> Vec<layout::Region> {
+        layout::layout(bytes, &self.header, &self.sections, &self.strings, self.debug_data.as_ref())
+    }
+    /// Returns `bytes`, the binary this `PE` was parsed from, laid out as the loader maps it into memory
+    pub fn map_image(&self, bytes: &[u8]) -> error::Result<Vec<u8>> {
+        mapping::map(bytes, &self.header, &self.sections)
+    }
+    /// Converts `image`, a mapped image such as a module dumped from process memory, back into its file layout
+    pub fn unmap_image(image: &[u8]) -> error::Result<Vec<u8>> {
+        let header = header::Header::parse(image)?;
+        let sections = parse_sections(image, &header)?;
+        mapping::unmap(image, &header, &sections)
+    }
+    /// Computes the optional header `CheckSum` of `bytes`, the binary this `PE` was parsed from, or `None` if it has no optional header
+    pub fn compute_checksum(&self, bytes: &[u8]) -> Option<u32> {
+        certificate::checksum_offset(&self.header).map(|offset| certificate::compute_checksum(bytes, offset))
+    }
+}
+
+/// Parses the section table following the optional header
+fn parse_sections(bytes: &[u8], header: &header::Header) -> error::Result<Vec<section_table::SectionTable>> {
+    let offset = &mut (header.dos_header.pe_pointer as usize + header::SIZEOF_COFF_HEADER + header.coff_header.size_of_optional_header as usize);
+    let nsections = header.coff_header.number_of_sections as usize;
+    let mut sections = Vec::with_capacity(nsections);
+    for i in 0..nsections {
+        let section = section_table::SectionTable::parse(bytes, offset)?;
+        debug!("({}) {:#?}", i, section);
+        sections.push(section);
+    }
+    Ok(sections)
+}
+
+/// Copies `sections`, pointing each one's raw data at its RVA, as it is in a mapped image
+fn mapped_sections(sections: &[section_table::SectionTable]) -> Vec<section_table::SectionTable> {
+    sections.iter().map(|section| {
+        let mut section = *section;
+        section.pointer_to_raw_data = section.virtual_address;
+        section.size_of_raw_data = ::std::cmp::max(section.virtual_size, section.size_of_raw_data);
+        section
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use scroll::{self, Pwrite};
+    use super::*;
+
+    /// A minimal PE32 executable with an empty `.text` section
+    pub fn tiny_pe() -> Vec<u8> {
+        let mut bytes = vec![0u8; 0x400];
+        bytes.pwrite_with(header::DOS_MAGIC, 0, scroll::LE).unwrap();
+        bytes.pwrite_with(0x40u32, header::PE_POINTER_OFFSET as usize, scroll::LE).unwrap();
+        // the COFF header: machine, number of sections, size of the optional header and characteristics
+        bytes.pwrite_with(header::COFF_MAGIC, 0x40, scroll::LE).unwrap();
+        bytes.pwrite_with(header::COFF_MACHINE_X86, 0x44, scroll::LE).unwrap();
+        bytes.pwrite_with(1u16, 0x46, scroll::LE).unwrap();
+        bytes.pwrite_with((optional_header::SIZEOF_STANDARD_FIELDS_32 + optional_header::SIZEOF_WINDOWS_FIELDS_32 + 16 * data_directories::SIZEOF_DATA_DIRECTORY) as u16, 0x54, scroll::LE).unwrap();
+        bytes.pwrite_with(0x102u16, 0x56, scroll::LE).unwrap();
+        let mut standard_fields = optional_header::StandardFields32::default();
+        standard_fields.magic = optional_header::MAGIC_32;
+        standard_fields.address_of_entry_point = 0x1000;
+        bytes.pwrite_with(standard_fields, 0x58, scroll::LE).unwrap();
+        let mut windows_fields = optional_header::WindowsFields32::default();
+        windows_fields.image_base = 0x400000;
+        windows_fields.section_alignment = 0x1000;
+        windows_fields.file_alignment = 0x200;
+        windows_fields.size_of_image = 0x2000;
+        windows_fields.size_of_headers = 0x200;
+        windows_fields.subsystem = optional_header::IMAGE_SUBSYSTEM_WINDOWS_CUI;
+        windows_fields.number_of_rva_and_sizes = 16;
+        bytes.pwrite_with(windows_fields, 0x74, scroll::LE).unwrap();
+        // the section table: `.text` at 0x1000, with its code at 0x200
+        bytes[0x138..0x13d].copy_from_slice(b".text");
+        bytes.pwrite_with(0x10u32, 0x140, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1000u32, 0x144, scroll::LE).unwrap();
+        bytes.pwrite_with(0x200u32, 0x148, scroll::LE).unwrap();
+        bytes.pwrite_with(0x200u32, 0x14c, scroll::LE).unwrap();
+        bytes.pwrite_with(0x6000_0020u32, 0x15c, scroll::LE).unwrap();
+        bytes[0x200] = 0xc3;
+        bytes
+    }
+
+    /// A section mapping RVA 0x1000 onwards to the start of the bytes, for parsing a directory on its own
+    pub fn directory_section(size: u32) -> section_table::SectionTable {
+        let mut section = section_table::SectionTable::default();
+        section.virtual_address = 0x1000;
+        section.virtual_size = size;
+        section.size_of_raw_data = size;
+        section
+    }
+
+    /// The offset of the data directory entry `index` in `tiny_pe`
+    pub fn data_directory_offset(index: usize) -> usize {
+        0xb8 + index * data_directories::SIZEOF_DATA_DIRECTORY
+    }
+
+    #[test]
+    fn unreadable_tls_and_load_config() {
+        let mut bytes = tiny_pe();
+        // the TLS and load config directories are the 10th and 11th data directories; neither is in a section
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x5000, size: 0x18 }, data_directory_offset(9), scroll::LE).unwrap();
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x6000, size: 0x48 }, data_directory_offset(10), scroll::LE).unwrap();
+        let pe = PE::parse(&bytes).unwrap();
+        assert_eq!(pe.tls_data, None);
+        assert_eq!(pe.load_config_data, None);
+        assert_eq!(pe.sections.len(), 1);
+    }
+
+    #[test]
+    fn truncated_certificate_table() {
+        let mut bytes = tiny_pe();
+        let entry = data_directory_offset(4);
+        let end = bytes.len() as u32;
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: end - 8, size: 0x100 }, entry, scroll::LE).unwrap();
+        let pe = PE::parse(&bytes).unwrap();
+        assert_eq!(pe.certificates, None);
+        assert_eq!(pe.sections.len(), 1);
+    }
+
+    #[test]
+    fn malformed_clr_metadata() {
+        let mut bytes = tiny_pe();
+        // the CLR runtime header is the 15th data directory; this one has no metadata
+        let entry = data_directory_offset(14);
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x1000, size: 0x48 }, entry, scroll::LE).unwrap();
+        let pe = PE::parse(&bytes).unwrap();
+        assert!(pe.header.optional_header.unwrap().data_directories.get_clr_runtime_header().is_some());
+        assert_eq!(pe.clr_data, None);
+    }
+
+    #[test]
+    fn malformed_exception_table() {
+        let mut bytes = tiny_pe();
+        bytes.pwrite_with(header::COFF_MACHINE_X86_64, 0x44, scroll::LE).unwrap();
+        // grow .text to hold an export directory exporting its first byte
+        bytes.pwrite_with(0x100u32, 0x140, scroll::LE).unwrap();
+        let export_directory = export::ExportDirectoryTable {
+            name_rva: 0x1080,
+            ordinal_base: 1,
+            address_table_entries: 1,
+            export_address_table_rva: 0x1060,
+            name_pointer_rva: 0x1070,
+            ordinal_table_rva: 0x1070,
+            ..Default::default()
+        };
+        bytes.pwrite_with(export_directory, 0x220, scroll::LE).unwrap();
+        bytes.pwrite_with(0x1000u32, 0x260, scroll::LE).unwrap();
+        bytes.pwrite_with(&b"tiny.dll\0"[..], 0x280, ()).unwrap();
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x1020, size: 0x70 }, data_directory_offset(0), scroll::LE).unwrap();
+        // the exception table is outside of any section
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x5000, size: 0x30 }, data_directory_offset(3), scroll::LE).unwrap();
+        let pe = PE::parse(&bytes).unwrap();
+        assert_eq!(pe.name, Some("tiny.dll"));
+        assert_eq!(pe.exports.len(), 1);
+        assert_eq!(pe.exports[0].rva, 0x1000);
+        assert_eq!(pe.exports[0].size, 0x100);
+    }
+
+    #[test]
+    fn parse_mapped_image() {
+        use pe::writer::{Writer, ImportSymbol};
+        let bytes = tiny_pe();
+        let pe = PE::parse(&bytes).unwrap();
+        let mut writer = Writer::new(&pe, &bytes).unwrap();
+        writer.add_import("hook.dll", &[ImportSymbol::Name(0, "Install")]).unwrap();
+        let bytes = writer.write().unwrap();
+        let image = PE::parse(&bytes).unwrap().map_image(&bytes).unwrap();
+        let pe = PE::parse_with_opts(&image, &options::ParseOptions::mapped()).unwrap();
+        assert_eq!(pe.libraries, vec!["hook.dll"]);
+        assert_eq!(pe.imports[0].name, "Install");
+        assert_eq!(pe.sections[1].pointer_to_raw_data, 0x400);
+        assert_eq!(PE::unmap_image(&image).unwrap(), bytes);
+    }
+
+    #[test]
+    fn unreadable_bound_imports() {
+        let mut bytes = tiny_pe();
+        // the bound import directory is the 12th data directory; this one is not in a section
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x5000, size: 0x10 }, data_directory_offset(11), scroll::LE).unwrap();
+        let pe = PE::parse(&bytes).unwrap();
+        assert!(pe.bound_imports.is_empty());
+        assert_eq!(pe.sections.len(), 1);
+    }
 }
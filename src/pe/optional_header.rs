@@ -3,7 +3,7 @@ use error;
 
 use pe::data_directories;
 
-use scroll::{ctx, Endian, LE, Pread};
+use scroll::{ctx, Endian, LE, Pread, Pwrite};
 
 /// standard COFF fields
 #[repr(C)]
@@ -88,6 +88,50 @@ impl From<StandardFields64> for StandardFields {
     }
 }
 
+impl StandardFields {
+    /// The size of these fields in the variant their `magic` selects
+    pub fn size(&self) -> usize {
+        if self.magic == MAGIC_64 { SIZEOF_STANDARD_FIELDS_64 } else { SIZEOF_STANDARD_FIELDS_32 }
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for StandardFields {
+    type Error = ::error::Error;
+    type Size = usize;
+    fn try_into_ctx(self, bytes: &mut [u8], le: Endian) -> error::Result<Self::Size> {
+        match self.magic {
+            MAGIC_32 => {
+                let fields = StandardFields32 {
+                    magic: self.magic,
+                    major_linker_version: self.major_linker_version,
+                    minor_linker_version: self.minor_linker_version,
+                    size_of_code: narrow(self.size_of_code, "size_of_code")?,
+                    size_of_initialized_data: narrow(self.size_of_initialized_data, "size_of_initialized_data")?,
+                    size_of_uninitialized_data: narrow(self.size_of_uninitialized_data, "size_of_uninitialized_data")?,
+                    address_of_entry_point: narrow(self.address_of_entry_point, "address_of_entry_point")?,
+                    base_of_code: narrow(self.base_of_code, "base_of_code")?,
+                    base_of_data: self.base_of_data,
+                };
+                Ok(bytes.pwrite_with(fields, 0, le)?)
+            },
+            MAGIC_64 => {
+                let fields = StandardFields64 {
+                    magic: self.magic,
+                    major_linker_version: self.major_linker_version,
+                    minor_linker_version: self.minor_linker_version,
+                    size_of_code: narrow(self.size_of_code, "size_of_code")?,
+                    size_of_initialized_data: narrow(self.size_of_initialized_data, "size_of_initialized_data")?,
+                    size_of_uninitialized_data: narrow(self.size_of_uninitialized_data, "size_of_uninitialized_data")?,
+                    address_of_entry_point: narrow(self.address_of_entry_point, "address_of_entry_point")?,
+                    base_of_code: narrow(self.base_of_code, "base_of_code")?,
+                };
+                Ok(bytes.pwrite_with(fields, 0, le)?)
+            },
+            magic => Err(error::Error::BadMagic(magic as u64)),
+        }
+    }
+}
+
 /// Standard fields magic number for 32-bit binary
 pub const MAGIC_32: u16 = 0x10b;
 /// Standard fields magic number for 64-bit binary
@@ -153,35 +197,95 @@ pub struct WindowsFields64 {
 
 pub const SIZEOF_WINDOWS_FIELDS_64: usize = 88;
 
-// /// Generic 32/64-bit Windows specific fields
-// #[derive(Debug, PartialEq, Copy, Clone, Default)]
-// pub struct WindowsFields {
-//     pub image_base: u64,
-//     pub section_alignment: u32,
-//     pub file_alignment: u32,
-//     pub major_operating_system_version: u16,
-//     pub minor_operating_system_version: u16,
-//     pub major_image_version: u16,
-//     pub minor_image_version: u16,
-//     pub major_subsystem_version: u16,
-//     pub minor_subsystem_version: u16,
-//     pub win32_version_value: u32,
-//     pub size_of_image: u32,
-//     pub size_of_headers: u32,
-//     pub check_sum: u32,
-//     pub subsystem: u16,
-//     pub dll_characteristics: u16,
-//     pub size_of_stack_reserve: u64,
-//     pub size_of_stack_commit:  u64,
-//     pub size_of_heap_reserve:  u64,
-//     pub size_of_heap_commit:   u64,
-//     pub loader_flags: u32,
-//     pub number_of_rva_and_sizes: u32,
-// }
+/// An unknown subsystem
+pub const IMAGE_SUBSYSTEM_UNKNOWN: u16 = 0;
+/// Device drivers and native Windows processes
+pub const IMAGE_SUBSYSTEM_NATIVE: u16 = 1;
+/// The Windows graphical user interface (GUI) subsystem
+pub const IMAGE_SUBSYSTEM_WINDOWS_GUI: u16 = 2;
+/// The Windows character subsystem, i.e., a console application
+pub const IMAGE_SUBSYSTEM_WINDOWS_CUI: u16 = 3;
+/// The OS/2 character subsystem
+pub const IMAGE_SUBSYSTEM_OS2_CUI: u16 = 5;
+/// The Posix character subsystem
+pub const IMAGE_SUBSYSTEM_POSIX_CUI: u16 = 7;
+/// Native Win9x driver
+pub const IMAGE_SUBSYSTEM_NATIVE_WINDOWS: u16 = 8;
+/// Windows CE
+pub const IMAGE_SUBSYSTEM_WINDOWS_CE_GUI: u16 = 9;
+/// An Extensible Firmware Interface (EFI) application
+pub const IMAGE_SUBSYSTEM_EFI_APPLICATION: u16 = 10;
+/// An EFI driver with boot services
+pub const IMAGE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER: u16 = 11;
+/// An EFI driver with run-time services
+pub const IMAGE_SUBSYSTEM_EFI_RUNTIME_DRIVER: u16 = 12;
+/// An EFI ROM image
+pub const IMAGE_SUBSYSTEM_EFI_ROM: u16 = 13;
+/// XBOX
+pub const IMAGE_SUBSYSTEM_XBOX: u16 = 14;
+/// Windows boot application
+pub const IMAGE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION: u16 = 16;
+
+/// Returns the name of the Windows `subsystem`, e.g. `"WINDOWS_GUI"`
+pub fn subsystem_to_str(subsystem: u16) -> &'static str {
+    match subsystem {
+        IMAGE_SUBSYSTEM_UNKNOWN => "UNKNOWN",
+        IMAGE_SUBSYSTEM_NATIVE => "NATIVE",
+        IMAGE_SUBSYSTEM_WINDOWS_GUI => "WINDOWS_GUI",
+        IMAGE_SUBSYSTEM_WINDOWS_CUI => "WINDOWS_CUI",
+        IMAGE_SUBSYSTEM_OS2_CUI => "OS2_CUI",
+        IMAGE_SUBSYSTEM_POSIX_CUI => "POSIX_CUI",
+        IMAGE_SUBSYSTEM_NATIVE_WINDOWS => "NATIVE_WINDOWS",
+        IMAGE_SUBSYSTEM_WINDOWS_CE_GUI => "WINDOWS_CE_GUI",
+        IMAGE_SUBSYSTEM_EFI_APPLICATION => "EFI_APPLICATION",
+        IMAGE_SUBSYSTEM_EFI_BOOT_SERVICE_DRIVER => "EFI_BOOT_SERVICE_DRIVER",
+        IMAGE_SUBSYSTEM_EFI_RUNTIME_DRIVER => "EFI_RUNTIME_DRIVER",
+        IMAGE_SUBSYSTEM_EFI_ROM => "EFI_ROM",
+        IMAGE_SUBSYSTEM_XBOX => "XBOX",
+        IMAGE_SUBSYSTEM_WINDOWS_BOOT_APPLICATION => "WINDOWS_BOOT_APPLICATION",
+        _ => "UNKNOWN_SUBSYSTEM",
+    }
+}
+
+/// Unified 32/64-bit Windows specific fields
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct WindowsFields {
+    /// Which of `WindowsFields32` (`Little`) or `WindowsFields64` (`Big`) these fields were read from, and are written as
+    pub container: container::Container,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub major_operating_system_version: u16,
+    pub minor_operating_system_version: u16,
+    pub major_image_version: u16,
+    pub minor_image_version: u16,
+    pub major_subsystem_version: u16,
+    pub minor_subsystem_version: u16,
+    pub win32_version_value: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub check_sum: u32,
+    pub subsystem: u16,
+    pub dll_characteristics: u16,
+    pub size_of_stack_reserve: u64,
+    pub size_of_stack_commit:  u64,
+    pub size_of_heap_reserve:  u64,
+    pub size_of_heap_commit:   u64,
+    pub loader_flags: u32,
+    pub number_of_rva_and_sizes: u32,
+}
+
+impl WindowsFields {
+    /// The size of these fields in their original variant
+    pub fn size(&self) -> usize {
+        if self.container.is_big() { SIZEOF_WINDOWS_FIELDS_64 } else { SIZEOF_WINDOWS_FIELDS_32 }
+    }
+}
 
 impl From<WindowsFields32> for WindowsFields {
     fn from(windows: WindowsFields32) -> Self {
         WindowsFields {
+            container: container::Container::Little,
             image_base: windows.image_base as u64,
             section_alignment: windows.section_alignment,
             file_alignment: windows.file_alignment,
@@ -207,35 +311,110 @@ impl From<WindowsFields32> for WindowsFields {
     }
 }
 
-// impl From<WindowsFields32> for WindowsFields {
-//     fn from(windows: WindowsFields32) -> Self {
-//         WindowsFields {
-//             image_base: windows.image_base,
-//             section_alignment: windows.section_alignment,
-//             file_alignment: windows.file_alignment,
-//             major_operating_system_version: windows.major_operating_system_version,
-//             minor_operating_system_version: windows.minor_operating_system_version,
-//             major_image_version: windows.major_image_version,
-//             minor_image_version: windows.minor_image_version,
-//             major_subsystem_version: windows.major_subsystem_version,
-//             minor_subsystem_version: windows.minor_subsystem_version,
-//             win32_version_value: windows.win32_version_value,
-//             size_of_image: windows.size_of_image,
-//             size_of_headers: windows.size_of_headers,
-//             check_sum: windows.check_sum,
-//             subsystem: windows.subsystem,
-//             dll_characteristics: windows.dll_characteristics,
-//             size_of_stack_reserve: windows.size_of_stack_reserve,
-//             size_of_stack_commit: windows.size_of_stack_commit,
-//             size_of_heap_reserve: windows.size_of_heap_reserve,
-//             size_of_heap_commit: windows.size_of_heap_commit,
-//             loader_flags: windows.loader_flags,
-//             number_of_rva_and_sizes: windows.number_of_rva_and_sizes,
-//         }
-//     }
-// }
+impl From<WindowsFields64> for WindowsFields {
+    fn from(windows: WindowsFields64) -> Self {
+        WindowsFields {
+            container: container::Container::Big,
+            image_base: windows.image_base,
+            section_alignment: windows.section_alignment,
+            file_alignment: windows.file_alignment,
+            major_operating_system_version: windows.major_operating_system_version,
+            minor_operating_system_version: windows.minor_operating_system_version,
+            major_image_version: windows.major_image_version,
+            minor_image_version: windows.minor_image_version,
+            major_subsystem_version: windows.major_subsystem_version,
+            minor_subsystem_version: windows.minor_subsystem_version,
+            win32_version_value: windows.win32_version_value,
+            size_of_image: windows.size_of_image,
+            size_of_headers: windows.size_of_headers,
+            check_sum: windows.check_sum,
+            subsystem: windows.subsystem,
+            dll_characteristics: windows.dll_characteristics,
+            size_of_stack_reserve: windows.size_of_stack_reserve,
+            size_of_stack_commit: windows.size_of_stack_commit,
+            size_of_heap_reserve: windows.size_of_heap_reserve,
+            size_of_heap_commit: windows.size_of_heap_commit,
+            loader_flags: windows.loader_flags,
+            number_of_rva_and_sizes: windows.number_of_rva_and_sizes,
+        }
+    }
+}
+
+impl From<WindowsFields> for WindowsFields64 {
+    fn from(windows: WindowsFields) -> Self {
+        WindowsFields64 {
+            image_base: windows.image_base,
+            section_alignment: windows.section_alignment,
+            file_alignment: windows.file_alignment,
+            major_operating_system_version: windows.major_operating_system_version,
+            minor_operating_system_version: windows.minor_operating_system_version,
+            major_image_version: windows.major_image_version,
+            minor_image_version: windows.minor_image_version,
+            major_subsystem_version: windows.major_subsystem_version,
+            minor_subsystem_version: windows.minor_subsystem_version,
+            win32_version_value: windows.win32_version_value,
+            size_of_image: windows.size_of_image,
+            size_of_headers: windows.size_of_headers,
+            check_sum: windows.check_sum,
+            subsystem: windows.subsystem,
+            dll_characteristics: windows.dll_characteristics,
+            size_of_stack_reserve: windows.size_of_stack_reserve,
+            size_of_stack_commit: windows.size_of_stack_commit,
+            size_of_heap_reserve: windows.size_of_heap_reserve,
+            size_of_heap_commit: windows.size_of_heap_commit,
+            loader_flags: windows.loader_flags,
+            number_of_rva_and_sizes: windows.number_of_rva_and_sizes,
+        }
+    }
+}
+
+/// Narrows a unified field back to its 32-bit width, failing if it does not fit
+fn narrow(value: u64, field: &str) -> error::Result<u32> {
+    if value > ::core::u32::MAX as u64 {
+        return Err(error::Error::Malformed(format!("{} ({:#x}) does not fit in 32-bit windows fields", field, value)));
+    }
+    Ok(value as u32)
+}
+
+impl WindowsFields32 {
+    /// Converts the unified `windows` fields back to their 32-bit variant, failing if a 64-bit value does not fit
+    pub fn try_from(windows: WindowsFields) -> error::Result<Self> {
+        Ok(WindowsFields32 {
+            image_base: narrow(windows.image_base, "image_base")?,
+            section_alignment: windows.section_alignment,
+            file_alignment: windows.file_alignment,
+            major_operating_system_version: windows.major_operating_system_version,
+            minor_operating_system_version: windows.minor_operating_system_version,
+            major_image_version: windows.major_image_version,
+            minor_image_version: windows.minor_image_version,
+            major_subsystem_version: windows.major_subsystem_version,
+            minor_subsystem_version: windows.minor_subsystem_version,
+            win32_version_value: windows.win32_version_value,
+            size_of_image: windows.size_of_image,
+            size_of_headers: windows.size_of_headers,
+            check_sum: windows.check_sum,
+            subsystem: windows.subsystem,
+            dll_characteristics: windows.dll_characteristics,
+            size_of_stack_reserve: narrow(windows.size_of_stack_reserve, "size_of_stack_reserve")?,
+            size_of_stack_commit: narrow(windows.size_of_stack_commit, "size_of_stack_commit")?,
+            size_of_heap_reserve: narrow(windows.size_of_heap_reserve, "size_of_heap_reserve")?,
+            size_of_heap_commit: narrow(windows.size_of_heap_commit, "size_of_heap_commit")?,
+            loader_flags: windows.loader_flags,
+            number_of_rva_and_sizes: windows.number_of_rva_and_sizes,
+        })
+    }
+}
 
-pub type WindowsFields = WindowsFields64;
+impl ctx::TryIntoCtx<Endian> for WindowsFields {
+    type Error = ::error::Error;
+    type Size = usize;
+    fn try_into_ctx(self, bytes: &mut [u8], le: Endian) -> error::Result<Self::Size> {
+        match self.container {
+            container::Container::Little => Ok(bytes.pwrite_with(WindowsFields32::try_from(self)?, 0, le)?),
+            container::Container::Big => Ok(bytes.pwrite_with(WindowsFields64::from(self), 0, le)?),
+        }
+    }
+}
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct OptionalHeader {
@@ -258,6 +437,10 @@ impl OptionalHeader {
             }
         }
     }
+    /// The number of bytes these fields and the `number_of_rva_and_sizes` data directories occupy
+    pub fn size(&self) -> usize {
+        self.standard_fields.size() + self.windows_fields.size() + self.windows_fields.number_of_rva_and_sizes as usize * data_directories::SIZEOF_DATA_DIRECTORY
+    }
 }
 
 impl<'a> ctx::TryFromCtx<'a, Endian> for OptionalHeader {
@@ -274,7 +457,7 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for OptionalHeader {
             },
             MAGIC_64 => {
                 let standard_fields = bytes.gread_with::<StandardFields64>(offset, LE)?.into();
-                let windows_fields = bytes.gread_with::<WindowsFields64>(offset, LE)?;
+                let windows_fields = bytes.gread_with::<WindowsFields64>(offset, LE)?.into();
                 (standard_fields, windows_fields)
             },
             _ => return Err(error::Error::BadMagic(magic as u64))
@@ -282,9 +465,30 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for OptionalHeader {
         let data_directories = data_directories::DataDirectories::parse(&bytes, windows_fields.number_of_rva_and_sizes as usize, offset)?;
         Ok ((OptionalHeader {
             standard_fields: standard_fields,
-            windows_fields: windows_fields, 
+            windows_fields: windows_fields,
             data_directories: data_directories,
-        }, 0)) // TODO: FIXME
+        }, *offset))
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for OptionalHeader {
+    type Error = ::error::Error;
+    type Size = usize;
+    fn try_into_ctx(self, bytes: &mut [u8], le: Endian) -> error::Result<Self::Size> {
+        if self.windows_fields.container != self.container()? {
+            return Err(error::Error::Malformed(format!("Standard fields magic {:#x} does not match {:?} windows fields", self.standard_fields.magic, self.windows_fields.container)));
+        }
+        let offset = &mut 0;
+        bytes.gwrite_with(self.standard_fields, offset, le)?;
+        bytes.gwrite_with(self.windows_fields, offset, le)?;
+        let count = self.windows_fields.number_of_rva_and_sizes as usize;
+        if count > self.data_directories.data_directories.len() {
+            return Err(error::Error::Malformed(format!("data directory count ({}) is greater than maximum number of data directories ({})", count, self.data_directories.data_directories.len())));
+        }
+        for dd in &self.data_directories.data_directories[..count] {
+            bytes.gwrite_with(dd.unwrap_or_default(), offset, le)?;
+        }
+        Ok(*offset)
     }
 }
 
@@ -307,4 +511,36 @@ mod tests {
     fn sizeof_windows64() {
         assert_eq!(::std::mem::size_of::<WindowsFields64>(), SIZEOF_WINDOWS_FIELDS_64);
     }
+    #[test]
+    fn round_trip32() {
+        let mut bytes = vec![0u8; SIZEOF_STANDARD_FIELDS_32 + SIZEOF_WINDOWS_FIELDS_32 + 2 * data_directories::SIZEOF_DATA_DIRECTORY];
+        let mut standard_fields = StandardFields32::default();
+        standard_fields.magic = MAGIC_32;
+        standard_fields.base_of_data = 0x2000;
+        bytes.pwrite_with(standard_fields, 0, LE).unwrap();
+        let mut windows_fields = WindowsFields32::default();
+        windows_fields.image_base = 0x400000;
+        windows_fields.size_of_stack_reserve = 0x100000;
+        windows_fields.number_of_rva_and_sizes = 2;
+        bytes.pwrite_with(windows_fields, SIZEOF_STANDARD_FIELDS_32, LE).unwrap();
+        bytes.pwrite_with(data_directories::DataDirectory { virtual_address: 0x3000, size: 0x28 }, SIZEOF_STANDARD_FIELDS_32 + SIZEOF_WINDOWS_FIELDS_32 + 8, LE).unwrap();
+        let (header, size) = <OptionalHeader as ctx::TryFromCtx<Endian>>::try_from_ctx(&bytes, LE).unwrap();
+        assert_eq!(size, bytes.len());
+        assert_eq!(header.size(), bytes.len());
+        assert_eq!(header.windows_fields.container, container::Container::Little);
+        assert_eq!(header.standard_fields.base_of_data, 0x2000);
+        let mut written = vec![0u8; bytes.len()];
+        assert_eq!(written.pwrite_with(header, 0, LE).unwrap(), bytes.len());
+        assert_eq!(written, bytes);
+    }
+    #[test]
+    fn narrow_windows32() {
+        let mut windows_fields: WindowsFields = WindowsFields32::default().into();
+        windows_fields.image_base = 0x1_4000_0000;
+        assert!(WindowsFields32::try_from(windows_fields).is_err());
+        windows_fields.container = container::Container::Big;
+        let mut bytes = [0u8; SIZEOF_WINDOWS_FIELDS_64];
+        assert_eq!(bytes.pwrite_with(windows_fields, 0, LE).unwrap(), SIZEOF_WINDOWS_FIELDS_64);
+        assert_eq!(bytes.pread_with::<WindowsFields64>(0, LE).unwrap().image_base, 0x1_4000_0000);
+    }
 }
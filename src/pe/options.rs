@@ -0,0 +1,21 @@
+/// Parsing options
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParseOptions {
+    /// Whether the parser should resolve RVAs into file offsets through the section table; disable this when parsing a
+    /// mapped image, e.g. a module dumped from process memory, whose RVAs are already offsets into the bytes
+    pub resolve_rva: bool,
+}
+
+impl ParseOptions {
+    /// Options for parsing an image captured from memory, i.e., in its mapped layout
+    pub fn mapped() -> Self {
+        ParseOptions { resolve_rva: false }
+    }
+}
+
+impl Default for ParseOptions {
+    /// Options for parsing a binary in its file layout
+    fn default() -> Self {
+        ParseOptions { resolve_rva: true }
+    }
+}
@@ -1,8 +1,10 @@
 use scroll::{self, Pread};
 use error;
+use strtab;
 
 #[repr(C)]
 #[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
 pub struct SectionTable {
     pub name: [u8; 8],
     pub virtual_size: u32,
@@ -18,6 +20,65 @@ pub struct SectionTable {
 
 pub const SIZEOF_SECTION_TABLE: usize = 8 * 5;
 
+/// The section should not be padded to the next boundary; obsolete, superseded by the `IMAGE_SCN_ALIGN_*` flags
+pub const IMAGE_SCN_TYPE_NO_PAD: u32 = 0x0000_0008;
+/// The section contains executable code
+pub const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+/// The section contains initialized data
+pub const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x0000_0040;
+/// The section contains uninitialized data
+pub const IMAGE_SCN_CNT_UNINITIALIZED_DATA: u32 = 0x0000_0080;
+pub const IMAGE_SCN_LNK_OTHER: u32 = 0x0000_0100;
+/// The section contains comments or other information, e.g. `.drectve`; object files only
+pub const IMAGE_SCN_LNK_INFO: u32 = 0x0000_0200;
+/// The section will not become part of the image; object files only
+pub const IMAGE_SCN_LNK_REMOVE: u32 = 0x0000_0800;
+/// The section contains COMDAT data; object files only
+pub const IMAGE_SCN_LNK_COMDAT: u32 = 0x0000_1000;
+/// The section contains data referenced through the global pointer
+pub const IMAGE_SCN_GPREL: u32 = 0x0000_8000;
+pub const IMAGE_SCN_MEM_PURGEABLE: u32 = 0x0002_0000;
+pub const IMAGE_SCN_MEM_16BIT: u32 = 0x0002_0000;
+pub const IMAGE_SCN_MEM_LOCKED: u32 = 0x0004_0000;
+pub const IMAGE_SCN_MEM_PRELOAD: u32 = 0x0008_0000;
+
+pub const IMAGE_SCN_ALIGN_1BYTES: u32 = 0x0010_0000;
+pub const IMAGE_SCN_ALIGN_2BYTES: u32 = 0x0020_0000;
+pub const IMAGE_SCN_ALIGN_4BYTES: u32 = 0x0030_0000;
+pub const IMAGE_SCN_ALIGN_8BYTES: u32 = 0x0040_0000;
+pub const IMAGE_SCN_ALIGN_16BYTES: u32 = 0x0050_0000;
+pub const IMAGE_SCN_ALIGN_32BYTES: u32 = 0x0060_0000;
+pub const IMAGE_SCN_ALIGN_64BYTES: u32 = 0x0070_0000;
+pub const IMAGE_SCN_ALIGN_128BYTES: u32 = 0x0080_0000;
+pub const IMAGE_SCN_ALIGN_256BYTES: u32 = 0x0090_0000;
+pub const IMAGE_SCN_ALIGN_512BYTES: u32 = 0x00a0_0000;
+pub const IMAGE_SCN_ALIGN_1024BYTES: u32 = 0x00b0_0000;
+pub const IMAGE_SCN_ALIGN_2048BYTES: u32 = 0x00c0_0000;
+pub const IMAGE_SCN_ALIGN_4096BYTES: u32 = 0x00d0_0000;
+pub const IMAGE_SCN_ALIGN_8192BYTES: u32 = 0x00e0_0000;
+/// Mask of the `IMAGE_SCN_ALIGN_*` values; object files only
+pub const IMAGE_SCN_ALIGN_MASK: u32 = 0x00f0_0000;
+
+/// The section contains more than 0xffff relocations; the real count is in the first relocation's `virtual_address`
+pub const IMAGE_SCN_LNK_NRELOC_OVFL: u32 = 0x0100_0000;
+/// The section can be discarded as needed, e.g. `.reloc`
+pub const IMAGE_SCN_MEM_DISCARDABLE: u32 = 0x0200_0000;
+/// The section cannot be cached
+pub const IMAGE_SCN_MEM_NOT_CACHED: u32 = 0x0400_0000;
+/// The section is not pageable
+pub const IMAGE_SCN_MEM_NOT_PAGED: u32 = 0x0800_0000;
+/// The section can be shared in memory
+pub const IMAGE_SCN_MEM_SHARED: u32 = 0x1000_0000;
+/// The section can be executed as code
+pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+/// The section can be read
+pub const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+/// The section can be written to
+pub const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+
+/// The characters of the base64 alphabet bigobj files use for `//` long section names
+const BASE64_ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
 impl SectionTable {
     pub fn parse(bytes: &[u8], offset: &mut usize) -> error::Result<Self> {
         let mut table = SectionTable::default();
@@ -40,4 +101,117 @@ impl SectionTable {
     pub fn name(&self) -> error::Result<&str> {
         Ok(self.name.pread(0)?)
     }
+    /// The offset of this section's name in the COFF string table, if it is a long name, i.e., `/123`, or `//BASE64` in bigobj files
+    pub fn name_offset(&self) -> error::Result<Option<usize>> {
+        if self.name[0] != b'/' {
+            return Ok(None)
+        }
+        let digits = self.name[1..].iter().take_while(|&&c| c != 0).cloned().collect::<Vec<u8>>();
+        let malformed = || error::Error::Malformed(format!("Invalid long section name {:?}", self.name));
+        if digits.first() == Some(&b'/') {
+            let mut offset = 0usize;
+            for c in &digits[1..] {
+                let value = BASE64_ALPHABET.iter().position(|b| b == c).ok_or_else(&malformed)?;
+                offset = offset * 64 + value;
+            }
+            Ok(Some(offset))
+        } else {
+            let digits = ::core::str::from_utf8(&digits).map_err(|_| malformed())?;
+            Ok(Some(digits.parse::<usize>().map_err(|_| malformed())?))
+        }
+    }
+    /// The name of this section, resolving long names through `strtab`, the COFF string table
+    pub fn full_name<'b>(&'b self, strtab: &strtab::Strtab<'b>) -> error::Result<&'b str> {
+        match self.name_offset()? {
+            Some(offset) => {
+                match strtab.get(offset) {
+                    Some(name) => name,
+                    None => Err(error::Error::Malformed(format!("Long section name offset {:#x} is out of bounds of the string table", offset))),
+                }
+            },
+            None => self.name(),
+        }
+    }
+    /// Returns this section's raw data in `bytes`, or `None` if it has none, e.g. `.bss`; in images, the raw data is
+    /// trimmed to the section's virtual size, dropping the file alignment padding
+    pub fn data<'a>(&self, bytes: &'a [u8]) -> error::Result<Option<&'a [u8]>> {
+        if self.pointer_to_raw_data == 0 || self.size_of_raw_data == 0 {
+            return Ok(None)
+        }
+        let size = if self.virtual_size != 0 && self.virtual_size < self.size_of_raw_data { self.virtual_size } else { self.size_of_raw_data };
+        Ok(Some(bytes.pread_with::<&[u8]>(self.pointer_to_raw_data as usize, size as usize)?))
+    }
+    /// Whether the virtual range of this section contains `rva`
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        let size = ::std::cmp::max(self.virtual_size, self.size_of_raw_data);
+        rva >= self.virtual_address && ((rva - self.virtual_address) as u64) < size as u64
+    }
+    /// The required alignment of this section's data, from its `IMAGE_SCN_ALIGN_*` flag; object files only
+    pub fn alignment(&self) -> Option<u32> {
+        match (self.characteristics & IMAGE_SCN_ALIGN_MASK) >> 20 {
+            0 | 0xf => None,
+            n => Some(1 << (n - 1)),
+        }
+    }
+    pub fn is_code(&self) -> bool {
+        self.characteristics & IMAGE_SCN_CNT_CODE != 0
+    }
+    pub fn is_initialized_data(&self) -> bool {
+        self.characteristics & IMAGE_SCN_CNT_INITIALIZED_DATA != 0
+    }
+    pub fn is_uninitialized_data(&self) -> bool {
+        self.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0
+    }
+    pub fn is_readable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_READ != 0
+    }
+    pub fn is_writable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_WRITE != 0
+    }
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_EXECUTE != 0
+    }
+    pub fn is_discardable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_DISCARDABLE != 0
+    }
+    pub fn is_shared(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_SHARED != 0
+    }
+    /// Sets the (at most 8 byte) short name of this section
+    pub fn set_name(&mut self, name: &str) -> error::Result<()> {
+        if name.len() > 8 {
+            return Err(error::Error::Malformed(format!("Section name {:?} is longer than 8 bytes", name)));
+        }
+        self.name = [0; 8];
+        self.name[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_names() {
+        let strings = b"\x0c\x00\x00\x00.debug_info\x00";
+        let strtab = strtab::Strtab::parse(strings, 0, strings.len(), 0x0).unwrap();
+        let mut section = SectionTable::default();
+        section.name = *b"/4\0\0\0\0\0\0";
+        assert_eq!(section.name_offset().unwrap(), Some(4));
+        assert_eq!(section.full_name(&strtab).unwrap(), ".debug_info");
+        section.name = *b"//AAAAAE";
+        assert_eq!(section.name_offset().unwrap(), Some(4));
+        section.set_name(".text").unwrap();
+        assert_eq!(section.full_name(&strtab).unwrap(), ".text");
+    }
+
+    #[test]
+    fn characteristics() {
+        let mut section = SectionTable::default();
+        section.characteristics = IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ | IMAGE_SCN_ALIGN_16BYTES;
+        assert!(section.is_code() && section.is_executable() && section.is_readable());
+        assert!(!section.is_writable());
+        assert_eq!(section.alignment(), Some(16));
+    }
 }
@@ -0,0 +1,205 @@
+use scroll::{self, Pread};
+use error;
+
+use pe::section_table;
+use pe::utils;
+use pe::data_directories;
+
+/// The 32-bit TLS directory, `IMAGE_TLS_DIRECTORY32`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageTlsDirectory32 {
+    pub start_address_of_raw_data: u32,
+    pub end_address_of_raw_data: u32,
+    pub address_of_index: u32,
+    pub address_of_callbacks: u32,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+}
+
+pub const SIZEOF_IMAGE_TLS_DIRECTORY_32: usize = 24;
+
+/// The 64-bit TLS directory, `IMAGE_TLS_DIRECTORY64`
+#[repr(C)]
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+#[derive(Pread, Pwrite, SizeWith)]
+pub struct ImageTlsDirectory64 {
+    pub start_address_of_raw_data: u64,
+    pub end_address_of_raw_data: u64,
+    pub address_of_index: u64,
+    pub address_of_callbacks: u64,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+}
+
+pub const SIZEOF_IMAGE_TLS_DIRECTORY_64: usize = 40;
+
+/// Unified 32/64-bit TLS directory; all addresses are virtual addresses, i.e., they include the image base
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct ImageTlsDirectory {
+    pub start_address_of_raw_data: u64,
+    pub end_address_of_raw_data: u64,
+    pub address_of_index: u64,
+    pub address_of_callbacks: u64,
+    pub size_of_zero_fill: u32,
+    pub characteristics: u32,
+}
+
+impl From<ImageTlsDirectory32> for ImageTlsDirectory {
+    fn from(tls: ImageTlsDirectory32) -> Self {
+        ImageTlsDirectory {
+            start_address_of_raw_data: tls.start_address_of_raw_data as u64,
+            end_address_of_raw_data: tls.end_address_of_raw_data as u64,
+            address_of_index: tls.address_of_index as u64,
+            address_of_callbacks: tls.address_of_callbacks as u64,
+            size_of_zero_fill: tls.size_of_zero_fill,
+            characteristics: tls.characteristics,
+        }
+    }
+}
+
+impl From<ImageTlsDirectory64> for ImageTlsDirectory {
+    fn from(tls: ImageTlsDirectory64) -> Self {
+        ImageTlsDirectory {
+            start_address_of_raw_data: tls.start_address_of_raw_data,
+            end_address_of_raw_data: tls.end_address_of_raw_data,
+            address_of_index: tls.address_of_index,
+            address_of_callbacks: tls.address_of_callbacks,
+            size_of_zero_fill: tls.size_of_zero_fill,
+            characteristics: tls.characteristics,
+        }
+    }
+}
+
+/// Upper bound on the number of TLS callbacks we are willing to read before declaring the array malformed
+const MAX_TLS_CALLBACKS: usize = 0x1000;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The TLS directory of this binary, along with its template data and resolved callbacks
+pub struct TlsData<'a> {
+    pub image_tls_directory: ImageTlsDirectory,
+    /// The TLS template which initializes each thread's TLS block, if it is mapped in the file
+    pub raw_data: Option<&'a [u8]>,
+    /// The virtual address of every TLS callback, in the order the loader calls them; empty if the array cannot be read
+    pub callbacks: Vec<u64>,
+}
+
+impl<'a> TlsData<'a> {
+    pub fn parse(bytes: &'a [u8], image_base: u64, is_64: bool, dd: &data_directories::DataDirectory, sections: &[section_table::SectionTable]) -> error::Result<Self> {
+        let rva = dd.virtual_address as usize;
+        let offset = utils::find_offset(rva, sections).ok_or(error::Error::Malformed(format!("Cannot map ImageTlsDirectory rva {:#x} into offset", rva)))?;
+        let image_tls_directory: ImageTlsDirectory = if is_64 {
+            bytes.pread_with::<ImageTlsDirectory64>(offset, scroll::LE)?.into()
+        } else {
+            bytes.pread_with::<ImageTlsDirectory32>(offset, scroll::LE)?.into()
+        };
+        debug!("{:#?}", image_tls_directory);
+
+        // the template and callbacks are often garbage in packed binaries, so ones which cannot be read are left out
+        let raw_data = {
+            let start = image_tls_directory.start_address_of_raw_data;
+            let end = image_tls_directory.end_address_of_raw_data;
+            if start == 0 || end <= start {
+                None
+            } else {
+                utils::find_offset_va(start, image_base, sections).and_then(|offset| {
+                    let size = (end - start) as usize;
+                    offset.checked_add(size).and_then(|end| bytes.get(offset..end))
+                })
+            }
+        };
+
+        let address_of_callbacks = image_tls_directory.address_of_callbacks;
+        let callbacks = if address_of_callbacks != 0 {
+            parse_callbacks(bytes, address_of_callbacks, image_base, is_64, sections).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        Ok(TlsData {
+            image_tls_directory: image_tls_directory,
+            raw_data: raw_data,
+            callbacks: callbacks,
+        })
+    }
+}
+
+/// Reads the null terminated array of TLS callbacks at the virtual address `address_of_callbacks`
+fn parse_callbacks(bytes: &[u8], address_of_callbacks: u64, image_base: u64, is_64: bool, sections: &[section_table::SectionTable]) -> error::Result<Vec<u64>> {
+    let offset = &mut utils::find_offset_va(address_of_callbacks, image_base, sections).ok_or(error::Error::Malformed(format!("Cannot map TLS callbacks address {:#x} into offset", address_of_callbacks)))?;
+    let mut callbacks = Vec::new();
+    loop {
+        let callback = if is_64 {
+            bytes.gread_with::<u64>(offset, scroll::LE)?
+        } else {
+            bytes.gread_with::<u32>(offset, scroll::LE)? as u64
+        };
+        if callback == 0 { break }
+        if callbacks.len() >= MAX_TLS_CALLBACKS {
+            return Err(error::Error::Malformed(format!("TLS callback array at {:#x} is not terminated after {} entries", address_of_callbacks, MAX_TLS_CALLBACKS)));
+        }
+        debug!("tls callback {:#x}", callback);
+        callbacks.push(callback);
+    }
+    Ok(callbacks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pe::tests::directory_section;
+
+    #[test]
+    fn sizeof_tls_directories() {
+        assert_eq!(::std::mem::size_of::<ImageTlsDirectory32>(), SIZEOF_IMAGE_TLS_DIRECTORY_32);
+        assert_eq!(::std::mem::size_of::<ImageTlsDirectory64>(), SIZEOF_IMAGE_TLS_DIRECTORY_64);
+    }
+
+    #[test]
+    fn parse_tls_callbacks() {
+        let sections = [directory_section(0x100)];
+        let image_base = 0x400000u64;
+        let mut bytes = vec![0u8; 0x100];
+        {
+            use scroll::Pwrite;
+            let tls = ImageTlsDirectory32 {
+                start_address_of_raw_data: 0x401040,
+                end_address_of_raw_data: 0x401048,
+                address_of_index: 0x401050,
+                address_of_callbacks: 0x401060,
+                size_of_zero_fill: 0,
+                characteristics: 0,
+            };
+            bytes.pwrite_with(tls, 0, scroll::LE).unwrap();
+            bytes.pwrite_with(0x401100u32, 0x60, scroll::LE).unwrap();
+            bytes.pwrite_with(0x401200u32, 0x64, scroll::LE).unwrap();
+        }
+        let dd = data_directories::DataDirectory { virtual_address: 0x1000, size: SIZEOF_IMAGE_TLS_DIRECTORY_32 as u32 };
+        let tls = TlsData::parse(&bytes, image_base, false, &dd, &sections).unwrap();
+        assert_eq!(tls.callbacks, vec![0x401100, 0x401200]);
+        assert_eq!(tls.raw_data.map(|data| data.len()), Some(8));
+    }
+
+    #[test]
+    fn parse_tls_with_bad_pointers() {
+        let sections = [directory_section(0x100)];
+        let mut bytes = vec![0u8; 0x100];
+        {
+            use scroll::Pwrite;
+            let tls = ImageTlsDirectory32 {
+                start_address_of_raw_data: 0x4010f0,
+                end_address_of_raw_data: 0x402000,
+                address_of_index: 0x401050,
+                address_of_callbacks: 0xdead0000,
+                size_of_zero_fill: 0,
+                characteristics: 0,
+            };
+            bytes.pwrite_with(tls, 0, scroll::LE).unwrap();
+        }
+        let dd = data_directories::DataDirectory { virtual_address: 0x1000, size: SIZEOF_IMAGE_TLS_DIRECTORY_32 as u32 };
+        let tls = TlsData::parse(&bytes, 0x400000, false, &dd, &sections).unwrap();
+        assert_eq!(tls.raw_data, None);
+        assert!(tls.callbacks.is_empty());
+    }
+}
@@ -41,3 +41,9 @@ pub fn try_name<'a>(bytes: &'a [u8], rva: usize, sections: &[section_table::Sect
         }
     }
 }
+
+/// Maps the virtual address `va`, i.e., an RVA biased by `image_base`, into a file offset
+pub fn find_offset_va (va: u64, image_base: u64, sections: &[section_table::SectionTable]) -> Option<usize> {
+    if va < image_base { return None }
+    find_offset((va - image_base) as usize, sections)
+}
@@ -0,0 +1,523 @@
+//! Modifying a parsed PE binary and serializing it back into a valid image
+//!
+//! A [`Writer`](struct.Writer.html) owns a copy of every section's raw data, so sections can be added or resized, import
+//! descriptors added and data directories repointed before [`Writer::write`](struct.Writer.html#method.write) lays the
+//! image out again with the binary's file and section alignment, recomputing `SizeOfImage`, `SizeOfHeaders` and `CheckSum`.
+//! File offsets in the headers, the certificate table directory and the debug directory entries follow the data they point at.
+//!
+//! Attribute certificates are carried over, but an Authenticode signature will no longer match the modified image.
+
+use scroll::{self, Pread, Pwrite};
+use error;
+
+use pe::PE;
+use pe::header;
+use pe::optional_header;
+use pe::section_table;
+use pe::data_directories;
+use pe::import;
+use pe::certificate;
+use pe::debug;
+
+const IMPORT_TABLE: usize = 1;
+const CERTIFICATE_TABLE: usize = 4;
+const DEBUG_TABLE: usize = 6;
+const BOUND_IMPORT_TABLE: usize = 11;
+const IMPORT_ADDRESS_TABLE: usize = 12;
+const NUM_DATA_DIRECTORIES: usize = 16;
+
+/// The name of the section [`Writer::add_import`](struct.Writer.html#method.add_import) creates
+pub const IMPORT_SECTION_NAME: &'static str = ".idata2";
+
+/// The offset of `pointer_to_raw_data` in an `ImageDebugDirectory`
+const DEBUG_POINTER_TO_RAW_DATA_OFFSET: usize = 24;
+
+/// Rounds `value` up to the next multiple of `alignment`; an `alignment` of 0 leaves `value` unchanged
+pub fn align_up(value: usize, alignment: usize) -> usize {
+    if alignment == 0 { return value }
+    (value + alignment - 1) / alignment * alignment
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// A section header along with an owned copy of its raw data
+pub struct Section {
+    pub table: section_table::SectionTable,
+    /// The section's initialized data, padded to the file alignment when written; the loader zero fills the rest of the section up to its virtual size
+    pub data: Vec<u8>,
+}
+
+impl Section {
+    /// The size this section occupies in memory
+    fn virtual_size(&self) -> usize {
+        if self.table.virtual_size == 0 { self.data.len() } else { self.table.virtual_size as usize }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+/// A symbol imported by [`Writer::add_import`](struct.Writer.html#method.add_import)
+pub enum ImportSymbol<'b> {
+    /// Import by name, with a hint into the exporting dll's export name table (0 if unknown)
+    Name(u16, &'b str),
+    /// Import by ordinal
+    Ordinal(u16),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+/// A mutable copy of a PE binary which can be serialized back into an image
+pub struct Writer {
+    /// The DOS header and stub, up to the PE header
+    dos_stub: Vec<u8>,
+    pub coff_header: header::CoffHeader,
+    pub standard_fields: optional_header::StandardFields,
+    /// The Windows specific fields, written back in their original 32 or 64-bit variant; `SizeOfImage`, `SizeOfHeaders`,
+    /// `CheckSum` and `NumberOfRvaAndSizes` are recomputed
+    pub windows_fields: optional_header::WindowsFields,
+    /// The original `SizeOfHeaders`, kept if the headers still fit in it
+    size_of_headers: u32,
+    /// The data directories, one per `NumberOfRvaAndSizes`
+    pub data_directories: Vec<Option<data_directories::DataDirectory>>,
+    pub sections: Vec<Section>,
+    /// Any data past the last section, e.g., the certificate table
+    pub overlay: Vec<u8>,
+    /// The file offset of `overlay` in the original binary
+    overlay_offset: usize,
+}
+
+impl Writer {
+    /// Copies `pe`, which was parsed from `bytes`, into a writer
+    pub fn new(pe: &PE, bytes: &[u8]) -> error::Result<Self> {
+        let optional_header = match pe.header.optional_header {
+            Some(optional_header) => optional_header,
+            None => return Err(error::Error::Malformed("Cannot write a PE binary without an optional header".to_string())),
+        };
+        let pe_pointer = pe.header.dos_header.pe_pointer as usize;
+        let dos_stub = bytes.pread_with::<&[u8]>(0, pe_pointer)?.to_vec();
+        let count = optional_header.windows_fields.number_of_rva_and_sizes as usize;
+        let data_directories = optional_header.data_directories.data_directories[..count].to_vec();
+
+        let size_of_headers = optional_header.windows_fields.size_of_headers;
+        let mut overlay_offset = size_of_headers as usize;
+        let mut sections = Vec::with_capacity(pe.sections.len());
+        for table in &pe.sections {
+            let start = table.pointer_to_raw_data as usize;
+            let data = if start == 0 || table.size_of_raw_data == 0 || start >= bytes.len() {
+                Vec::new()
+            } else {
+                let end = start.checked_add(table.size_of_raw_data as usize)
+                    .ok_or_else(|| error::Error::Malformed(format!("Section {} raw data at {:#x} of size {:#x} overflows", table.name().unwrap_or(""), start, table.size_of_raw_data)))?;
+                let end = ::std::cmp::min(end, bytes.len());
+                overlay_offset = ::std::cmp::max(overlay_offset, end);
+                bytes[start..end].to_vec()
+            };
+            sections.push(Section { table: *table, data: data });
+        }
+        let overlay_offset = ::std::cmp::min(overlay_offset, bytes.len());
+        Ok(Writer {
+            dos_stub: dos_stub,
+            coff_header: pe.header.coff_header,
+            standard_fields: optional_header.standard_fields,
+            windows_fields: optional_header.windows_fields,
+            size_of_headers: size_of_headers,
+            data_directories: data_directories,
+            sections: sections,
+            overlay: bytes[overlay_offset..].to_vec(),
+            overlay_offset: overlay_offset,
+        })
+    }
+    /// Returns data directory `index`, if it is present
+    pub fn data_directory(&self, index: usize) -> Option<data_directories::DataDirectory> {
+        self.data_directories.get(index).and_then(|dd| *dd)
+    }
+    /// Sets (or with `None`, clears) data directory `index`, growing `NumberOfRvaAndSizes` if necessary
+    pub fn set_data_directory(&mut self, index: usize, dd: Option<data_directories::DataDirectory>) -> error::Result<()> {
+        if index >= NUM_DATA_DIRECTORIES {
+            return Err(error::Error::Malformed(format!("Data directory index ({}) is greater than maximum number of data directories ({})", index, NUM_DATA_DIRECTORIES)));
+        }
+        if index >= self.data_directories.len() {
+            self.data_directories.resize(index + 1, None);
+        }
+        self.data_directories[index] = dd;
+        Ok(())
+    }
+    /// Returns `size` bytes of section data at `rva`, if they are backed by a section's raw data
+    pub fn read(&self, rva: u32, size: usize) -> Option<&[u8]> {
+        for section in &self.sections {
+            let start = section.table.virtual_address;
+            if rva >= start && ((rva - start) as usize) < section.virtual_size() {
+                let offset = (rva - start) as usize;
+                return if offset + size <= section.data.len() { Some(&section.data[offset..offset + size]) } else { None }
+            }
+        }
+        None
+    }
+    /// The section aligned RVA just past the last section in memory, where a new section is placed
+    fn next_virtual_address(&self) -> u32 {
+        let end = self.sections.iter().map(|section| {
+            section.table.virtual_address as usize + ::std::cmp::max(section.virtual_size(), section.data.len())
+        }).max().unwrap_or(self.size_of_headers as usize);
+        align_up(end, self.windows_fields.section_alignment as usize) as u32
+    }
+    /// Appends a new section named `name` (at most 8 bytes) holding `data` after every other section in memory, returning its index
+    pub fn add_section(&mut self, name: &str, data: Vec<u8>, characteristics: u32) -> error::Result<usize> {
+        let mut table = section_table::SectionTable::default();
+        table.set_name(name)?;
+        table.virtual_address = self.next_virtual_address();
+        table.virtual_size = data.len() as u32;
+        table.characteristics = characteristics;
+        debug!("adding section {} at {:#x} with size {:#x}", name, table.virtual_address, table.virtual_size);
+        self.sections.push(Section { table: table, data: data });
+        Ok(self.sections.len() - 1)
+    }
+    /// Sets the virtual size of section `index` to `size`, discarding any raw data past it.
+    ///
+    /// A section cannot grow into the next section in memory, as that would require relocating the image.
+    pub fn resize_section(&mut self, index: usize, size: u32) -> error::Result<()> {
+        let start = match self.sections.get(index) {
+            Some(section) => section.table.virtual_address,
+            None => return Err(error::Error::Malformed(format!("Section index {} is out of bounds for {} sections", index, self.sections.len()))),
+        };
+        let end = start as u64 + size as u64;
+        for section in &self.sections {
+            let next = section.table.virtual_address;
+            if next > start && end > next as u64 {
+                return Err(error::Error::Malformed(format!("Resizing section {} to {:#x} overlaps section {} at {:#x}", index, size, section.table.name().unwrap_or(""), next)));
+            }
+        }
+        let section = &mut self.sections[index];
+        section.table.virtual_size = size;
+        section.data.truncate(size as usize);
+        Ok(())
+    }
+    /// Adds an import descriptor for `dll` importing `symbols`, returning the RVA of its import address table, whose `n`th
+    /// slot the loader fills with the address of `symbols[n]`.
+    ///
+    /// The existing descriptors are copied, together with the new one, into a new section and the import directory is
+    /// pointed at it. Binding is undone, as the bound import directory would otherwise be out of date. The import address
+    /// table directory is grown to cover the new import address table.
+    pub fn add_import(&mut self, dll: &str, symbols: &[ImportSymbol]) -> error::Result<u32> {
+        let mut descriptors = Vec::new();
+        if let Some(dd) = self.data_directory(IMPORT_TABLE) {
+            let mut rva = dd.virtual_address;
+            loop {
+                let mut descriptor = match self.read(rva, import::SIZEOF_IMPORT_DIRECTORY_ENTRY) {
+                    Some(bytes) => bytes.pread_with::<import::ImportDirectoryEntry>(0, scroll::LE)?,
+                    None => return Err(error::Error::Malformed(format!("Cannot read import directory entry at rva {:#x}", rva))),
+                };
+                if descriptor.is_null() { break }
+                // the loader resolves from the import lookup table when the descriptor is not bound
+                if descriptor.import_lookup_table_rva != 0 {
+                    descriptor.time_date_stamp = 0;
+                    descriptor.forwarder_chain = 0;
+                }
+                descriptors.push(descriptor);
+                rva += import::SIZEOF_IMPORT_DIRECTORY_ENTRY as u32;
+            }
+        }
+        // the import address table directory is grown to cover the new table, so check it before changing anything
+        let iat_range = match self.data_directory(IMPORT_ADDRESS_TABLE) {
+            Some(dd) if dd.virtual_address != 0 => {
+                let end = dd.virtual_address.checked_add(dd.size)
+                    .ok_or_else(|| error::Error::Malformed(format!("Import address table directory at {:#x} of size {:#x} overflows", dd.virtual_address, dd.size)))?;
+                Some((dd.virtual_address, end))
+            },
+            _ => None,
+        };
+
+        let is_64 = self.windows_fields.container.is_big();
+        let thunk_size = if is_64 { 8 } else { 4 };
+        let descriptors_size = (descriptors.len() + 2) * import::SIZEOF_IMPORT_DIRECTORY_ENTRY;
+        let thunks_size = (symbols.len() + 1) * thunk_size;
+        let ilt_offset = align_up(descriptors_size, thunk_size);
+        let iat_offset = ilt_offset + thunks_size;
+        let mut names_offset = iat_offset + thunks_size;
+        let mut size = names_offset;
+        for symbol in symbols {
+            if let ImportSymbol::Name(_, name) = *symbol {
+                size = align_up(size, 2) + 2 + name.len() + 1;
+            }
+        }
+        let dll_offset = size;
+        size += dll.len() + 1;
+
+        let va = self.next_virtual_address();
+        let mut data = vec![0u8; size];
+        for (i, descriptor) in descriptors.into_iter().enumerate() {
+            data.pwrite_with(descriptor, i * import::SIZEOF_IMPORT_DIRECTORY_ENTRY, scroll::LE)?;
+        }
+        let last = import::ImportDirectoryEntry {
+            import_lookup_table_rva: va + ilt_offset as u32,
+            time_date_stamp: 0,
+            forwarder_chain: 0,
+            name_rva: va + dll_offset as u32,
+            import_address_table_rva: va + iat_offset as u32,
+        };
+        data.pwrite_with(last, descriptors_size - 2 * import::SIZEOF_IMPORT_DIRECTORY_ENTRY, scroll::LE)?;
+        for (i, symbol) in symbols.iter().enumerate() {
+            let thunk = match *symbol {
+                ImportSymbol::Ordinal(ordinal) => {
+                    if is_64 { import::IMPORT_BY_ORDINAL_64 | ordinal as u64 } else { import::IMPORT_BY_ORDINAL_32 as u64 | ordinal as u64 }
+                },
+                ImportSymbol::Name(hint, name) => {
+                    names_offset = align_up(names_offset, 2);
+                    let rva = va as u64 + names_offset as u64;
+                    data.pwrite_with(hint, names_offset, scroll::LE)?;
+                    data[names_offset + 2..names_offset + 2 + name.len()].copy_from_slice(name.as_bytes());
+                    names_offset += 2 + name.len() + 1;
+                    rva
+                },
+            };
+            for table in &[ilt_offset, iat_offset] {
+                let offset = table + i * thunk_size;
+                if is_64 {
+                    data.pwrite_with(thunk, offset, scroll::LE)?;
+                } else {
+                    data.pwrite_with(thunk as u32, offset, scroll::LE)?;
+                }
+            }
+        }
+        data[dll_offset..dll_offset + dll.len()].copy_from_slice(dll.as_bytes());
+
+        let characteristics = section_table::IMAGE_SCN_CNT_INITIALIZED_DATA | section_table::IMAGE_SCN_MEM_READ | section_table::IMAGE_SCN_MEM_WRITE;
+        self.add_section(IMPORT_SECTION_NAME, data, characteristics)?;
+        self.set_data_directory(IMPORT_TABLE, Some(data_directories::DataDirectory { virtual_address: va, size: descriptors_size as u32 }))?;
+        if self.data_directory(BOUND_IMPORT_TABLE).is_some() {
+            self.set_data_directory(BOUND_IMPORT_TABLE, None)?;
+        }
+        let iat = va + iat_offset as u32;
+        let iat_end = iat + thunks_size as u32;
+        let iat_dd = match iat_range {
+            Some((start, end)) => {
+                let start = ::std::cmp::min(start, iat);
+                let end = ::std::cmp::max(end, iat_end);
+                data_directories::DataDirectory { virtual_address: start, size: end - start }
+            },
+            None => data_directories::DataDirectory { virtual_address: iat, size: iat_end - iat },
+        };
+        self.set_data_directory(IMPORT_ADDRESS_TABLE, Some(iat_dd))?;
+        Ok(iat)
+    }
+    /// Serializes this binary into a new image
+    pub fn write(&self) -> error::Result<Vec<u8>> {
+        let file_alignment = self.windows_fields.file_alignment as usize;
+        let section_alignment = self.windows_fields.section_alignment as usize;
+        if file_alignment == 0 || section_alignment == 0 {
+            return Err(error::Error::Malformed(format!("Cannot lay out an image with file alignment {:#x} and section alignment {:#x}", file_alignment, section_alignment)));
+        }
+        let pe_pointer = self.dos_stub.len();
+        let optional_header_offset = pe_pointer + header::SIZEOF_COFF_HEADER;
+        let fields_size = self.standard_fields.size() + self.windows_fields.size();
+        let size_of_optional_header = fields_size + self.data_directories.len() * data_directories::SIZEOF_DATA_DIRECTORY;
+        let section_table_offset = optional_header_offset + size_of_optional_header;
+        let headers_end = align_up(section_table_offset + self.sections.len() * section_table::SIZEOF_SECTION_TABLE, file_alignment);
+        let size_of_headers = ::std::cmp::max(headers_end, self.size_of_headers as usize);
+        if let Some(first) = self.sections.iter().map(|section| section.table.virtual_address as usize).min() {
+            if headers_end > first {
+                return Err(error::Error::Malformed(format!("Headers ({:#x} bytes) no longer fit before the first section at {:#x}", headers_end, first)));
+            }
+        }
+
+        // virtual layout
+        let mut by_address = self.sections.iter().collect::<Vec<_>>();
+        by_address.sort_by_key(|section| section.table.virtual_address);
+        let mut size_of_image = size_of_headers;
+        for section in by_address {
+            let start = section.table.virtual_address as usize;
+            if start < size_of_image {
+                return Err(error::Error::Malformed(format!("Section {} at {:#x} overlaps the preceding section or headers ending at {:#x}", section.table.name().unwrap_or(""), start, size_of_image)));
+            }
+            size_of_image = align_up(start + section.virtual_size(), section_alignment);
+        }
+        let size_of_image = align_up(size_of_image, section_alignment);
+
+        // file layout
+        let mut tables = Vec::with_capacity(self.sections.len());
+        let mut cursor = size_of_headers;
+        for section in &self.sections {
+            let mut table = section.table;
+            if section.data.is_empty() {
+                table.pointer_to_raw_data = 0;
+                table.size_of_raw_data = 0;
+            } else {
+                let original = table.pointer_to_raw_data as usize;
+                let start = if original != 0 && original >= cursor { original } else { align_up(cursor, file_alignment) };
+                table.pointer_to_raw_data = start as u32;
+                table.size_of_raw_data = align_up(section.data.len(), file_alignment) as u32;
+                cursor = start + table.size_of_raw_data as usize;
+            }
+            tables.push(table);
+        }
+        let overlay_start = ::std::cmp::max(cursor, self.overlay_offset);
+        let relocate = |offset: u32| -> u32 {
+            if offset as usize >= self.overlay_offset { (offset as usize - self.overlay_offset + overlay_start) as u32 } else { offset }
+        };
+        // data at a file offset moves along with the section raw data or the overlay holding it
+        let rebase = |offset: u32| -> u32 {
+            for (section, table) in self.sections.iter().zip(&tables) {
+                let original = section.table.pointer_to_raw_data;
+                if original != 0 && offset >= original && ((offset - original) as usize) < section.data.len() {
+                    return table.pointer_to_raw_data + (offset - original);
+                }
+            }
+            relocate(offset)
+        };
+
+        let mut bytes = vec![0u8; overlay_start + self.overlay.len()];
+        bytes[..pe_pointer].copy_from_slice(&self.dos_stub);
+        let mut coff_header = self.coff_header;
+        coff_header.signature = header::COFF_MAGIC;
+        coff_header.number_of_sections = self.sections.len() as u16;
+        coff_header.size_of_optional_header = size_of_optional_header as u16;
+        if coff_header.pointer_to_symbol_table != 0 {
+            coff_header.pointer_to_symbol_table = relocate(coff_header.pointer_to_symbol_table);
+        }
+        bytes.pwrite_with(coff_header, pe_pointer, scroll::LE)?;
+
+        let mut windows_fields = self.windows_fields;
+        windows_fields.size_of_image = size_of_image as u32;
+        windows_fields.size_of_headers = size_of_headers as u32;
+        windows_fields.check_sum = 0;
+        windows_fields.number_of_rva_and_sizes = self.data_directories.len() as u32;
+        let offset = &mut (pe_pointer + header::SIZEOF_COFF_HEADER);
+        bytes.gwrite_with(self.standard_fields, offset, scroll::LE)?;
+        bytes.gwrite_with(windows_fields, offset, scroll::LE)?;
+        for (i, dd) in self.data_directories.iter().enumerate() {
+            let mut dd = dd.unwrap_or_default();
+            if i == CERTIFICATE_TABLE && dd.virtual_address != 0 {
+                dd.virtual_address = relocate(dd.virtual_address);
+            }
+            bytes.gwrite_with(dd, offset, scroll::LE)?;
+        }
+        for table in &tables {
+            bytes.gwrite_with(*table, offset, scroll::LE)?;
+        }
+
+        for (section, table) in self.sections.iter().zip(&tables) {
+            let start = table.pointer_to_raw_data as usize;
+            bytes[start..start + section.data.len()].copy_from_slice(&section.data);
+        }
+        bytes[overlay_start..].copy_from_slice(&self.overlay);
+
+        // debug directory entries locate their data by file offset too
+        if let Some(dd) = self.data_directory(DEBUG_TABLE) {
+            let location = self.sections.iter().zip(&tables).find(|&(section, _)| {
+                let start = section.table.virtual_address;
+                dd.virtual_address >= start && ((dd.virtual_address - start) as usize) < section.data.len()
+            });
+            if let Some((section, table)) = location {
+                let directory = (table.pointer_to_raw_data + dd.virtual_address - section.table.virtual_address) as usize;
+                let section_end = table.pointer_to_raw_data as usize + section.data.len();
+                let count = ::std::cmp::max(dd.size as usize / debug::SIZEOF_IMAGE_DEBUG_DIRECTORY, 1);
+                for i in 0..count {
+                    let offset = directory + i * debug::SIZEOF_IMAGE_DEBUG_DIRECTORY + DEBUG_POINTER_TO_RAW_DATA_OFFSET;
+                    if offset + 4 > section_end { break }
+                    let pointer: u32 = bytes.pread_with(offset, scroll::LE)?;
+                    if pointer != 0 {
+                        bytes.pwrite_with(rebase(pointer), offset, scroll::LE)?;
+                    }
+                }
+            }
+        }
+
+        let checksum_offset = pe_pointer + header::SIZEOF_COFF_HEADER + certificate::CHECKSUM_OFFSET;
+        let checksum = certificate::compute_checksum(&bytes, checksum_offset);
+        bytes.pwrite_with(checksum, checksum_offset, scroll::LE)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pe::tests::tiny_pe;
+
+    #[test]
+    fn round_trip() {
+        let bytes = tiny_pe();
+        let pe = PE::parse(&bytes).unwrap();
+        let written = Writer::new(&pe, &bytes).unwrap().write().unwrap();
+        assert_eq!(written.len(), bytes.len());
+        let pe = PE::parse(&written).unwrap();
+        assert_eq!(pe.sections.len(), 1);
+        assert_eq!(pe.compute_checksum(&written), Some(pe.header.optional_header.unwrap().windows_fields.check_sum));
+        assert_eq!(header::machine_to_str(pe.header.coff_header.machine), "X86");
+        assert_eq!(pe.security_features(), ::pe::characteristic::SecurityFeatures::default());
+    }
+
+    #[test]
+    fn add_import() {
+        let bytes = tiny_pe();
+        let pe = PE::parse(&bytes).unwrap();
+        let mut writer = Writer::new(&pe, &bytes).unwrap();
+        let iat = writer.add_import("hook.dll", &[ImportSymbol::Name(0, "Install"), ImportSymbol::Ordinal(7)]).unwrap();
+        let written = writer.write().unwrap();
+        let pe = PE::parse(&written).unwrap();
+        assert_eq!(pe.sections.len(), 2);
+        assert_eq!(pe.sections[1].virtual_address, 0x2000);
+        assert_eq!(pe.header.optional_header.unwrap().windows_fields.size_of_image, 0x3000);
+        assert_eq!(pe.libraries, vec!["hook.dll"]);
+        assert_eq!(pe.imports.len(), 2);
+        assert_eq!(pe.imports[0].name, "Install");
+        assert_eq!(pe.imports[0].offset, iat as usize);
+        assert_eq!(pe.imports[1].ordinal, 7);
+    }
+
+    #[test]
+    fn add_import_with_overflowing_iat_directory() {
+        let bytes = tiny_pe();
+        let pe = PE::parse(&bytes).unwrap();
+        let mut writer = Writer::new(&pe, &bytes).unwrap();
+        writer.set_data_directory(IMPORT_ADDRESS_TABLE, Some(data_directories::DataDirectory { virtual_address: 0xffff_fff0, size: 0x20 })).unwrap();
+        assert!(writer.add_import("hook.dll", &[ImportSymbol::Ordinal(1)]).is_err());
+        // nothing was added
+        assert_eq!(writer.sections.len(), 1);
+    }
+
+    #[test]
+    fn rebase_debug_directory() {
+        let mut bytes = tiny_pe();
+        // grow .text to hold a debug directory, whose CodeView record is appended to the file
+        bytes.pwrite_with(0x100u32, 0x140, scroll::LE).unwrap();
+        let record = b"NB10\0\0\0\0\x78\x56\x34\x12\x01\0\0\0tiny.pdb\0";
+        let codeview = debug::ImageDebugDirectory {
+            data_type: debug::IMAGE_DEBUG_TYPE_CODEVIEW,
+            size_of_data: record.len() as u32,
+            pointer_to_raw_data: bytes.len() as u32,
+            ..Default::default()
+        };
+        bytes.pwrite_with(codeview, 0x220, scroll::LE).unwrap();
+        bytes.extend_from_slice(record);
+        let pe = PE::parse(&bytes).unwrap();
+        let mut writer = Writer::new(&pe, &bytes).unwrap();
+        writer.set_data_directory(DEBUG_TABLE, Some(data_directories::DataDirectory { virtual_address: 0x1020, size: debug::SIZEOF_IMAGE_DEBUG_DIRECTORY as u32 })).unwrap();
+        let iat = writer.add_import("hook.dll", &[ImportSymbol::Ordinal(1)]).unwrap();
+        let written = writer.write().unwrap();
+        let pe = PE::parse(&written).unwrap();
+        // the new section's raw data pushed the record back
+        let entry = &pe.debug_data.as_ref().unwrap().entries[0];
+        assert_eq!(entry.directory.pointer_to_raw_data, 0x600);
+        match entry.info {
+            debug::DebugInfo::CodeviewPDB20(info) => {
+                assert_eq!(info.signature, 0x12345678);
+                assert_eq!(info.filename, b"tiny.pdb");
+            },
+            ref info => panic!("unexpected debug info {:?}", info),
+        }
+        let iat_dd = pe.header.optional_header.unwrap().data_directories.get_import_address_table().unwrap();
+        assert_eq!(iat_dd, data_directories::DataDirectory { virtual_address: iat, size: 8 });
+    }
+
+    #[test]
+    fn resize_section_overlap() {
+        let bytes = tiny_pe();
+        let pe = PE::parse(&bytes).unwrap();
+        let mut writer = Writer::new(&pe, &bytes).unwrap();
+        writer.add_section(".hook", vec![0x90; 0x20], section_table::IMAGE_SCN_CNT_CODE).unwrap();
+        assert!(writer.resize_section(0, 0x800).is_ok());
+        assert!(writer.resize_section(0, 0x1001).is_err());
+        let written = writer.write().unwrap();
+        let pe = PE::parse(&written).unwrap();
+        assert_eq!(pe.sections[0].virtual_size, 0x800);
+        assert_eq!(pe.sections[1].name().unwrap(), ".hook");
+        assert_eq!(pe.sections[1].pointer_to_raw_data, 0x400);
+    }
+}
@@ -1,6 +1,8 @@
 extern crate goblin;
+extern crate scroll;
 
 use goblin::mach::*;
+use scroll::{Pread, Pwrite};
 
 #[test]
 fn parse_fat_header() {
@@ -122,3 +124,125 @@ fn relocations() {
     assert_eq!(reloc.is_pic(), true);
     assert_eq!(reloc.is_extern(), true);
 }
+
+#[test]
+fn version_min() {
+    use load_command::{Version, PLATFORM_MACOS};
+    let binary = MachO::parse(&DEADBEEF_MACH_64[..], 0).unwrap();
+    assert_eq!(binary.platform(), Some(PLATFORM_MACOS));
+    let version = Version { major: 10, minor: 10, patch: 0 };
+    assert_eq!(binary.min_os_version(), Some(version));
+    assert_eq!(binary.sdk_version(), Some(version));
+    assert_eq!(version.to_string(), "10.10.0");
+    assert!(binary.sdk_version().unwrap() < Version::from(0x000a0b00));
+    assert_eq!(binary.build_tools().unwrap(), vec![]);
+}
+
+#[test]
+fn indirect_symbols() {
+    let binary = MachO::parse(&DEADBEEF_MACH_64[..], 0).unwrap();
+    fn names<'a>(symbols: Vec<(&'a str, symbols::Nlist)>) -> Vec<&'a str> {
+        symbols.into_iter().map(|(name, _)| name).collect()
+    }
+    assert_eq!(names(binary.local_symbols().unwrap()), Vec::<&str>::new());
+    assert_eq!(names(binary.external_symbols().unwrap()), vec!["__mh_execute_header", "_main"]);
+    assert_eq!(names(binary.undefined_symbols().unwrap()), vec!["_printf", "dyld_stub_binder"]);
+    let indirect_symbols = binary.indirect_symbols().unwrap();
+    assert_eq!(indirect_symbols.len(), 4);
+    assert!(indirect_symbols[0].is_stub());
+    assert!(indirect_symbols[2].is_absolute());
+    assert_eq!(indirect_symbols[2].name(), None);
+    let names = binary.indirect_symbol_names().unwrap();
+    assert_eq!(names.into_iter().collect::<Vec<_>>(), vec![(0x100000f74, "_printf"), (0x100001000, "dyld_stub_binder"), (0x100001010, "_printf")]);
+}
+
+#[test]
+fn write_load_commands() {
+    use load_command::*;
+    let bytes = &DEADBEEF_MACH_64[..];
+    let binary = MachO::parse(bytes, 0).unwrap();
+    let mut writer = writer::Writer::new(&binary, bytes).unwrap();
+    writer.add_rpath("@executable_path/../Frameworks").unwrap();
+    assert!(writer.add_rpath("@executable_path/../Frameworks").is_err());
+    writer.add_dylib(LC_LOAD_WEAK_DYLIB, "/usr/lib/libz.1.dylib", 0x10000, 0x10000).unwrap();
+    assert!(writer.change_dylib("/usr/lib/libSystem.B.dylib", "/usr/lib/libSystem.dylib").unwrap());
+    assert!(writer.set_install_name("libdeadbeef.dylib").is_err());
+    assert!(!writer.remove_signature().unwrap());
+    let written = writer.write().unwrap();
+    assert_eq!(written.len(), bytes.len());
+
+    let binary = MachO::parse(&written, 0).unwrap();
+    assert_eq!(binary.header.ncmds, 18);
+    assert_eq!(binary.libs, vec!["self", "/usr/lib/libSystem.dylib", "/usr/lib/libz.1.dylib"]);
+    let rpath = binary.load_commands.iter().filter_map(|cmd| match cmd.command {
+        CommandVariant::Rpath(command) => Some(written.pread::<&str>(cmd.offset + command.path as usize).unwrap()),
+        _ => None,
+    }).collect::<Vec<_>>();
+    assert_eq!(rpath, vec!["@executable_path/../Frameworks"]);
+    assert_eq!(binary.symbols().count(), 4);
+
+    let mut writer = writer::Writer::new(&binary, &written).unwrap();
+    assert!(writer.remove_rpath("@executable_path/../Frameworks").unwrap());
+    assert!(writer.remove_dylib("/usr/lib/libz.1.dylib").unwrap());
+    assert!(writer.change_dylib("/usr/lib/libSystem.dylib", "/usr/lib/libSystem.B.dylib").unwrap());
+    assert_eq!(writer.write().unwrap(), bytes);
+}
+
+#[test]
+fn write_fat() {
+    let thin = &DEADBEEF_MACH_64[..];
+    let mut arm64 = thin.to_vec();
+    arm64.pwrite_with(cputype::CPU_TYPE_ARM64, 4, scroll::LE).unwrap();
+    let bytes = writer::fat(&[thin, &arm64]).unwrap();
+    assert!(writer::fat(&[thin, thin]).is_err());
+    match Mach::parse(&bytes).unwrap() {
+        Mach::Fat(multi) => {
+            let arches = multi.arches().unwrap();
+            assert_eq!(arches.len(), 2);
+            assert_eq!((arches[0].offset, arches[0].align), (0x1000, 12));
+            assert_eq!((arches[1].offset, arches[1].align), (0x4000, 14));
+            assert_eq!(arches[1].slice(&bytes), &arm64[..]);
+            assert_eq!(multi.get(0).unwrap().header.cputype(), cputype::CPU_TYPE_X86_64);
+        },
+        _ => panic!("expected a fat binary"),
+    }
+    let replaced = writer::replace_fat(&bytes, &arm64[..0x1000]).unwrap();
+    let multi = MultiArch::new(&replaced).unwrap();
+    assert_eq!(multi.arches().unwrap()[1].size, 0x1000);
+}
+
+#[test]
+fn function_starts_and_data_in_code() {
+    use load_command::*;
+    let bytes = &DEADBEEF_MACH_64[..];
+    let binary = MachO::parse(bytes, 0).unwrap();
+    assert_eq!(binary.function_starts().unwrap(), vec![0x100000f40]);
+    assert_eq!(binary.data_in_code().unwrap().len(), 0);
+
+    // point LC_DATA_IN_CODE at two entries, written over the LC_DYLIB_CODE_SIGN_DRS data that follows it
+    let mut bytes = bytes.to_vec();
+    let (command_offset, command) = binary.load_commands.iter().filter_map(|cmd| match cmd.command {
+        CommandVariant::DataInCode(command) => Some((cmd.offset, command)),
+        _ => None,
+    }).next().unwrap();
+    let entries = [(0xf50u32, 8u16, DICE_KIND_JUMP_TABLE32), (0xf60, 4, 0x42)];
+    for (i, &(offset, length, kind)) in entries.iter().enumerate() {
+        bytes.pwrite_with(DataInCodeEntry { offset: offset, length: length, kind: kind }, command.dataoff as usize + i * 8, scroll::LE).unwrap();
+    }
+    bytes.pwrite_with(16u32, command_offset + 12, scroll::LE).unwrap();
+    let binary = MachO::parse(&bytes, 0).unwrap();
+    let entries = binary.data_in_code().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!((entries[0].offset, entries[0].length, entries[0].kind()), (0xf50, 8, DataInCodeKind::JumpTable32));
+    assert_eq!(entries[1].kind(), DataInCodeKind::Unknown(0x42));
+}
+
+#[test]
+fn lookup_address() {
+    let binary = MachO::parse(&DEADBEEF_MACH_64, 0).unwrap();
+    assert_eq!(binary.lookup_address(0x100000f50).unwrap().map(|(name, _)| name), Some("_main"));
+    let (_, printf) = binary.symbols().find(|symbol| symbol.as_ref().unwrap().0 == "_printf").unwrap().unwrap();
+    assert!(printf.is_undefined());
+    assert_eq!(printf.library_ordinal(), 1);
+    assert!(binary.debug_map().unwrap().is_empty());
+}